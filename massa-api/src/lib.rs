@@ -11,8 +11,11 @@ use jsonrpc_http_server::{CloseHandle, ServerBuilder};
 use massa_consensus_exports::{ConsensusCommandSender, ConsensusConfig};
 use massa_execution_exports::ExecutionController;
 use massa_models::api::{
-    AddressInfo, BlockInfo, BlockSummary, EndorsementInfo, EventFilter, NodeStatus, OperationInfo,
-    ReadOnlyBytecodeExecution, ReadOnlyCall, TimeInterval,
+    AddressInfo, AsyncMessageFilter, AsyncMessagesInfo, BlockInfo, BlockSummary, DatastoreKeysInfo,
+    DatastoreKeysRequest, EndorsementInfo, EventFilter, FinalStateRoot, GasEstimation,
+    GasEstimationRequest, HistoricalLedgerEntry, HistoricalLedgerEntryRequest, LedgerEntryProof,
+    LedgerEntryProofRequest, NodeStatus, OperationInfo, ReadOnlyBytecodeExecution, ReadOnlyCall,
+    ReadOnlyExecution, TimeInterval,
 };
 use massa_models::clique::Clique;
 use massa_models::composite::PubkeySig;
@@ -21,7 +24,7 @@ use massa_models::node::NodeId;
 use massa_models::operation::OperationId;
 use massa_models::output_event::SCOutputEvent;
 use massa_models::prehash::{Map, Set};
-use massa_models::{Address, BlockId, EndorsementId, SignedOperation, Version};
+use massa_models::{Address, BlockId, EndorsementId, SignedOperation, Slot, Version};
 use massa_network_exports::{NetworkCommandSender, NetworkSettings};
 use massa_pool::PoolCommandSender;
 use massa_signature::PrivateKey;
@@ -154,6 +157,24 @@ pub trait Endpoints {
         _: Vec<ReadOnlyCall>,
     ) -> BoxFuture<Result<Vec<ExecuteReadOnlyResponse>, ApiError>>;
 
+    /// Execute a batch of read-only bytecode executions and SC function calls on the same state.
+    /// The result of each entry is returned in the same order.
+    /// The total `max_gas` of the batch can not exceed the maximal gas of a block.
+    #[rpc(name = "execute_read_only_multicall")]
+    fn execute_read_only_multicall(
+        &self,
+        _: Vec<ReadOnlyExecution>,
+    ) -> BoxFuture<Result<Vec<ExecuteReadOnlyResponse>, ApiError>>;
+
+    /// Estimate the smallest `max_gas` at which `ExecuteSC` or `CallSC` operations succeed,
+    /// by executing them in read-only mode.
+    /// The requested `max_gas` is capped to the maximal gas of a block.
+    #[rpc(name = "estimate_gas")]
+    fn estimate_gas(
+        &self,
+        _: Vec<GasEstimationRequest>,
+    ) -> BoxFuture<Result<Vec<GasEstimation>, ApiError>>;
+
     /// Remove a vector of addresses used to stake.
     /// No confirmation to expect.
     #[rpc(name = "remove_staking_addresses")]
@@ -238,11 +259,54 @@ pub trait Endpoints {
     /// * emitter address
     /// * original caller address
     /// * operation id
+    /// * asynchronous message ID and status, for the outcomes of asynchronous messages
     #[rpc(name = "get_filtered_sc_output_event")]
     fn get_filtered_sc_output_event(
         &self,
         _: EventFilter,
     ) -> BoxFuture<Result<Vec<SCOutputEvent>, ApiError>>;
+
+    /// List the datastore keys of addresses, optionally filtered by a prefix of their bytes and paginated.
+    /// The number of keys listed per address is capped by the node settings.
+    #[rpc(name = "get_datastore_keys")]
+    fn get_datastore_keys(
+        &self,
+        _: Vec<DatastoreKeysRequest>,
+    ) -> BoxFuture<Result<Vec<DatastoreKeysInfo>, ApiError>>;
+
+    /// List the pending asynchronous messages matching a filter, by decreasing priority,
+    /// in the final pool and in the pool at the output of the latest executed candidate slot.
+    /// The number of messages listed is capped by the node settings.
+    #[rpc(name = "get_async_messages")]
+    fn get_async_messages(
+        &self,
+        _: AsyncMessageFilter,
+    ) -> BoxFuture<Result<AsyncMessagesInfo, ApiError>>;
+
+    /// Get final ledger entry values (balance, bytecode and an optional datastore value)
+    /// at the output of recent final slots. The node only keeps a limited history of final slots.
+    #[rpc(name = "get_historical_ledger_entries")]
+    fn get_historical_ledger_entries(
+        &self,
+        _: Vec<HistoricalLedgerEntryRequest>,
+    ) -> BoxFuture<Result<Vec<HistoricalLedgerEntry>, ApiError>>;
+
+    /// Get final ledger entry values (balance, bytecode hash and an optional datastore value)
+    /// along with proofs of those values against the final state root,
+    /// at the output of the last final slot or of a recent final slot kept in the node history.
+    #[rpc(name = "get_ledger_entry_proofs")]
+    fn get_ledger_entry_proofs(
+        &self,
+        _: Vec<LedgerEntryProofRequest>,
+    ) -> BoxFuture<Result<Vec<LedgerEntryProof>, ApiError>>;
+
+    /// Get the root hashes of the final state tree at the output of recent final slots,
+    /// to compare the final state of nodes. The node only keeps a limited history of final slots.
+    #[rpc(name = "get_final_state_roots")]
+    fn get_final_state_roots(
+        &self,
+        _: Vec<Slot>,
+    ) -> BoxFuture<Result<Vec<FinalStateRoot>, ApiError>>;
 }
 
 fn wrong_api<T>() -> BoxFuture<Result<T, ApiError>> {
@@ -8,15 +8,18 @@ use jsonrpc_http_server::tokio::sync::mpsc;
 use massa_consensus_exports::{ConsensusCommandSender, ConsensusConfig};
 use massa_execution_exports::ExecutionController;
 use massa_models::api::{
-    AddressInfo, BlockInfo, BlockSummary, EndorsementInfo, EventFilter, NodeStatus, OperationInfo,
-    ReadOnlyBytecodeExecution, ReadOnlyCall, TimeInterval,
+    AddressInfo, AsyncMessageFilter, AsyncMessagesInfo, BlockInfo, BlockSummary, DatastoreKeysInfo,
+    DatastoreKeysRequest, EndorsementInfo, EventFilter, FinalStateRoot, GasEstimation,
+    GasEstimationRequest, HistoricalLedgerEntry, HistoricalLedgerEntryRequest, LedgerEntryProof,
+    LedgerEntryProofRequest, NodeStatus, OperationInfo, ReadOnlyBytecodeExecution, ReadOnlyCall,
+    ReadOnlyExecution, TimeInterval,
 };
 use massa_models::clique::Clique;
 use massa_models::composite::PubkeySig;
 use massa_models::execution::ExecuteReadOnlyResponse;
 use massa_models::output_event::SCOutputEvent;
 use massa_models::prehash::{Map, Set};
-use massa_models::{Address, BlockId, EndorsementId, OperationId, SignedOperation};
+use massa_models::{Address, BlockId, EndorsementId, OperationId, SignedOperation, Slot};
 use massa_network_exports::NetworkCommandSender;
 use massa_signature::PrivateKey;
 use std::net::{IpAddr, SocketAddr};
@@ -90,6 +93,20 @@ impl Endpoints for API<Private> {
         crate::wrong_api::<_>()
     }
 
+    fn execute_read_only_multicall(
+        &self,
+        _reqs: Vec<ReadOnlyExecution>,
+    ) -> BoxFuture<Result<Vec<ExecuteReadOnlyResponse>, ApiError>> {
+        crate::wrong_api::<_>()
+    }
+
+    fn estimate_gas(
+        &self,
+        _reqs: Vec<GasEstimationRequest>,
+    ) -> BoxFuture<Result<Vec<GasEstimation>, ApiError>> {
+        crate::wrong_api::<_>()
+    }
+
     fn remove_staking_addresses(&self, keys: Vec<Address>) -> BoxFuture<Result<(), ApiError>> {
         let cmd_sender = self.0.consensus_command_sender.clone();
         let closure = async move || {
@@ -173,6 +190,41 @@ impl Endpoints for API<Private> {
         crate::wrong_api::<Vec<SCOutputEvent>>()
     }
 
+    fn get_datastore_keys(
+        &self,
+        _: Vec<DatastoreKeysRequest>,
+    ) -> BoxFuture<Result<Vec<DatastoreKeysInfo>, ApiError>> {
+        crate::wrong_api::<Vec<DatastoreKeysInfo>>()
+    }
+
+    fn get_async_messages(
+        &self,
+        _: AsyncMessageFilter,
+    ) -> BoxFuture<Result<AsyncMessagesInfo, ApiError>> {
+        crate::wrong_api::<AsyncMessagesInfo>()
+    }
+
+    fn get_historical_ledger_entries(
+        &self,
+        _: Vec<HistoricalLedgerEntryRequest>,
+    ) -> BoxFuture<Result<Vec<HistoricalLedgerEntry>, ApiError>> {
+        crate::wrong_api::<Vec<HistoricalLedgerEntry>>()
+    }
+
+    fn get_ledger_entry_proofs(
+        &self,
+        _: Vec<LedgerEntryProofRequest>,
+    ) -> BoxFuture<Result<Vec<LedgerEntryProof>, ApiError>> {
+        crate::wrong_api::<Vec<LedgerEntryProof>>()
+    }
+
+    fn get_final_state_roots(
+        &self,
+        _: Vec<Slot>,
+    ) -> BoxFuture<Result<Vec<FinalStateRoot>, ApiError>> {
+        crate::wrong_api::<Vec<FinalStateRoot>>()
+    }
+
     fn node_whitelist(&self, ips: Vec<IpAddr>) -> BoxFuture<Result<(), ApiError>> {
         let network_command_sender = self.0.network_command_sender.clone();
         let closure = async move || Ok(network_command_sender.whitelist(ips).await?);
@@ -7,10 +7,16 @@ use futures::{stream::FuturesUnordered, StreamExt};
 use jsonrpc_core::BoxFuture;
 use massa_consensus_exports::{ConsensusCommandSender, ConsensusConfig};
 use massa_execution_exports::{
-    ExecutionController, ExecutionStackElement, ReadOnlyExecutionRequest, ReadOnlyExecutionTarget,
+    ExecutionController, ExecutionError, ExecutionOutput, ExecutionStackElement,
+    ReadOnlyExecutionRequest, ReadOnlyExecutionTarget,
 };
 use massa_graph::{DiscardReason, ExportBlockStatus};
-use massa_models::api::{ReadOnlyBytecodeExecution, ReadOnlyCall, SCELedgerInfo};
+use massa_models::api::{
+    AsyncMessageFilter, AsyncMessagesInfo, DatastoreKeysInfo, DatastoreKeysRequest, FinalStateRoot,
+    GasEstimation, GasEstimationRequest, HistoricalLedgerEntry, HistoricalLedgerEntryRequest,
+    LedgerEntryProof, LedgerEntryProofRequest, ReadOnlyBytecodeExecution, ReadOnlyCall,
+    ReadOnlyExecution, SCELedgerInfo,
+};
 use massa_models::execution::ReadOnlyResult;
 use massa_models::SignedOperation;
 
@@ -93,51 +99,20 @@ impl Endpoints for API<Public> {
             return Box::pin(closure());
         }
 
-        let mut res: Vec<ExecuteReadOnlyResponse> = Vec::with_capacity(reqs.len());
-        for ReadOnlyBytecodeExecution {
-            max_gas,
-            address,
-            simulated_gas_price,
-            bytecode,
-        } in reqs
-        {
-            let address = address.unwrap_or_else(|| {
-                // if no addr provided, use a random one
-                Address::from_public_key(&derive_public_key(&generate_random_private_key()))
-            });
-
-            // TODO:
-            // * set a maximum gas value for read-only executions to prevent attacks
-            // * stop mapping request and result, reuse execution's structures
-            // * remove async stuff
-
-            // translate request
-            let req = ReadOnlyExecutionRequest {
-                max_gas,
-                simulated_gas_price,
-                target: ReadOnlyExecutionTarget::BytecodeExecution(bytecode),
-                call_stack: vec![ExecutionStackElement {
-                    address,
-                    coins: Default::default(),
-                    owned_addresses: vec![address],
-                }],
-            };
-
-            // run
-            let result = self.0.execution_controller.execute_readonly_request(req);
-
-            // map result
-            let result = ExecuteReadOnlyResponse {
-                executed_at: result.as_ref().map_or_else(|_| Slot::new(0, 0), |v| v.slot),
-                result: result.as_ref().map_or_else(
-                    |err| ReadOnlyResult::Error(format!("readonly call failed: {}", err)),
-                    |_| ReadOnlyResult::Ok,
-                ),
-                output_events: result.map_or_else(|_| Default::default(), |mut v| v.events.take()),
-            };
-
-            res.push(result);
-        }
+        // TODO:
+        // * set a maximum gas value for read-only executions to prevent attacks
+        // * stop mapping request and result, reuse execution's structures
+        // * remove async stuff
+        let res: Vec<ExecuteReadOnlyResponse> = reqs
+            .into_iter()
+            .map(|req| {
+                let result = self
+                    .0
+                    .execution_controller
+                    .execute_readonly_request(bytecode_execution_request(req));
+                read_only_response(result)
+            })
+            .collect();
 
         // return result
         let closure = async move || Ok(res);
@@ -154,66 +129,96 @@ impl Endpoints for API<Public> {
             return Box::pin(closure());
         }
 
-        let mut res: Vec<ExecuteReadOnlyResponse> = Vec::with_capacity(reqs.len());
-        for ReadOnlyCall {
-            max_gas,
-            simulated_gas_price,
-            target_address,
-            target_function,
-            parameter,
-            caller_address,
-        } in reqs
-        {
-            let caller_address = caller_address.unwrap_or_else(|| {
-                // if no addr provided, use a random one
-                Address::from_public_key(&derive_public_key(&generate_random_private_key()))
-            });
+        // TODO:
+        // * set a maximum gas value for read-only executions to prevent attacks
+        // * stop mapping request and result, reuse execution's structures
+        // * remove async stuff
+        let res: Vec<ExecuteReadOnlyResponse> = reqs
+            .into_iter()
+            .map(|req| {
+                let result = self
+                    .0
+                    .execution_controller
+                    .execute_readonly_request(call_request(req));
+                read_only_response(result)
+            })
+            .collect();
 
-            // TODO:
-            // * set a maximum gas value for read-only executions to prevent attacks
-            // * stop mapping request and result, reuse execution's structures
-            // * remove async stuff
+        // return result
+        let closure = async move || Ok(res);
+        Box::pin(closure())
+    }
 
-            // translate request
-            let req = ReadOnlyExecutionRequest {
-                max_gas,
-                simulated_gas_price,
-                target: ReadOnlyExecutionTarget::FunctionCall {
-                    target_func: target_function,
-                    target_addr: target_address,
-                    parameter,
-                },
-                call_stack: vec![
-                    ExecutionStackElement {
-                        address: caller_address,
-                        coins: Default::default(),
-                        owned_addresses: vec![caller_address],
-                    },
-                    ExecutionStackElement {
-                        address: target_address,
-                        coins: Default::default(),
-                        owned_addresses: vec![target_address],
-                    },
-                ],
-            };
+    fn execute_read_only_multicall(
+        &self,
+        reqs: Vec<ReadOnlyExecution>,
+    ) -> BoxFuture<Result<Vec<ExecuteReadOnlyResponse>, ApiError>> {
+        if reqs.len() > self.0.api_settings.max_arguments as usize {
+            let closure =
+                async move || Err(ApiError::TooManyArguments("too many arguments".into()));
+            return Box::pin(closure());
+        }
 
-            // run
-            let result = self.0.execution_controller.execute_readonly_request(req);
+        // all the entries are sent as one batch, executed on the same state
+        let reqs: Vec<ReadOnlyExecutionRequest> = reqs
+            .into_iter()
+            .map(|req| match req {
+                ReadOnlyExecution::BytecodeExecution(req) => bytecode_execution_request(req),
+                ReadOnlyExecution::Call(req) => call_request(req),
+            })
+            .collect();
 
-            // map result
-            let result = ExecuteReadOnlyResponse {
-                executed_at: result.as_ref().map_or_else(|_| Slot::new(0, 0), |v| v.slot),
-                result: result.as_ref().map_or_else(
-                    |err| ReadOnlyResult::Error(format!("readonly call failed: {}", err)),
-                    |_| ReadOnlyResult::Ok,
-                ),
-                output_events: result.map_or_else(|_| Default::default(), |mut v| v.events.take()),
+        // the execution state is locked during the whole batch: bound the gas it can use
+        let max_gas_per_block = self.0.consensus_config.max_gas_per_block;
+        let total_gas = reqs
+            .iter()
+            .fold(0u64, |total, req| total.saturating_add(req.max_gas));
+        if total_gas > max_gas_per_block {
+            let closure = async move || {
+                Err(ApiError::TooManyArguments(format!(
+                    "the batch requests {} gas while at most {} is allowed",
+                    total_gas, max_gas_per_block
+                )))
             };
+            return Box::pin(closure());
+        }
+
+        let res = self
+            .0
+            .execution_controller
+            .execute_readonly_requests(reqs)
+            .map(|results| results.into_iter().map(read_only_response).collect())
+            .map_err(ApiError::from);
+
+        let closure = async move || res;
+        Box::pin(closure())
+    }
 
-            res.push(result);
+    fn estimate_gas(
+        &self,
+        reqs: Vec<GasEstimationRequest>,
+    ) -> BoxFuture<Result<Vec<GasEstimation>, ApiError>> {
+        if reqs.len() > self.0.api_settings.max_arguments as usize {
+            let closure =
+                async move || Err(ApiError::TooManyArguments("too many arguments".into()));
+            return Box::pin(closure());
         }
 
-        // return result
+        let res: Vec<GasEstimation> = reqs
+            .into_iter()
+            .map(|req| {
+                let req = match req {
+                    GasEstimationRequest::ExecuteSC(req) => bytecode_execution_request(req),
+                    GasEstimationRequest::CallSC(req) => call_request(req),
+                };
+                estimate_min_gas(
+                    &*self.0.execution_controller,
+                    req,
+                    self.0.consensus_config.max_gas_per_block,
+                )
+            })
+            .collect();
+
         let closure = async move || Ok(res);
         Box::pin(closure())
     }
@@ -305,6 +310,7 @@ impl Endpoints for API<Public> {
         let api_cfg = self.0.api_settings;
         let consensus_command_sender = self.0.consensus_command_sender.clone();
         let mut pool_command_sender = self.0.pool_command_sender.clone();
+        let execution_controller = self.0.execution_controller.clone();
         let closure = async move || {
             if ops.len() as u64 > api_cfg.max_arguments {
                 return Err(ApiError::TooManyArguments("too many arguments".into()));
@@ -333,6 +339,7 @@ impl Endpoints for API<Public> {
                         in_blocks: Vec::new(),
                         id,
                         is_final: false,
+                        receipt: None,
                     },
                 )
             }));
@@ -348,12 +355,21 @@ impl Endpoints for API<Public> {
                         .iter()
                         .any(|(_, (_, is_final))| *is_final),
                     operation: search_new.op,
+                    receipt: None,
                 };
                 res.entry(op_id)
                     .and_modify(|search_old| search_old.extend(&search_new))
                     .or_insert(search_new);
             });
 
+            // add execution receipts
+            let receipts = execution_controller.get_operation_receipts(&ops);
+            for (op_id, receipt) in ops.iter().zip(receipts) {
+                if let Some(info) = res.get_mut(op_id) {
+                    info.receipt = receipt;
+                }
+            }
+
             // return values in the right order
             Ok(ops
                 .into_iter()
@@ -716,6 +732,107 @@ impl Endpoints for API<Public> {
         Box::pin(closure())
     }
 
+    fn get_datastore_keys(
+        &self,
+        reqs: Vec<DatastoreKeysRequest>,
+    ) -> BoxFuture<Result<Vec<DatastoreKeysInfo>, ApiError>> {
+        if reqs.len() > self.0.api_settings.max_arguments as usize {
+            let closure =
+                async move || Err(ApiError::TooManyArguments("too many arguments".into()));
+            return Box::pin(closure());
+        }
+
+        let max_datastore_keys = self.0.api_settings.max_datastore_keys;
+        let res: Vec<DatastoreKeysInfo> = reqs
+            .into_iter()
+            .map(|req| {
+                let (final_keys, candidate_keys) = self
+                    .0
+                    .execution_controller
+                    .get_final_and_active_datastore_keys(
+                        &req.address,
+                        &req.prefix,
+                        req.start_after.as_ref(),
+                        req.max_count.min(max_datastore_keys) as usize,
+                    );
+                DatastoreKeysInfo {
+                    address: req.address,
+                    final_keys,
+                    candidate_keys,
+                }
+            })
+            .collect();
+
+        let closure = async move || Ok(res);
+        Box::pin(closure())
+    }
+
+    fn get_async_messages(
+        &self,
+        mut filter: AsyncMessageFilter,
+    ) -> BoxFuture<Result<AsyncMessagesInfo, ApiError>> {
+        filter.max_count = filter.max_count.min(self.0.api_settings.max_async_messages);
+        let res = self
+            .0
+            .execution_controller
+            .get_final_and_active_async_messages(filter)
+            .map(|(final_messages, candidate_messages)| AsyncMessagesInfo {
+                final_messages,
+                candidate_messages,
+            });
+
+        let closure = async move || Ok(res?);
+        Box::pin(closure())
+    }
+
+    fn get_historical_ledger_entries(
+        &self,
+        reqs: Vec<HistoricalLedgerEntryRequest>,
+    ) -> BoxFuture<Result<Vec<HistoricalLedgerEntry>, ApiError>> {
+        if reqs.len() > self.0.api_settings.max_arguments as usize {
+            let closure =
+                async move || Err(ApiError::TooManyArguments("too many arguments".into()));
+            return Box::pin(closure());
+        }
+
+        let res = self
+            .0
+            .execution_controller
+            .get_historical_ledger_entries(reqs);
+
+        let closure = async move || Ok(res?);
+        Box::pin(closure())
+    }
+
+    fn get_ledger_entry_proofs(
+        &self,
+        requests: Vec<LedgerEntryProofRequest>,
+    ) -> BoxFuture<Result<Vec<LedgerEntryProof>, ApiError>> {
+        if requests.len() > self.0.api_settings.max_arguments as usize {
+            let closure =
+                async move || Err(ApiError::TooManyArguments("too many arguments".into()));
+            return Box::pin(closure());
+        }
+
+        let res = self
+            .0
+            .execution_controller
+            .get_final_ledger_entry_proofs(requests);
+
+        let closure = async move || Ok(res?);
+        Box::pin(closure())
+    }
+
+    fn get_final_state_roots(
+        &self,
+        slots: Vec<Slot>,
+    ) -> BoxFuture<Result<Vec<FinalStateRoot>, ApiError>> {
+        let state_roots = self.0.execution_controller.get_final_state_roots(slots);
+
+        let closure = async move || Ok(state_roots);
+        Box::pin(closure())
+    }
+
     fn node_whitelist(&self, _: Vec<IpAddr>) -> BoxFuture<Result<(), ApiError>> {
         crate::wrong_api::<()>()
     }
@@ -724,3 +841,313 @@ impl Endpoints for API<Public> {
         crate::wrong_api::<()>()
     }
 }
+
+/// Translates a read-only bytecode execution request of the API into an execution request
+fn bytecode_execution_request(req: ReadOnlyBytecodeExecution) -> ReadOnlyExecutionRequest {
+    let ReadOnlyBytecodeExecution {
+        max_gas,
+        address,
+        simulated_gas_price,
+        bytecode,
+        trace,
+        overrides,
+        base_state,
+    } = req;
+    let address = address.unwrap_or_else(|| {
+        // if no addr provided, use a random one
+        Address::from_public_key(&derive_public_key(&generate_random_private_key()))
+    });
+    ReadOnlyExecutionRequest {
+        max_gas,
+        simulated_gas_price,
+        target: ReadOnlyExecutionTarget::BytecodeExecution(bytecode),
+        trace,
+        overrides,
+        base_state,
+        call_stack: vec![ExecutionStackElement {
+            address,
+            coins: Default::default(),
+            owned_addresses: vec![address],
+        }],
+    }
+}
+
+/// Translates a read-only call request of the API into an execution request
+fn call_request(req: ReadOnlyCall) -> ReadOnlyExecutionRequest {
+    let ReadOnlyCall {
+        max_gas,
+        simulated_gas_price,
+        target_address,
+        target_function,
+        parameter,
+        caller_address,
+        coins,
+        trace,
+        overrides,
+        base_state,
+    } = req;
+    let caller_address = caller_address.unwrap_or_else(|| {
+        // if no addr provided, use a random one
+        Address::from_public_key(&derive_public_key(&generate_random_private_key()))
+    });
+    ReadOnlyExecutionRequest {
+        max_gas,
+        simulated_gas_price,
+        target: ReadOnlyExecutionTarget::FunctionCall {
+            target_func: target_function,
+            target_addr: target_address,
+            parameter,
+            coins,
+        },
+        trace,
+        overrides,
+        base_state,
+        call_stack: vec![
+            ExecutionStackElement {
+                address: caller_address,
+                coins: Default::default(),
+                owned_addresses: vec![caller_address],
+            },
+            ExecutionStackElement {
+                address: target_address,
+                coins,
+                owned_addresses: vec![target_address],
+            },
+        ],
+    }
+}
+
+/// Maps the result of a read-only execution to the response of the API
+fn read_only_response(result: Result<ExecutionOutput, ExecutionError>) -> ExecuteReadOnlyResponse {
+    match result {
+        Ok(mut output) => ExecuteReadOnlyResponse {
+            executed_at: output.slot,
+            result: ReadOnlyResult::Ok,
+            output_events: output.events.take(),
+            gas_used: output.gas_used,
+            trace: output.trace,
+        },
+        Err(err) => ExecuteReadOnlyResponse {
+            executed_at: Slot::new(0, 0),
+            result: ReadOnlyResult::Error(format!("readonly call failed: {}", err)),
+            output_events: Default::default(),
+            gas_used: 0,
+            trace: match err {
+                ExecutionError::TracedRuntimeError { trace, .. } => Some(*trace),
+                _ => None,
+            },
+        },
+    }
+}
+
+/// Maximal number of dichotomy steps of a gas estimation, bounding the number of executions it runs
+const MAX_GAS_ESTIMATION_STEPS: u32 = 16;
+
+/// Searches for the smallest `max_gas` at which a read-only execution succeeds,
+/// `req.max_gas` capped to `max_gas_per_block` being the largest value tried.
+///
+/// The execution is first run with that largest value, then with the gas it used, which is usually enough.
+/// Otherwise, a sufficient value is searched by dichotomy between those two bounds,
+/// stopping after `MAX_GAS_ESTIMATION_STEPS` steps even if the smallest one is not reached yet.
+/// Each run happens at the latest executed slot, so the estimation can change if the state changes meanwhile.
+fn estimate_min_gas(
+    execution_controller: &dyn ExecutionController,
+    req: ReadOnlyExecutionRequest,
+    max_gas_per_block: u64,
+) -> GasEstimation {
+    // the repeated runs of an estimation are not worth tracing,
+    // and an operation cannot use more gas than a block
+    let req = ReadOnlyExecutionRequest {
+        trace: false,
+        max_gas: std::cmp::min(req.max_gas, max_gas_per_block),
+        ..req
+    };
+    let succeeds_with = |max_gas: u64| {
+        execution_controller
+            .execute_readonly_request(ReadOnlyExecutionRequest {
+                max_gas,
+                ..req.clone()
+            })
+            .is_ok()
+    };
+
+    let gas_used = match execution_controller.execute_readonly_request(req.clone()) {
+        Ok(output) => output.gas_used,
+        Err(err) => {
+            return GasEstimation {
+                max_gas: None,
+                error: Some(err.to_string()),
+            }
+        }
+    };
+    if succeeds_with(gas_used) {
+        return GasEstimation {
+            max_gas: Some(gas_used),
+            error: None,
+        };
+    }
+
+    // the execution fails with `low` gas and succeeds with `high` gas
+    let (mut low, mut high) = (gas_used, req.max_gas);
+    for _ in 0..MAX_GAS_ESTIMATION_STEPS {
+        if high - low <= 1 {
+            break;
+        }
+        let mid = low + (high - low) / 2;
+        if succeeds_with(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    GasEstimation {
+        max_gas: Some(high),
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use massa_execution_exports::test_exports::{
+        MockExecutionController, MockExecutionControllerMessage,
+    };
+    use massa_models::Amount;
+    use std::{sync::mpsc::Receiver, thread::JoinHandle};
+
+    /// Answers the read-only executions of the mock as if they needed `required_gas` and used `used_gas`,
+    /// until the mock is dropped.
+    /// Returns the `max_gas` of each execution, in order.
+    fn answer_executions(
+        rx: Receiver<MockExecutionControllerMessage>,
+        required_gas: u64,
+        used_gas: u64,
+    ) -> JoinHandle<Vec<u64>> {
+        std::thread::spawn(move || {
+            let mut runs = Vec::new();
+            while let Ok(msg) = rx.recv() {
+                if let MockExecutionControllerMessage::ExecuteReadonlyRequest { req, response_tx } =
+                    msg
+                {
+                    runs.push(req.max_gas);
+                    let result = if req.max_gas >= required_gas {
+                        Ok(ExecutionOutput {
+                            slot: Slot::new(1, 0),
+                            block_id: None,
+                            state_changes: Default::default(),
+                            events: Default::default(),
+                            gas_used: std::cmp::min(used_gas, req.max_gas),
+                            receipts: Vec::new(),
+                            trace: None,
+                        })
+                    } else {
+                        Err(ExecutionError::RuntimeError("not enough gas".into()))
+                    };
+                    response_tx.send(result).unwrap();
+                }
+            }
+            runs
+        })
+    }
+
+    /// Estimates the gas of a sample bytecode execution requesting `max_gas`,
+    /// answered as if it needed `required_gas` and used `used_gas`.
+    /// Returns the estimation and the `max_gas` of each execution it ran.
+    fn estimate(
+        max_gas: u64,
+        max_gas_per_block: u64,
+        required_gas: u64,
+        used_gas: u64,
+    ) -> (GasEstimation, Vec<u64>) {
+        let (controller, rx) = MockExecutionController::new_with_receiver();
+        let handle = answer_executions(rx, required_gas, used_gas);
+        let req = bytecode_execution_request(ReadOnlyBytecodeExecution {
+            max_gas,
+            simulated_gas_price: Amount::zero(),
+            bytecode: vec![1, 2, 3],
+            address: None,
+            trace: true,
+            overrides: Default::default(),
+            base_state: Default::default(),
+        });
+        let estimation = estimate_min_gas(&*controller, req, max_gas_per_block);
+        drop(controller);
+        (estimation, handle.join().unwrap())
+    }
+
+    #[test]
+    fn test_estimate_gas_used() {
+        let (estimation, runs) = estimate(10_000, 1_000_000, 1000, 1000);
+        assert_eq!(estimation.max_gas, Some(1000));
+        assert_eq!(estimation.error, None);
+        assert_eq!(runs, vec![10_000, 1000]);
+    }
+
+    #[test]
+    fn test_estimate_gas_dichotomy() {
+        // the execution needs more gas than it reports, for instance to refund it afterwards
+        let (estimation, runs) = estimate(10_000, 1_000_000, 1500, 1000);
+        assert_eq!(estimation.max_gas, Some(1500));
+        assert!(runs.len() <= 2 + MAX_GAS_ESTIMATION_STEPS as usize);
+    }
+
+    #[test]
+    fn test_estimate_gas_failure() {
+        let (estimation, runs) = estimate(1000, 1_000_000, 1500, 1000);
+        assert_eq!(estimation.max_gas, None);
+        assert!(estimation.error.unwrap().contains("not enough gas"));
+        assert_eq!(runs, vec![1000]);
+    }
+
+    #[test]
+    fn test_estimate_gas_capped_to_block_gas() {
+        let (estimation, runs) = estimate(u64::MAX, 1_000_000, 1000, 1000);
+        assert_eq!(estimation.max_gas, Some(1000));
+        assert_eq!(runs[0], 1_000_000);
+
+        // more gas than a block can hold is not enough
+        let (estimation, runs) = estimate(u64::MAX, 1_000_000, 2_000_000, 1000);
+        assert_eq!(estimation.max_gas, None);
+        assert_eq!(runs, vec![1_000_000]);
+    }
+
+    #[test]
+    fn test_estimate_gas_bounded_steps() {
+        // the dichotomy stops before reaching the smallest sufficient gas,
+        // but the estimation is still sufficient
+        let (estimation, runs) = estimate(u64::MAX, u64::MAX, 1_000_000_000, 1);
+        assert_eq!(runs.len(), 2 + MAX_GAS_ESTIMATION_STEPS as usize);
+        let estimated = estimation.max_gas.unwrap();
+        assert!(estimated >= 1_000_000_000);
+        assert!(estimated - 1_000_000_000 <= u64::MAX >> MAX_GAS_ESTIMATION_STEPS);
+    }
+
+    #[test]
+    fn test_call_request_coins() {
+        let target_address =
+            Address::from_public_key(&derive_public_key(&generate_random_private_key()));
+        let coins = Amount::from_raw(42);
+        let req = call_request(ReadOnlyCall {
+            max_gas: 1000,
+            simulated_gas_price: Amount::zero(),
+            target_address,
+            target_function: "main".into(),
+            parameter: String::new(),
+            caller_address: None,
+            coins,
+            trace: false,
+            overrides: Default::default(),
+            base_state: Default::default(),
+        });
+        match req.target {
+            ReadOnlyExecutionTarget::FunctionCall {
+                coins: target_coins,
+                ..
+            } => assert_eq!(target_coins, coins),
+            _ => panic!("expected a function call"),
+        }
+        let top = req.call_stack.last().unwrap();
+        assert_eq!(top.address, target_address);
+        assert_eq!(top.coins, coins);
+    }
+}
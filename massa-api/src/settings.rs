@@ -15,4 +15,8 @@ pub struct APISettings {
     pub bind_public: SocketAddr,
     /// max argument count
     pub max_arguments: u64,
+    /// max number of datastore keys listed per address in a request
+    pub max_datastore_keys: u64,
+    /// max number of asynchronous messages listed per pool in a request
+    pub max_async_messages: u64,
 }
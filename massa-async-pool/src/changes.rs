@@ -1,7 +1,13 @@
 ///! Copyright (c) 2022 MASSA LABS <info@massa.net>
 
 ///! This file provides structures representing changes to the asynchronous message pool
-use crate::message::{AsyncMessage, AsyncMessageId};
+use crate::message::{
+    async_message_id_from_bytes, async_message_id_to_bytes, AsyncMessage, AsyncMessageId,
+};
+use massa_models::{
+    u8_from_slice, DeserializeCompact, DeserializeVarInt, ModelsError, SerializeCompact,
+    SerializeVarInt,
+};
 
 /// Enum representing a value U with identifier T being added or deleted
 #[derive(Debug, Clone)]
@@ -18,6 +24,11 @@ pub enum Change<T, U> {
 pub struct AsyncPoolChanges(pub(crate) Vec<Change<AsyncMessageId, AsyncMessage>>);
 
 impl AsyncPoolChanges {
+    /// Gets the list of additions and deletions, in the order in which they are applied
+    pub fn get_changes(&self) -> &[Change<AsyncMessageId, AsyncMessage>] {
+        &self.0
+    }
+
     /// Extends self with another another `AsyncPoolChanges`.
     /// This simply appends the contents of other to self.
     /// No add/delete compensations are done.
@@ -44,3 +55,72 @@ impl AsyncPoolChanges {
         self.0.push(Change::Delete(msg_id));
     }
 }
+
+/// Allows serializing the `AsyncPoolChanges` to a compact binary representation
+impl SerializeCompact for AsyncPoolChanges {
+    fn to_bytes_compact(&self) -> Result<Vec<u8>, ModelsError> {
+        let mut res: Vec<u8> = Vec::new();
+
+        // change count
+        let change_count: u64 = self.0.len().try_into().map_err(|_| {
+            ModelsError::SerializeError("could not represent change count as u64".into())
+        })?;
+        res.extend(change_count.to_varint_bytes());
+
+        // changes
+        for change in &self.0 {
+            match change {
+                // the message ID is recomputed from the message on deserialization
+                Change::Add(_id, msg) => {
+                    res.push(0u8);
+                    res.extend(msg.to_bytes_compact()?);
+                }
+                Change::Delete(id) => {
+                    res.push(1u8);
+                    res.extend(async_message_id_to_bytes(id)?);
+                }
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Allows deserializing `AsyncPoolChanges` from their compact binary representation
+impl DeserializeCompact for AsyncPoolChanges {
+    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), ModelsError> {
+        let mut cursor = 0usize;
+
+        // change count
+        let (change_count, delta) = u64::from_varint_bytes(&buffer[cursor..])?;
+        // TODO cap change count https://github.com/massalabs/massa/issues/1200
+        cursor += delta;
+
+        // changes
+        let mut changes = Vec::with_capacity(change_count as usize);
+        for _ in 0..change_count {
+            let change_type = u8_from_slice(&buffer[cursor..])?;
+            cursor += 1;
+            let change = match change_type {
+                0u8 => {
+                    let (msg, delta) = AsyncMessage::from_bytes_compact(&buffer[cursor..])?;
+                    cursor += delta;
+                    Change::Add(msg.compute_id(), msg)
+                }
+                1u8 => {
+                    let (id, delta) = async_message_id_from_bytes(&buffer[cursor..])?;
+                    cursor += delta;
+                    Change::Delete(id)
+                }
+                _ => {
+                    return Err(ModelsError::DeserializeError(
+                        "invalid async pool change type".into(),
+                    ))
+                }
+            };
+            changes.push(change);
+        }
+
+        Ok((AsyncPoolChanges(changes), cursor))
+    }
+}
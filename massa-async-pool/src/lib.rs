@@ -38,7 +38,9 @@
 //!   * it consumes `max_gas * gas_price + coins` coins from the sender
 //!   * it generates an `AsyncMessage` and stores it in an asynchronous pool
 //!
-//! Note that `max_gas*gas_price` coins are burned when sending the message.
+//! Note that `max_gas*gas_price` coins are burned when the message is executed successfully.
+//! If the message expires, is evicted from the pool or fails to execute,
+//! `max_gas * gas_price + coins` are credited back to the sender.
 //!
 //! ## How is the `AsyncPool` handled
 //! ```md
@@ -46,11 +48,14 @@
 //!
 //! * when an AsyncMessage is added to the AsyncPool:
 //!   * if the AsyncPool length has exceeded config.max_async_pool_length:
-//!     * remove the lowest-priority message and reimburse "coins" to the message sender
+//!     * remove the lowest-priority message and reimburse "coins" and "max_gas * gas_price" to the message sender
 //!
 //! * At every slot S :
-//!   * expired messages are deleted, and "coins" are credited back to the message sender
-//!   * messages that are valid at slot S (in terms of validity_start, validity end) are popped in highest-to-lowest priority order until they accumulate max_async_gas_per_slot. For each selected message M in decreasing priority order:
+//!   * expired messages are deleted, and "coins" and "max_gas * gas_price" are credited back to the message sender
+//!   * messages that are valid at slot S (in terms of validity_start, validity end) are considered by decreasing gas_price, then by decreasing priority:
+//!     * a message is selected if its max_gas fits in what remains of max_async_gas_per_slot, and of max_async_gas_per_sender for its sender
+//!     * otherwise it is skipped and stays in the pool
+//!   * for each selected message M in decreasing priority order:
 //!     * make sure that M.target_address exists and has a method called M.target_handler with the right signature, otherwise fail the execution
 //!     * credit target_address with M.coins
 //!     * run the target handler function with M.payload as parameter and the context:
@@ -58,7 +63,7 @@
 //!       * gas_price = M.gas_price
 //!       * slot = S
 //!       * call_stack = [M.target_address, M.sender_address]
-//!   * on any failure, cancel all the effects of execution and credit M.coins back to the sender
+//!   * on any failure, cancel all the effects of execution and credit M.coins and M.max_gas * M.gas_price back to the sender
 //!   * if there is a block at slot S, the execution of the block happens here
 //!
 //! ## How to receive a message (inside the smart contract)
@@ -97,9 +102,12 @@ mod message;
 mod pool;
 
 pub use bootstrap::AsyncPoolBootstrap;
-pub use changes::AsyncPoolChanges;
+pub use changes::{AsyncPoolChanges, Change};
 pub use config::AsyncPoolConfig;
-pub use message::{AsyncMessage, AsyncMessageId};
+pub use message::{
+    async_message_id_from_bytes, async_message_id_from_str, async_message_id_to_bytes,
+    async_message_id_to_string, AsyncMessage, AsyncMessageId,
+};
 pub use pool::AsyncPool;
 
 #[cfg(test)]
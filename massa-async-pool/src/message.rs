@@ -2,6 +2,7 @@
 
 //! This file defines the structure representing an asynchronous message
 
+use massa_models::api::AsyncMessageFilter;
 use massa_models::constants::ADDRESS_SIZE_BYTES;
 use massa_models::{
     array_from_slice, Address, Amount, DeserializeVarInt, ModelsError, SerializeVarInt, Slot,
@@ -14,6 +15,54 @@ use serde::{Deserialize, Serialize};
 /// `(rev(max_gas*gas_price), emission_slot, emission_index)`
 pub type AsyncMessageId = (std::cmp::Reverse<Amount>, Slot, u64);
 
+/// Serializes an `AsyncMessageId` into a compact binary representation
+pub fn async_message_id_to_bytes(id: &AsyncMessageId) -> Result<Vec<u8>, ModelsError> {
+    let (std::cmp::Reverse(score), slot, index) = id;
+    let mut res = score.to_bytes_compact()?;
+    res.extend(slot.to_bytes_compact()?);
+    res.extend(index.to_varint_bytes());
+    Ok(res)
+}
+
+/// Deserializes an `AsyncMessageId` from its compact binary representation
+///
+/// # Returns
+/// The message ID and the number of bytes read
+pub fn async_message_id_from_bytes(buffer: &[u8]) -> Result<(AsyncMessageId, usize), ModelsError> {
+    let mut cursor = 0usize;
+    let (score, delta) = Amount::from_bytes_compact(&buffer[cursor..])?;
+    cursor += delta;
+    let (slot, delta) = Slot::from_bytes_compact(&buffer[cursor..])?;
+    cursor += delta;
+    let (index, delta) = u64::from_varint_bytes(&buffer[cursor..])?;
+    cursor += delta;
+    Ok(((std::cmp::Reverse(score), slot, index), cursor))
+}
+
+/// Gets the textual representation of an `AsyncMessageId`: its compact binary representation encoded in `bs58` with checksum
+pub fn async_message_id_to_string(id: &AsyncMessageId) -> Result<String, ModelsError> {
+    Ok(bs58::encode(async_message_id_to_bytes(id)?)
+        .with_check()
+        .into_string())
+}
+
+/// Parses the textual representation of an `AsyncMessageId` (see `async_message_id_to_string`)
+pub fn async_message_id_from_str(data: &str) -> Result<AsyncMessageId, ModelsError> {
+    let bytes = bs58::decode(data)
+        .with_check(None)
+        .into_vec()
+        .map_err(|err| {
+            ModelsError::DeserializeError(format!("invalid asynchronous message ID: {}", err))
+        })?;
+    let (id, cursor) = async_message_id_from_bytes(&bytes)?;
+    if cursor != bytes.len() {
+        return Err(ModelsError::DeserializeError(
+            "invalid asynchronous message ID: trailing bytes".into(),
+        ));
+    }
+    Ok(id)
+}
+
 /// Structure defining an asynchronous smart contract message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AsyncMessage {
@@ -37,7 +86,9 @@ pub struct AsyncMessage {
     pub max_gas: u64,
 
     /// Gas price to take into account when executing the message.
-    /// `max_gas * gas_price` are burned by the sender when the message is sent.
+    /// `max_gas * gas_price` are spent by the sender when the message is sent,
+    /// and burned once the message is executed successfully.
+    /// In case of failure or discard, they are reimbursed to the sender along with `coins`.
     pub gas_price: Amount,
 
     /// Coins sent from the sender to the target address of the message.
@@ -66,6 +117,28 @@ impl AsyncMessage {
             self.emission_index,
         )
     }
+
+    /// Checks whether the message matches the criteria of a filter.
+    /// `filter.start_after` and `filter.max_count` apply to a list of messages and are not checked here.
+    pub fn matches_filter(&self, filter: &AsyncMessageFilter) -> bool {
+        filter.sender.iter().all(|addr| self.sender == *addr)
+            && filter
+                .destination
+                .iter()
+                .all(|addr| self.destination == *addr)
+            && filter
+                .handler
+                .iter()
+                .all(|handler| self.handler == *handler)
+            && filter
+                .valid_from
+                .iter()
+                .all(|slot| self.validity_end > *slot)
+            && filter
+                .valid_until
+                .iter()
+                .all(|slot| self.validity_start < *slot)
+    }
 }
 
 impl SerializeCompact for AsyncMessage {
@@ -215,3 +288,16 @@ impl DeserializeCompact for AsyncMessage {
         ))
     }
 }
+
+#[test]
+fn test_message_id_string() {
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let msg_id: AsyncMessageId = (
+        std::cmp::Reverse(Amount::from_raw(1_000_000)),
+        Slot::new(12, 1),
+        3,
+    );
+    let id_str = async_message_id_to_string(&msg_id).unwrap();
+    assert_eq!(async_message_id_from_str(&id_str).unwrap(), msg_id);
+    assert!(async_message_id_from_str(&id_str[1..]).is_err());
+}
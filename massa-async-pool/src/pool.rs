@@ -6,10 +6,12 @@ use crate::{
     bootstrap::AsyncPoolBootstrap,
     changes::{AsyncPoolChanges, Change},
     config::AsyncPoolConfig,
-    message::{AsyncMessage, AsyncMessageId},
+    message::{async_message_id_from_str, AsyncMessage, AsyncMessageId},
 };
-use massa_models::Slot;
-use std::collections::BTreeMap;
+use massa_models::{api::AsyncMessageFilter, Address, ModelsError, Slot};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound::{Excluded, Unbounded};
 
 /// Represents a pool of sorted messages in a deterministic way.
 /// The final asynchronous pool is attached to the output of the latest final slot within the context of massa-final-state.
@@ -54,6 +56,36 @@ impl AsyncPool {
         }
     }
 
+    /// Gets the messages of the pool, sorted by decreasing priority
+    pub fn get_messages(&self) -> &BTreeMap<AsyncMessageId, AsyncMessage> {
+        &self.messages
+    }
+
+    /// Lists the messages of the pool matching a filter, in decreasing priority order.
+    /// At most `filter.max_count` messages are listed.
+    ///
+    /// # arguments
+    /// * `filter`: criteria that the listed messages match
+    ///
+    /// # returns
+    /// The listed `(message_id, message)`, or an error if `filter.start_after` is not a valid message ID
+    pub fn get_filtered_messages(
+        &self,
+        filter: &AsyncMessageFilter,
+    ) -> Result<Vec<(AsyncMessageId, AsyncMessage)>, ModelsError> {
+        let start = match &filter.start_after {
+            Some(msg_id) => Excluded(async_message_id_from_str(msg_id)?),
+            None => Unbounded,
+        };
+        Ok(self
+            .messages
+            .range((start, Unbounded))
+            .filter(|(_id, msg)| msg.matches_filter(filter))
+            .take(filter.max_count as usize)
+            .map(|(id, msg)| (*id, msg.clone()))
+            .collect())
+    }
+
     /// Applies pre-compiled `AsyncPoolChanges` to the pool without checking for overflows.
     /// This function is used when applying pre-compiled `AsyncPoolChanges` to an `AsyncPool`.
     ///
@@ -120,9 +152,17 @@ impl AsyncPool {
     /// The returned messages are removed from the pool.
     /// This method is used at the beginning of a slot execution to list asynchronous messages to execute.
     ///
+    /// Messages valid at `slot` are considered by decreasing gas price, then by decreasing priority,
+    /// so that a message paying a lot of coins only because of its large `max_gas` does not take precedence over
+    /// many smaller messages paying more per unit of gas.
+    /// A message is selected if its `max_gas` fits both the remaining gas and the remaining gas of its sender,
+    /// otherwise it is skipped and the next ones are considered.
+    /// The selection only depends on the content of the pool, which keeps it deterministic across nodes.
+    ///
     /// # arguments
     /// * `slot`: select only messages that are valid within this slot
     /// * `available_gas`: maximum amount of available gas
+    /// * `max_gas_per_sender`: maximum amount of gas that the selected messages of a single sender can use
     ///
     /// # returns
     /// A vector of messages, sorted from the most priority to the least priority
@@ -130,21 +170,41 @@ impl AsyncPool {
         &mut self,
         slot: Slot,
         mut available_gas: u64,
+        max_gas_per_sender: u64,
     ) -> Vec<(AsyncMessageId, AsyncMessage)> {
-        // gather all selected items and remove them from self.messages
-        // iterate in decreasing priority order
-        self.messages
-            .drain_filter(|_, msg| {
-                // check available gas and validity period
-                if available_gas >= msg.max_gas
-                    && slot >= msg.validity_start
-                    && slot < msg.validity_end
-                {
-                    available_gas -= msg.max_gas;
-                    true
-                } else {
-                    false
-                }
+        // list the messages valid at this slot by decreasing gas price, ties broken by decreasing priority
+        let mut candidates: Vec<(&AsyncMessageId, &AsyncMessage)> = self
+            .messages
+            .iter()
+            .filter(|(_, msg)| slot >= msg.validity_start && slot < msg.validity_end)
+            .collect();
+        candidates.sort_by_key(|(id, msg)| (Reverse(msg.gas_price), *id));
+
+        // select the messages fitting the remaining gas, both globally and for their sender
+        let mut sender_gas: BTreeMap<Address, u64> = BTreeMap::new();
+        let mut selected: BTreeSet<AsyncMessageId> = BTreeSet::new();
+        for (id, msg) in candidates {
+            if msg.max_gas > available_gas {
+                continue;
+            }
+            let used_gas = sender_gas.entry(msg.sender).or_default();
+            if msg.max_gas > max_gas_per_sender.saturating_sub(*used_gas) {
+                continue;
+            }
+            available_gas -= msg.max_gas;
+            *used_gas += msg.max_gas;
+            selected.insert(*id);
+        }
+
+        // remove the selected messages from the pool, in decreasing priority order
+        selected
+            .into_iter()
+            .map(|id| {
+                let msg = self
+                    .messages
+                    .remove(&id)
+                    .expect("critical: selected message missing from the pool");
+                (id, msg)
             })
             .collect()
     }
@@ -177,6 +237,241 @@ fn test_take_batch() {
         );
     }
     assert_eq!(pool.messages.len(), 9);
-    pool.take_batch_to_execute(Slot::new(2, 0), 19);
+    pool.take_batch_to_execute(Slot::new(2, 0), 19, u64::MAX);
     assert_eq!(pool.messages.len(), 6);
 }
+
+#[test]
+fn test_filtered_messages() {
+    use crate::async_message_id_to_string;
+    use massa_hash::Hash;
+    use massa_models::{Address, Amount};
+
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let mut pool = AsyncPool::new(AsyncPoolConfig { max_length: 10 });
+    let sender = Address(Hash::compute_from(b"sender"));
+    let other = Address(Hash::compute_from(b"other"));
+    for i in 1..10 {
+        let msg = AsyncMessage {
+            emission_slot: Slot::new(0, 0),
+            emission_index: i,
+            sender: if i % 2 == 0 { sender } else { other },
+            destination: other,
+            handler: "function".to_string(),
+            validity_start: Slot::new(i, 0),
+            validity_end: Slot::new(i + 2, 0),
+            max_gas: i,
+            gas_price: Amount::from_raw(1),
+            coins: Amount::from_raw(0),
+            data: Vec::new(),
+        };
+        pool.messages.insert(msg.compute_id(), msg);
+    }
+
+    // messages of a sender, by decreasing priority
+    let filter = AsyncMessageFilter {
+        sender: Some(sender),
+        max_count: 10,
+        ..Default::default()
+    };
+    let gas: Vec<u64> = pool
+        .get_filtered_messages(&filter)
+        .unwrap()
+        .iter()
+        .map(|(_id, msg)| msg.max_gas)
+        .collect();
+    assert_eq!(gas, vec![8, 6, 4, 2]);
+
+    // validity range and pagination
+    let filter = AsyncMessageFilter {
+        valid_from: Some(Slot::new(5, 0)),
+        valid_until: Some(Slot::new(8, 0)),
+        max_count: 2,
+        ..Default::default()
+    };
+    let page = pool.get_filtered_messages(&filter).unwrap();
+    let gas: Vec<u64> = page.iter().map(|(_id, msg)| msg.max_gas).collect();
+    assert_eq!(gas, vec![7, 6]);
+    let filter = AsyncMessageFilter {
+        start_after: Some(async_message_id_to_string(&page[1].0).unwrap()),
+        ..filter
+    };
+    let gas: Vec<u64> = pool
+        .get_filtered_messages(&filter)
+        .unwrap()
+        .iter()
+        .map(|(_id, msg)| msg.max_gas)
+        .collect();
+    assert_eq!(gas, vec![5, 4]);
+}
+
+#[test]
+fn test_take_batch_sender_gas_cap() {
+    use massa_hash::Hash;
+    use massa_models::Amount;
+
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let mut pool = AsyncPool::new(AsyncPoolConfig { max_length: 100 });
+    let big_sender = Address(Hash::compute_from(b"big"));
+    let small_sender = Address(Hash::compute_from(b"small"));
+    let mut push = |sender: Address, index: u64, max_gas: u64| {
+        let msg = AsyncMessage {
+            emission_slot: Slot::new(0, 0),
+            emission_index: index,
+            sender,
+            destination: sender,
+            handler: "function".to_string(),
+            validity_start: Slot::new(1, 0),
+            validity_end: Slot::new(3, 0),
+            max_gas,
+            gas_price: Amount::from_raw(1),
+            coins: Amount::from_raw(0),
+            data: Vec::new(),
+        };
+        pool.messages.insert(msg.compute_id(), msg);
+    };
+    // a single sender owns the highest priority messages
+    for i in 0..5 {
+        push(big_sender, i, 30);
+    }
+    for i in 5..10 {
+        push(small_sender, i, 10);
+    }
+
+    // without a cap, the first sender uses the whole budget
+    let batch = pool
+        .clone()
+        .take_batch_to_execute(Slot::new(2, 0), 90, u64::MAX);
+    assert!(batch.iter().all(|(_, msg)| msg.sender == big_sender));
+
+    // with a cap, the remaining gas goes to the other sender
+    let batch = pool.take_batch_to_execute(Slot::new(2, 0), 90, 60);
+    let gas_of = |sender| {
+        batch
+            .iter()
+            .filter(|(_, msg)| msg.sender == sender)
+            .map(|(_, msg)| msg.max_gas)
+            .sum::<u64>()
+    };
+    assert_eq!(gas_of(big_sender), 60);
+    assert_eq!(gas_of(small_sender), 30);
+    assert_eq!(pool.messages.len(), 5);
+}
+
+#[test]
+fn test_take_batch_properties() {
+    use massa_hash::Hash;
+    use massa_models::Amount;
+    use rand::{seq::SliceRandom, Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+    let senders: Vec<Address> = (0..4u8)
+        .map(|i| Address(Hash::compute_from(&[i])))
+        .collect();
+    for _ in 0..200 {
+        // random pool
+        let uniform_price = rng.gen_bool(0.3);
+        let mut messages: Vec<AsyncMessage> = (0..rng.gen_range(0..50))
+            .map(|index| {
+                let validity_start = Slot::new(rng.gen_range(0..4), rng.gen_range(0..2));
+                let validity_end = Slot::new(rng.gen_range(0..6), rng.gen_range(0..2));
+                AsyncMessage {
+                    emission_slot: Slot::new(rng.gen_range(0..3), rng.gen_range(0..2)),
+                    emission_index: index,
+                    sender: *senders.choose(&mut rng).unwrap(),
+                    destination: senders[0],
+                    handler: "function".to_string(),
+                    validity_start,
+                    validity_end,
+                    max_gas: rng.gen_range(1..=1000),
+                    gas_price: Amount::from_raw(if uniform_price {
+                        1
+                    } else {
+                        rng.gen_range(1..=5)
+                    }),
+                    coins: Amount::from_raw(0),
+                    data: Vec::new(),
+                }
+            })
+            .collect();
+        let mut pool = AsyncPool::new(AsyncPoolConfig { max_length: 100 });
+        for msg in &messages {
+            pool.messages.insert(msg.compute_id(), msg.clone());
+        }
+        let slot = Slot::new(rng.gen_range(0..5), rng.gen_range(0..2));
+        let available_gas = rng.gen_range(0..10_000);
+        let max_gas_per_sender = if rng.gen_bool(0.5) {
+            u64::MAX
+        } else {
+            rng.gen_range(0..5_000)
+        };
+        let is_valid = |msg: &AsyncMessage| slot >= msg.validity_start && slot < msg.validity_end;
+
+        let mut remaining = pool.clone();
+        let batch = remaining.take_batch_to_execute(slot, available_gas, max_gas_per_sender);
+
+        // the pool is deterministic: the insertion order of the messages does not matter
+        messages.shuffle(&mut rng);
+        let mut shuffled = AsyncPool::new(AsyncPoolConfig { max_length: 100 });
+        for msg in &messages {
+            shuffled.messages.insert(msg.compute_id(), msg.clone());
+        }
+        let shuffled_batch =
+            shuffled.take_batch_to_execute(slot, available_gas, max_gas_per_sender);
+        assert_eq!(
+            batch.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            shuffled_batch.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+
+        // the batch is sorted by decreasing priority, and only holds valid messages taken from the pool
+        assert!(batch.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(batch.iter().all(|(id, msg)| is_valid(msg)
+            && pool.messages.contains_key(id)
+            && !remaining.messages.contains_key(id)));
+        assert_eq!(batch.len() + remaining.messages.len(), pool.messages.len());
+
+        // the gas limits are respected
+        let mut sender_gas: BTreeMap<Address, u64> = BTreeMap::new();
+        for (_, msg) in &batch {
+            *sender_gas.entry(msg.sender).or_default() += msg.max_gas;
+        }
+        let used_gas: u64 = sender_gas.values().sum();
+        assert!(used_gas <= available_gas);
+        assert!(sender_gas.values().all(|gas| *gas <= max_gas_per_sender));
+
+        // no remaining valid message fits in the gas left
+        assert!(remaining
+            .messages
+            .values()
+            .filter(|msg| is_valid(msg))
+            .all(|msg| {
+                let sender_used = sender_gas.get(&msg.sender).copied().unwrap_or_default();
+                msg.max_gas > available_gas - used_gas
+                    || msg.max_gas > max_gas_per_sender.saturating_sub(sender_used)
+            }));
+
+        // with a single gas price and no sender cap, messages are selected in priority order
+        if uniform_price && max_gas_per_sender == u64::MAX {
+            let mut gas = available_gas;
+            let expected: Vec<AsyncMessageId> = pool
+                .messages
+                .iter()
+                .filter(|(_, msg)| {
+                    if is_valid(msg) && gas >= msg.max_gas {
+                        gas -= msg.max_gas;
+                        true
+                    } else {
+                        false
+                    }
+                })
+                .map(|(id, _)| *id)
+                .collect();
+            assert_eq!(
+                batch.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+}
@@ -1,15 +1,16 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-use super::messages::BootstrapMessage;
+use super::messages::{BootstrapClientMessage, BootstrapMessage};
 use crate::error::BootstrapError;
 use crate::establisher::types::Duplex;
 use massa_hash::Hash;
 use massa_models::{
     constants::BOOTSTRAP_RANDOMNESS_SIZE_BYTES, with_serialization_context, DeserializeCompact,
-    DeserializeMinBEInt,
+    DeserializeMinBEInt, SerializeCompact, SerializeMinBEInt,
 };
 use massa_signature::{verify_signature, PublicKey, Signature, SIGNATURE_SIZE_BYTES};
 use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::convert::TryInto;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
 
@@ -104,4 +105,24 @@ impl BootstrapClientBinder {
 
         Ok(message)
     }
+
+    /// Sends a message to the bootstrap server. NOT cancel-safe
+    pub async fn send(&mut self, msg: BootstrapClientMessage) -> Result<(), BootstrapError> {
+        // serialize message
+        let msg_bytes = msg.to_bytes_compact()?;
+        let msg_len: u32 = msg_bytes.len().try_into().map_err(|e| {
+            BootstrapError::GeneralError(format!("bootstrap message too large to encode: {}", e))
+        })?;
+
+        // send message length
+        {
+            let msg_len_bytes = msg_len.to_be_bytes_min(self.max_bootstrap_message_size)?;
+            self.duplex.write_all(&msg_len_bytes).await?;
+        }
+
+        // send message
+        self.duplex.write_all(&msg_bytes).await?;
+
+        Ok(())
+    }
 }
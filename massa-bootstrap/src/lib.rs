@@ -7,6 +7,13 @@
 //! On server side, the server will query consensus for the graph and the ledger,
 //! execution for execution related data and network for the peer list.
 //!
+//! The final state is streamed before the consensus state.
+//! A client that already has a complete final state (loaded from disk for example) sends its slot to the server,
+//! which answers with the final state changes that happened since then if they are still in its history.
+//! Otherwise, the server sends the final state in parts: the ledger is sent in address-ordered chunks following
+//! a cursor kept by the client, along with the final state changes that happened since the previous part,
+//! so that the client ends up with a final state attached to a single slot.
+//!
 #![feature(async_closure)]
 #![warn(missing_docs)]
 #![warn(unused_crate_dependencies)]
@@ -18,15 +25,15 @@ use error::BootstrapError;
 pub use establisher::types::Establisher;
 use futures::{stream::FuturesUnordered, StreamExt};
 use massa_consensus_exports::ConsensusCommandSender;
-use massa_final_state::{FinalState, FinalStateBootstrap};
+use massa_final_state::FinalState;
 use massa_graph::BootstrapableGraph;
 use massa_logging::massa_trace;
-use massa_models::Version;
+use massa_models::{with_serialization_context, Address, Slot, Version};
 use massa_network_exports::{BootstrapPeers, NetworkCommandSender};
 use massa_proof_of_stake_exports::ExportProofOfStake;
 use massa_signature::{PrivateKey, PublicKey};
 use massa_time::MassaTime;
-use messages::BootstrapMessage;
+use messages::{BootstrapClientMessage, BootstrapMessage};
 use parking_lot::RwLock;
 use rand::{prelude::SliceRandom, rngs::StdRng, SeedableRng};
 use std::collections::{hash_map, HashMap};
@@ -63,9 +70,147 @@ pub struct GlobalBootstrapState {
 
     /// list of network peers
     pub peers: Option<BootstrapPeers>,
+}
+
+/// Progress of the final state bootstrap on the client side.
+/// It is kept across bootstrap attempts so that an interrupted transfer can go on with another server.
+#[derive(Debug, Clone, Copy)]
+enum FinalStateCursor {
+    /// the final state is complete and attached to the given slot
+    Complete(Slot),
+    /// the final state is being received in parts
+    Streaming {
+        /// slot of the last received part, `None` if no part was received yet
+        last_slot: Option<Slot>,
+        /// last received ledger address, `None` if no address was received yet
+        last_address: Option<Address>,
+    },
+}
+
+/// Gets the final state from a bootstrap server (internal private function).
+/// The final state and the cursor are updated as the parts are received.
+/// needs to be CANCELLABLE
+async fn get_final_state(
+    cfg: &BootstrapSettings,
+    client: &mut BootstrapClientBinder,
+    final_state: &RwLock<FinalState>,
+    cursor: &mut FinalStateCursor,
+) -> Result<(), BootstrapError> {
+    loop {
+        // ask for the changes since our slot if our final state is complete, or for the next part otherwise
+        let request = match *cursor {
+            FinalStateCursor::Complete(last_slot) => {
+                BootstrapClientMessage::AskFinalStateChanges { last_slot }
+            }
+            FinalStateCursor::Streaming {
+                last_slot,
+                last_address,
+            } => BootstrapClientMessage::AskFinalStatePart {
+                last_slot,
+                last_address,
+            },
+        };
+        // client.send() is not cancel-safe but we drop the whole client object if cancelled => it's OK
+        match tokio::time::timeout(cfg.write_timeout.into(), client.send(request)).await {
+            Err(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "final state request send timed out",
+                )
+                .into())
+            }
+            Ok(Err(e)) => return Err(e),
+            Ok(Ok(_)) => {}
+        }
+
+        // client.next() is not cancel-safe but we drop the whole client object if cancelled => it's OK
+        match tokio::time::timeout(cfg.read_timeout.into(), client.next()).await {
+            Err(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "final state bootstrap read timed out",
+                )
+                .into())
+            }
+            Ok(Err(e)) => return Err(e),
+            Ok(Ok(BootstrapMessage::FinalStateChanges {
+                changes,
+                state_root,
+            })) if matches!(*cursor, FinalStateCursor::Complete(_)) => {
+                let mut final_state = final_state.write();
+
+                // the changes must follow each other from our final slot on
+                let thread_count = with_serialization_context(|context| context.thread_count);
+                let mut slot = final_state.slot;
+                for (changes_slot, _) in &changes {
+                    slot = slot.get_next_slot(thread_count)?;
+                    if *changes_slot != slot {
+                        return Err(BootstrapError::GeneralError(format!(
+                            "final state changes at slot {} while slot {} was expected",
+                            changes_slot, slot
+                        )));
+                    }
+                }
+                for (slot, slot_changes) in changes {
+                    final_state.finalize(slot, slot_changes);
+                }
 
-    /// state of the final state
-    pub final_state: Option<FinalStateBootstrap>,
+                // check the caught up final state against the server's one
+                if final_state.get_state_root() != state_root {
+                    // our final state can not be trusted anymore: start over from the beginning
+                    *cursor = FinalStateCursor::Streaming {
+                        last_slot: None,
+                        last_address: None,
+                    };
+                    return Err(BootstrapError::GeneralError(format!(
+                        "final state root mismatch at slot {}: expected {}, got {}",
+                        final_state.slot,
+                        state_root,
+                        final_state.get_state_root()
+                    )));
+                }
+                *cursor = FinalStateCursor::Complete(final_state.slot);
+                return Ok(());
+            }
+            Ok(Ok(BootstrapMessage::FinalStatePart { part, reset })) => {
+                let mut final_state = final_state.write();
+                let last_address = match *cursor {
+                    FinalStateCursor::Streaming {
+                        last_slot: Some(_),
+                        last_address,
+                    } if !reset => last_address,
+                    _ => {
+                        // the server sends the final state from the beginning
+                        final_state.reset();
+                        None
+                    }
+                };
+                let slot = part.get_slot();
+                let is_last = part.is_last();
+                let last_address = match final_state.set_bootstrap_part(part, last_address) {
+                    Ok(last_address) => last_address,
+                    Err(err) => {
+                        // the received final state is inconsistent: start over from the beginning
+                        *cursor = FinalStateCursor::Streaming {
+                            last_slot: None,
+                            last_address: None,
+                        };
+                        return Err(err.into());
+                    }
+                };
+                debug!("received final state part at slot {}", slot);
+                if is_last {
+                    *cursor = FinalStateCursor::Complete(slot);
+                    return Ok(());
+                }
+                *cursor = FinalStateCursor::Streaming {
+                    last_slot: Some(slot),
+                    last_address,
+                };
+            }
+            Ok(Ok(msg)) => return Err(BootstrapError::UnexpectedMessage(msg)),
+        }
+    }
 }
 
 /// Gets the state from a bootstrap server (internal private function)
@@ -76,6 +221,8 @@ async fn get_state_internal(
     bootstrap_public_key: &PublicKey,
     establisher: &mut Establisher,
     our_version: Version,
+    final_state: &RwLock<FinalState>,
+    final_state_cursor: &mut FinalStateCursor,
 ) -> Result<GlobalBootstrapState, BootstrapError> {
     massa_trace!("bootstrap.lib.get_state_internal", {});
     info!("Start bootstrapping from {}", bootstrap_addr);
@@ -181,7 +328,10 @@ async fn get_state_internal(
         Ok(Ok(msg)) => return Err(BootstrapError::UnexpectedMessage(msg)),
     };
 
-    // Third, get consensus state
+    // Third, get the final state
+    get_final_state(cfg, &mut client, final_state, final_state_cursor).await?;
+
+    // Fourth, get consensus state
     // client.next() is not cancel-safe but we drop the whole client object if cancelled => it's OK
     let (pos, graph) = match tokio::time::timeout(cfg.read_timeout.into(), client.next()).await {
         Err(_) => {
@@ -196,21 +346,6 @@ async fn get_state_internal(
         Ok(Ok(msg)) => return Err(BootstrapError::UnexpectedMessage(msg)),
     };
 
-    // Fourth, get final state
-    // client.next() is not cancel-safe but we drop the whole client object if cancelled => it's OK
-    let final_state = match tokio::time::timeout(cfg.read_timeout.into(), client.next()).await {
-        Err(_) => {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::TimedOut,
-                "final state bootstrap read timed out",
-            )
-            .into())
-        }
-        Ok(Err(e)) => return Err(e),
-        Ok(Ok(BootstrapMessage::FinalState { final_state })) => final_state,
-        Ok(Ok(msg)) => return Err(BootstrapError::UnexpectedMessage(msg)),
-    };
-
     info!("Successful bootstrap");
 
     Ok(GlobalBootstrapState {
@@ -218,14 +353,17 @@ async fn get_state_internal(
         graph: Some(graph),
         compensation_millis,
         peers: Some(peers),
-        final_state: Some(final_state),
     })
 }
 
 /// Gets the state from a bootstrap server
 /// needs to be CANCELLABLE
+///
+/// `final_state` is the complete final state the node starts from (loaded from disk or initial),
+/// which is brought up to date during the bootstrap.
 pub async fn get_state(
     bootstrap_settings: &'static BootstrapSettings,
+    final_state: Arc<RwLock<FinalState>>,
     mut establisher: Establisher,
     version: Version,
     genesis_timestamp: MassaTime,
@@ -247,6 +385,7 @@ pub async fn get_state(
     }
     let mut shuffled_list = bootstrap_settings.bootstrap_list.clone();
     shuffled_list.shuffle(&mut StdRng::from_entropy());
+    let mut final_state_cursor = FinalStateCursor::Complete(final_state.read().slot);
     loop {
         for (addr, pub_key) in shuffled_list.iter() {
             if let Some(end) = end_timestamp {
@@ -254,7 +393,7 @@ pub async fn get_state(
                     panic!("This episode has come to an end, please get the latest testnet node version to continue");
                 }
             }
-            match get_state_internal(bootstrap_settings, addr, pub_key, &mut establisher, version)
+            match get_state_internal(bootstrap_settings, addr, pub_key, &mut establisher, version, &final_state, &mut final_state_cursor)
                 .await  // cancellable
             {
                 Err(e) => {
@@ -352,12 +491,7 @@ impl BootstrapServer {
         let mut listener = self.establisher.get_listener(self.bind).await?;
         let mut bootstrap_sessions = FuturesUnordered::new();
         let cache_timeout = self.bootstrap_settings.cache_duration.to_duration();
-        let mut bootstrap_data: Option<(
-            ExportProofOfStake,
-            BootstrapableGraph,
-            BootstrapPeers,
-            FinalStateBootstrap,
-        )> = None;
+        let mut bootstrap_data: Option<BootstrapPeers> = None;
         let cache_timer = sleep(cache_timeout);
         let per_ip_min_interval = self.bootstrap_settings.per_ip_min_interval.to_duration();
         tokio::pin!(cache_timer);
@@ -426,14 +560,10 @@ impl BootstrapServer {
                     if bootstrap_data.is_none() {
                         massa_trace!("bootstrap.lib.run.select.accept.cache_load.start", {});
 
-                        // Note that all requests are done simultaneously except for the consensus graph that is done after the others.
-                        // This is done to ensure that the execution bootstrap state is older than the consensus state.
-                        // If the consensus state snapshot is older than the execution state snapshot,
-                        //   the execution final ledger will be in the future after bootstrap, which causes an inconsistency.
+                        // Only the peers are cached: the final state is streamed
+                        // and the consensus state is read after it during each session (see `manage_bootstrap`).
                         let peer_boot = self.network_command_sender.get_bootstrap_peers().await?;
-                        let res_state = self.final_state.read().get_bootstrap_state();
-                        let (pos_boot, graph_boot) = self.consensus_command_sender.get_bootstrap_state().await?;
-                        bootstrap_data = Some((pos_boot, graph_boot, peer_boot, res_state));
+                        bootstrap_data = Some(peer_boot);
                         cache_timer.set(sleep(cache_timeout));
                     }
                     massa_trace!("bootstrap.lib.run.select.accept.cache_available", {});
@@ -442,9 +572,11 @@ impl BootstrapServer {
                     let private_key = self.private_key;
                     let compensation_millis = self.compensation_millis;
                     let version = self.version;
-                    let (data_pos, data_graph, data_peers, data_execution) = bootstrap_data.clone().unwrap(); // will not panic (checked above)
+                    let data_peers = bootstrap_data.clone().unwrap(); // will not panic (checked above)
+                    let final_state = self.final_state.clone();
+                    let consensus_command_sender = self.consensus_command_sender.clone();
                     bootstrap_sessions.push(async move {
-                        match manage_bootstrap(self.bootstrap_settings, dplx, data_pos, data_graph, data_peers, data_execution, private_key, compensation_millis, version).await {
+                        match manage_bootstrap(self.bootstrap_settings, dplx, data_peers, final_state, consensus_command_sender, private_key, compensation_millis, version).await {
                             Ok(_) => info!("bootstrapped peer {}", remote_addr),
                             Err(err) => debug!("bootstrap serving error for peer {}: {}", remote_addr, err),
                         }
@@ -467,10 +599,9 @@ impl BootstrapServer {
 async fn manage_bootstrap(
     bootstrap_settings: &'static BootstrapSettings,
     duplex: Duplex,
-    data_pos: ExportProofOfStake,
-    data_graph: BootstrapableGraph,
     data_peers: BootstrapPeers,
-    final_state: FinalStateBootstrap,
+    final_state: Arc<RwLock<FinalState>>,
+    consensus_command_sender: ConsensusCommandSender,
     private_key: PrivateKey,
     compensation_millis: i64,
     version: Version,
@@ -509,26 +640,106 @@ async fn manage_bootstrap(
     )
     .await?;
 
-    // Third, send consensus state
-    send_state_timeout(
-        write_timeout,
-        server.send(messages::BootstrapMessage::ConsensusState {
-            pos: data_pos,
-            graph: data_graph,
-        }),
-        "bootstrap graph send timed out",
-    )
-    .await?;
+    // Third, stream the final state until the client has a complete one
+    loop {
+        let request =
+            match tokio::time::timeout(bootstrap_settings.read_timeout.into(), server.next()).await
+            {
+                Err(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "bootstrap final state request read timed out",
+                    )
+                    .into())
+                }
+                Ok(Err(e)) => return Err(e),
+                Ok(Ok(request)) => request,
+            };
+        let msg = {
+            let state = final_state.read();
+            match request {
+                BootstrapClientMessage::AskFinalStateChanges { last_slot } => {
+                    let max_changes_count = with_serialization_context(|context| {
+                        context.max_bootstrap_final_state_changes as usize
+                    });
+                    match state.get_state_changes_part(last_slot, state.slot) {
+                        Some(changes) if changes.len() <= max_changes_count => {
+                            BootstrapMessage::FinalStateChanges {
+                                changes,
+                                state_root: state.get_state_root(),
+                            }
+                        }
+                        // the changes are not in the history anymore, or too many to be sent at once:
+                        // send the whole final state
+                        _ => get_final_state_part_message(
+                            &state,
+                            None,
+                            None,
+                            bootstrap_settings.max_ledger_part_size,
+                        ),
+                    }
+                }
+                BootstrapClientMessage::AskFinalStatePart {
+                    last_slot,
+                    last_address,
+                } => get_final_state_part_message(
+                    &state,
+                    last_slot,
+                    last_address,
+                    bootstrap_settings.max_ledger_part_size,
+                ),
+            }
+        };
+        let finished = match &msg {
+            BootstrapMessage::FinalStatePart { part, .. } => part.is_last(),
+            _ => true,
+        };
+        send_state_timeout(
+            write_timeout,
+            server.send(msg),
+            "bootstrap final state send timed out",
+        )
+        .await?;
+        if finished {
+            break;
+        }
+    }
 
-    // Fourth, send final state
+    // Fourth, send consensus state.
+    // It is read once the final state was sent to ensure that the final state is older than the consensus state:
+    // otherwise the final ledger of the client would be in the future of its consensus graph, which causes an inconsistency.
+    let (pos, graph) = consensus_command_sender.get_bootstrap_state().await?;
     send_state_timeout(
         write_timeout,
-        server.send(messages::BootstrapMessage::FinalState { final_state }),
-        "bootstrap ledger state send timed out",
+        server.send(messages::BootstrapMessage::ConsensusState { pos, graph }),
+        "bootstrap graph send timed out",
     )
     .await
 }
 
+/// Builds the message containing the next final state part to send to a client.
+/// If the changes history does not go back to the slot of the last part received by the client anymore,
+/// the final state is sent again from the beginning.
+fn get_final_state_part_message(
+    final_state: &FinalState,
+    last_slot: Option<Slot>,
+    last_address: Option<Address>,
+    max_ledger_part_size: usize,
+) -> BootstrapMessage {
+    match final_state.get_bootstrap_part(last_slot, last_address.as_ref(), max_ledger_part_size) {
+        Some(part) => BootstrapMessage::FinalStatePart {
+            part,
+            reset: last_slot.is_none(),
+        },
+        None => BootstrapMessage::FinalStatePart {
+            part: final_state
+                .get_bootstrap_part(None, None, max_ledger_part_size)
+                .expect("a final state part can always be built from the beginning"),
+            reset: true,
+        },
+    }
+}
+
 /// Tooling, Send a future with a timeout, print error if timeout reached
 async fn send_state_timeout(
     duration: std::time::Duration,
@@ -1,19 +1,21 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-use massa_final_state::FinalStateBootstrap;
+use massa_final_state::{FinalStatePart, StateChanges};
 use massa_graph::BootstrapableGraph;
+use massa_hash::{Hash, HASH_SIZE_BYTES};
 use massa_models::{
-    DeserializeCompact, DeserializeVarInt, ModelsError, SerializeCompact, SerializeVarInt, Version,
+    array_from_slice, constants::ADDRESS_SIZE_BYTES, u8_from_slice, with_serialization_context,
+    Address, DeserializeCompact, DeserializeVarInt, ModelsError, SerializeCompact, SerializeVarInt,
+    Slot, Version,
 };
 use massa_network_exports::BootstrapPeers;
 use massa_proof_of_stake_exports::ExportProofOfStake;
 use massa_time::MassaTime;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 
 /// Messages used during bootstrap
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug)]
 pub enum BootstrapMessage {
     /// Sync clocks
     BootstrapTime {
@@ -33,10 +35,19 @@ pub enum BootstrapMessage {
         /// block graph
         graph: BootstrapableGraph,
     },
-    /// Final execution state
-    FinalState {
-        /// final execution state bootstrap
-        final_state: FinalStateBootstrap,
+    /// Next part of the final execution state
+    FinalStatePart {
+        /// final execution state part
+        part: FinalStatePart,
+        /// true if the client needs to discard the parts it received so far because the part starts over
+        reset: bool,
+    },
+    /// Final state changes that happened after the final slot of the client
+    FinalStateChanges {
+        /// list of `(slot, changes)` sorted by slot
+        changes: Vec<(Slot, StateChanges)>,
+        /// root hash of the server's final state once the changes are applied
+        state_root: Hash,
     },
 }
 
@@ -46,7 +57,8 @@ enum MessageTypeId {
     BootstrapTime = 0u32,
     Peers = 1u32,
     ConsensusState = 2u32,
-    FinalState = 3u32,
+    FinalStatePart = 3u32,
+    FinalStateChanges = 4u32,
 }
 
 impl SerializeCompact for BootstrapMessage {
@@ -70,9 +82,33 @@ impl SerializeCompact for BootstrapMessage {
                 res.extend(&pos.to_bytes_compact()?);
                 res.extend(&graph.to_bytes_compact()?);
             }
-            BootstrapMessage::FinalState { final_state } => {
-                res.extend(u32::from(MessageTypeId::FinalState).to_varint_bytes());
-                res.extend(&final_state.to_bytes_compact()?);
+            BootstrapMessage::FinalStatePart { part, reset } => {
+                res.extend(u32::from(MessageTypeId::FinalStatePart).to_varint_bytes());
+                res.extend(&part.to_bytes_compact()?);
+                res.push(u8::from(*reset));
+            }
+            BootstrapMessage::FinalStateChanges {
+                changes,
+                state_root,
+            } => {
+                res.extend(u32::from(MessageTypeId::FinalStateChanges).to_varint_bytes());
+                let max_changes_count =
+                    with_serialization_context(|context| context.max_bootstrap_final_state_changes);
+                let changes_count: u32 = changes.len().try_into().map_err(|_| {
+                    ModelsError::SerializeError("could not represent changes count as u32".into())
+                })?;
+                if changes_count > max_changes_count {
+                    return Err(ModelsError::SerializeError(format!(
+                        "too many slots in final state changes: {}",
+                        changes_count
+                    )));
+                }
+                res.extend(changes_count.to_varint_bytes());
+                for (slot, slot_changes) in changes {
+                    res.extend(&slot.to_bytes_compact()?);
+                    res.extend(&slot_changes.to_bytes_compact()?);
+                }
+                res.extend(state_root.to_bytes());
             }
         }
         Ok(res)
@@ -116,12 +152,164 @@ impl DeserializeCompact for BootstrapMessage {
 
                 BootstrapMessage::ConsensusState { pos, graph }
             }
-            MessageTypeId::FinalState => {
-                let (final_state, delta) =
-                    FinalStateBootstrap::from_bytes_compact(&buffer[cursor..])?;
+            MessageTypeId::FinalStatePart => {
+                let (part, delta) = FinalStatePart::from_bytes_compact(&buffer[cursor..])?;
+                cursor += delta;
+                let reset = match u8_from_slice(&buffer[cursor..])? {
+                    0u8 => false,
+                    1u8 => true,
+                    _ => return Err(ModelsError::DeserializeError("invalid reset flag".into())),
+                };
+                cursor += 1;
+
+                BootstrapMessage::FinalStatePart { part, reset }
+            }
+            MessageTypeId::FinalStateChanges => {
+                let max_changes_count =
+                    with_serialization_context(|context| context.max_bootstrap_final_state_changes);
+                let (changes_count, delta) = u32::from_varint_bytes(&buffer[cursor..])?;
+                if changes_count > max_changes_count {
+                    return Err(ModelsError::DeserializeError(format!(
+                        "too many slots in final state changes: {}",
+                        changes_count
+                    )));
+                }
                 cursor += delta;
+                // the count comes from the remote peer: grow the list as the changes are read
+                let mut changes = Vec::new();
+                for _ in 0..changes_count {
+                    let (slot, delta) = Slot::from_bytes_compact(&buffer[cursor..])?;
+                    cursor += delta;
+                    let (slot_changes, delta) =
+                        StateChanges::from_bytes_compact(&buffer[cursor..])?;
+                    cursor += delta;
+                    changes.push((slot, slot_changes));
+                }
+                let state_root = Hash::from_bytes(&array_from_slice(&buffer[cursor..])?)?;
+                cursor += HASH_SIZE_BYTES;
 
-                BootstrapMessage::FinalState { final_state }
+                BootstrapMessage::FinalStateChanges {
+                    changes,
+                    state_root,
+                }
+            }
+        };
+        Ok((res, cursor))
+    }
+}
+
+/// Messages sent by the bootstrap client to the server
+#[derive(Debug)]
+pub enum BootstrapClientMessage {
+    /// Ask for the final state changes that happened after the slot of a complete final state the client already has
+    /// (for example loaded from disk). If the server does not have them anymore, it starts sending the whole final state.
+    AskFinalStateChanges {
+        /// final slot of the state the client has
+        last_slot: Slot,
+    },
+    /// Ask for the next part of the final state
+    AskFinalStatePart {
+        /// slot of the last part received, `None` if no part was received yet
+        last_slot: Option<Slot>,
+        /// last ledger address received, `None` if no address was received yet
+        last_address: Option<Address>,
+    },
+}
+
+#[derive(IntoPrimitive, Debug, Eq, PartialEq, TryFromPrimitive)]
+#[repr(u32)]
+enum ClientMessageTypeId {
+    AskFinalStateChanges = 0u32,
+    AskFinalStatePart = 1u32,
+}
+
+impl SerializeCompact for BootstrapClientMessage {
+    fn to_bytes_compact(&self) -> Result<Vec<u8>, ModelsError> {
+        let mut res: Vec<u8> = Vec::new();
+        match self {
+            BootstrapClientMessage::AskFinalStateChanges { last_slot } => {
+                res.extend(u32::from(ClientMessageTypeId::AskFinalStateChanges).to_varint_bytes());
+                res.extend(&last_slot.to_bytes_compact()?);
+            }
+            BootstrapClientMessage::AskFinalStatePart {
+                last_slot,
+                last_address,
+            } => {
+                res.extend(u32::from(ClientMessageTypeId::AskFinalStatePart).to_varint_bytes());
+                match last_slot {
+                    Some(slot) => {
+                        res.push(1u8);
+                        res.extend(&slot.to_bytes_compact()?);
+                    }
+                    None => res.push(0u8),
+                }
+                match last_address {
+                    Some(addr) => {
+                        res.push(1u8);
+                        res.extend(addr.to_bytes());
+                    }
+                    None => res.push(0u8),
+                }
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl DeserializeCompact for BootstrapClientMessage {
+    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), ModelsError> {
+        let mut cursor = 0usize;
+
+        let (type_id_raw, delta) = u32::from_varint_bytes(&buffer[cursor..])?;
+        cursor += delta;
+
+        let type_id: ClientMessageTypeId = type_id_raw
+            .try_into()
+            .map_err(|_| ModelsError::DeserializeError("invalid message type ID".into()))?;
+
+        let res = match type_id {
+            ClientMessageTypeId::AskFinalStateChanges => {
+                let (last_slot, delta) = Slot::from_bytes_compact(&buffer[cursor..])?;
+                cursor += delta;
+
+                BootstrapClientMessage::AskFinalStateChanges { last_slot }
+            }
+            ClientMessageTypeId::AskFinalStatePart => {
+                let has_slot = u8_from_slice(&buffer[cursor..])?;
+                cursor += 1;
+                let last_slot = match has_slot {
+                    0u8 => None,
+                    1u8 => {
+                        let (slot, delta) = Slot::from_bytes_compact(&buffer[cursor..])?;
+                        cursor += delta;
+                        Some(slot)
+                    }
+                    _ => {
+                        return Err(ModelsError::DeserializeError(
+                            "invalid last slot flag".into(),
+                        ))
+                    }
+                };
+                let has_address = u8_from_slice(&buffer[cursor..])?;
+                cursor += 1;
+                let last_address = match has_address {
+                    0u8 => None,
+                    1u8 => {
+                        let addr = Address::from_bytes(&array_from_slice(&buffer[cursor..])?)?;
+                        cursor += ADDRESS_SIZE_BYTES;
+                        Some(addr)
+                    }
+                    _ => {
+                        return Err(ModelsError::DeserializeError(
+                            "invalid last address flag".into(),
+                        ))
+                    }
+                };
+
+                BootstrapClientMessage::AskFinalStatePart {
+                    last_slot,
+                    last_address,
+                }
             }
         };
         Ok((res, cursor))
@@ -1,12 +1,14 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-use super::messages::BootstrapMessage;
+use super::messages::{BootstrapClientMessage, BootstrapMessage};
 use crate::error::BootstrapError;
 use crate::establisher::types::Duplex;
 use massa_hash::Hash;
 use massa_hash::HASH_SIZE_BYTES;
 use massa_models::{constants::BOOTSTRAP_RANDOMNESS_SIZE_BYTES, SerializeMinBEInt};
-use massa_models::{with_serialization_context, SerializeCompact};
+use massa_models::{
+    with_serialization_context, DeserializeCompact, DeserializeMinBEInt, SerializeCompact,
+};
 use massa_signature::{sign, PrivateKey, Signature, SIGNATURE_SIZE_BYTES};
 use std::convert::TryInto;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -14,6 +16,7 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 /// Bootstrap server binder
 pub struct BootstrapServerBinder {
     max_bootstrap_message_size: u32,
+    size_field_len: usize,
     local_privkey: PrivateKey,
     duplex: Duplex,
     prev_sig: Option<Signature>,
@@ -27,8 +30,10 @@ impl BootstrapServerBinder {
     pub fn new(duplex: Duplex, local_privkey: PrivateKey) -> Self {
         let max_bootstrap_message_size =
             with_serialization_context(|context| context.max_bootstrap_message_size);
+        let size_field_len = u32::be_bytes_min_length(max_bootstrap_message_size);
         BootstrapServerBinder {
             max_bootstrap_message_size,
+            size_field_len,
             local_privkey,
             duplex,
             prev_sig: None,
@@ -95,4 +100,21 @@ impl BootstrapServerBinder {
 
         Ok(())
     }
+
+    /// Reads the next message sent by the client. NOT cancel-safe
+    pub async fn next(&mut self) -> Result<BootstrapClientMessage, BootstrapError> {
+        // read message length
+        let msg_len = {
+            let mut msg_len_bytes = vec![0u8; self.size_field_len];
+            self.duplex.read_exact(&mut msg_len_bytes[..]).await?;
+            u32::from_be_bytes_min(&msg_len_bytes, self.max_bootstrap_message_size)?.0
+        };
+
+        // read message and deserialize
+        let mut msg_bytes = vec![0u8; msg_len as usize];
+        self.duplex.read_exact(&mut msg_bytes).await?;
+        let (msg, _len) = BootstrapClientMessage::from_bytes_compact(&msg_bytes)?;
+
+        Ok(msg)
+    }
 }
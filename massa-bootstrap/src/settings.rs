@@ -33,4 +33,6 @@ pub struct BootstrapSettings {
     pub per_ip_min_interval: MassaTime,
     /// Max size of the IP list
     pub ip_list_max_size: usize,
+    /// Approximate max size in bytes of the ledger parts streamed to bootstrapping nodes
+    pub max_ledger_part_size: usize,
 }
@@ -3,7 +3,7 @@
 use super::{
     mock_establisher,
     tools::{
-        bridge_mock_streams, get_boot_state, get_keys, get_peers, get_random_final_state_bootstrap,
+        bridge_mock_streams, get_boot_state, get_keys, get_peers, get_random_final_state,
         wait_consensus_command, wait_network_command,
     },
 };
@@ -15,14 +15,15 @@ use crate::{
     },
 };
 use massa_consensus_exports::{commands::ConsensusCommand, ConsensusCommandSender};
-use massa_final_state::{test_exports::assert_eq_final_state_bootstrap, FinalState};
+use massa_final_state::{test_exports::assert_eq_final_state, FinalState, FinalStateConfig};
+use massa_ledger::LedgerConfig;
 use massa_models::Version;
 use massa_network_exports::{NetworkCommand, NetworkCommandSender};
 use massa_signature::PrivateKey;
 use massa_time::MassaTime;
 use parking_lot::RwLock;
 use serial_test::serial;
-use std::{str::FromStr, sync::Arc};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 use tokio::sync::mpsc;
 
 lazy_static::lazy_static! {
@@ -40,17 +41,28 @@ async fn test_bootstrap_server() {
 
     let (consensus_cmd_tx, mut consensus_cmd_rx) = mpsc::channel::<ConsensusCommand>(5);
     let (network_cmd_tx, mut network_cmd_rx) = mpsc::channel::<NetworkCommand>(5);
-    let final_state_bootstrap = get_random_final_state_bootstrap(2);
-    let final_state = Arc::new(RwLock::new(FinalState::from_bootstrap_state(
-        Default::default(),
-        final_state_bootstrap.clone(),
-    )));
+    let (server_ledger_config, _keep_server_file, _keep_server_dir) =
+        LedgerConfig::sample(&BTreeMap::new());
+    let final_state_server = Arc::new(RwLock::new(get_random_final_state(FinalStateConfig {
+        ledger_config: server_ledger_config,
+        ..Default::default()
+    })));
+    let (client_ledger_config, _keep_client_file, _keep_client_dir) =
+        LedgerConfig::sample(&BTreeMap::new());
+    let final_state_client = Arc::new(RwLock::new(
+        FinalState::new(FinalStateConfig {
+            ledger_config: client_ledger_config,
+            ..Default::default()
+        })
+        .unwrap(),
+    ));
+    let final_state_client_clone = final_state_client.clone();
 
     let (bootstrap_establisher, bootstrap_interface) = mock_establisher::new();
     let bootstrap_manager = start_bootstrap_server(
         ConsensusCommandSender(consensus_cmd_tx),
         NetworkCommandSender(network_cmd_tx),
-        final_state,
+        final_state_server.clone(),
         bootstrap_settings,
         bootstrap_establisher,
         *private_key,
@@ -66,6 +78,7 @@ async fn test_bootstrap_server() {
     let get_state_h = tokio::spawn(async move {
         get_state(
             bootstrap_settings,
+            final_state_client_clone,
             remote_establisher,
             Version::from_str("TEST.1.2").unwrap(),
             MassaTime::now().unwrap().saturating_sub(1000.into()),
@@ -127,7 +140,7 @@ async fn test_bootstrap_server() {
     // wait for peers
     let sent_peers = wait_peers().await;
 
-    // here the ledger is queried directly. We don't intercept this
+    // here the final state is queried directly. We don't intercept this
 
     // wait for bootstrap to ask consensus for bootstrap graph, send it
     let response = match wait_consensus_command(&mut consensus_cmd_rx, 1000.into(), |cmd| match cmd
@@ -160,8 +173,8 @@ async fn test_bootstrap_server() {
         "mismatch between sent and received peers"
     );
 
-    // check ledger
-    assert_eq_final_state_bootstrap(&final_state_bootstrap, &bootstrap_res.final_state.unwrap());
+    // check final states
+    assert_eq_final_state(&final_state_server.read(), &final_state_client.read());
 
     // check states
     assert_eq_thread_cycle_states(&sent_pos, &bootstrap_res.pos.unwrap());
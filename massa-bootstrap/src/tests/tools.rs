@@ -4,9 +4,7 @@ use super::mock_establisher::Duplex;
 use crate::settings::BootstrapSettings;
 use bitvec::prelude::*;
 use massa_consensus_exports::commands::ConsensusCommand;
-use massa_final_state::{
-    test_exports::make_bootstrap_state as make_final_state_bootstrap, FinalStateBootstrap,
-};
+use massa_final_state::{test_exports::create_final_state, FinalState, FinalStateConfig};
 use massa_graph::{
     export_active_block::ExportActiveBlock, ledger::LedgerSubset, BootstrapableGraph,
 };
@@ -65,8 +63,8 @@ fn get_random_ledger_entry() -> LedgerEntry {
     }
 }
 
-/// generates a random bootstrap state for the final state
-pub fn get_random_final_state_bootstrap(thread_count: u8) -> FinalStateBootstrap {
+/// generates a random final state
+pub fn get_random_final_state(config: FinalStateConfig) -> FinalState {
     let mut rng = rand::thread_rng();
 
     let mut sorted_ledger = BTreeMap::new();
@@ -76,11 +74,8 @@ pub fn get_random_final_state_bootstrap(thread_count: u8) -> FinalStateBootstrap
         sorted_ledger.insert(get_random_address(), get_random_ledger_entry());
     }
 
-    make_final_state_bootstrap(
-        Slot::new(rng.gen::<u64>(), rng.gen_range(0..thread_count)),
-        sorted_ledger,
-        messages,
-    )
+    let slot = Slot::new(rng.gen::<u64>(), rng.gen_range(0..config.thread_count));
+    create_final_state(config, slot, sorted_ledger, messages)
 }
 
 pub fn get_dummy_block_id(s: &str) -> BlockId {
@@ -121,6 +116,7 @@ pub fn get_bootstrap_config(bootstrap_public_key: PublicKey) -> BootstrapSetting
         max_endorsements_per_message: 1024,
         max_bootstrap_message_size: 100000000,
         max_bootstrap_pos_entries: 1000,
+        max_bootstrap_final_state_changes: 1000,
         max_bootstrap_pos_cycles: 5,
         endorsement_count: 8,
     });
@@ -138,6 +134,7 @@ pub fn get_bootstrap_config(bootstrap_public_key: PublicKey) -> BootstrapSetting
         max_simultaneous_bootstraps: 2,
         ip_list_max_size: 10,
         per_ip_min_interval: 10000.into(),
+        max_ledger_part_size: 100,
     }
 }
 
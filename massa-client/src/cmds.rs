@@ -127,7 +127,7 @@ pub enum Command {
     #[strum(
         ascii_case_insensitive,
         props(
-            args = "start=Slot end=Slot emitter_address=Address caller_address=Address operation_id=OperationId"
+            args = "start=Slot end=Slot emitter_address=Address caller_address=Address operation_id=OperationId async_message_id=String async_message_status=(executed|execution_failed|expired|evicted)"
         ),
         message = "show events emitted by smart contracts with various filters"
     )]
@@ -508,12 +508,14 @@ impl Command {
             }
 
             Command::get_filtered_sc_output_event => {
-                let p_list: [&str; 5] = [
+                let p_list: [&str; 7] = [
                     "start",
                     "end",
                     "emitter_address",
                     "caller_address",
                     "operation_id",
+                    "async_message_id",
+                    "async_message_status",
                 ];
                 let mut p: HashMap<&str, &str> = HashMap::new();
                 for v in parameters {
@@ -530,6 +532,8 @@ impl Command {
                     emitter_address: parse_value(&p, p_list[2]),
                     original_caller_address: parse_value(&p, p_list[3]),
                     original_operation_id: parse_value(&p, p_list[4]),
+                    async_message_id: parse_value(&p, p_list[5]),
+                    async_message_status: parse_value(&p, p_list[6]),
                 };
                 match client.public.get_filtered_sc_output_event(filter).await {
                     Ok(events) => Ok(Box::new(events)),
@@ -918,6 +922,9 @@ impl Command {
                         simulated_gas_price,
                         bytecode,
                         address,
+                        trace: false,
+                        overrides: Default::default(),
+                        base_state: Default::default(),
                     })
                     .await
                 {
@@ -949,6 +956,10 @@ impl Command {
                         parameter,
                         max_gas,
                         simulated_gas_price,
+                        coins: Amount::zero(),
+                        trace: false,
+                        overrides: Default::default(),
+                        base_state: Default::default(),
                     })
                     .await
                 {
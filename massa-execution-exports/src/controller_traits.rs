@@ -5,11 +5,17 @@
 use crate::types::ExecutionOutput;
 use crate::types::ReadOnlyExecutionRequest;
 use crate::ExecutionError;
+use massa_hash::Hash;
 use massa_ledger::LedgerEntry;
-use massa_models::api::EventFilter;
+use massa_models::api::{
+    AsyncMessageFilter, AsyncMessageInfo, EventFilter, FinalStateRoot, HistoricalLedgerEntry,
+    HistoricalLedgerEntryRequest, LedgerEntryProof, LedgerEntryProofRequest,
+};
+use massa_models::execution::ExecutionReceipt;
 use massa_models::output_event::SCOutputEvent;
 use massa_models::Address;
 use massa_models::BlockId;
+use massa_models::OperationId;
 use massa_models::Slot;
 use std::collections::HashMap;
 
@@ -32,8 +38,17 @@ pub trait ExecutionController: Send + Sync {
     /// * emitter address
     /// * original caller address
     /// * operation id
+    /// * asynchronous message ID and status, for the outcomes of asynchronous messages
     fn get_filtered_sc_output_event(&self, filter: EventFilter) -> Vec<SCOutputEvent>;
 
+    /// Get the execution receipts of a list of operations, in the same order.
+    /// Receipts are looked up in the blockclique first, then among the final ones.
+    /// Operations that were not executed, or whose receipt was pruned, get None.
+    fn get_operation_receipts(
+        &self,
+        operation_ids: &[OperationId],
+    ) -> Vec<Option<ExecutionReceipt>>;
+
     /// Get a copy of a full ledger entry with its final and active values
     ///
     /// # return value
@@ -43,6 +58,51 @@ pub trait ExecutionController: Send + Sync {
         addr: &Address,
     ) -> (Option<LedgerEntry>, Option<LedgerEntry>);
 
+    /// List the datastore keys of an address with their final and active values.
+    /// Only the keys starting with `prefix` and strictly greater than `start_after` (if provided) are listed,
+    /// in increasing order and up to `max_count` of them.
+    ///
+    /// # return value
+    /// * `(final_keys, active_keys)`
+    fn get_final_and_active_datastore_keys(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+    ) -> (Vec<Hash>, Vec<Hash>);
+
+    /// List the pending asynchronous messages matching a filter in the final pool
+    /// and in the pool at the output of the latest executed active slot.
+    /// Fails if the `start_after` message ID of the filter is invalid.
+    ///
+    /// # return value
+    /// * `(final_messages, active_messages)`
+    fn get_final_and_active_async_messages(
+        &self,
+        filter: AsyncMessageFilter,
+    ) -> Result<(Vec<AsyncMessageInfo>, Vec<AsyncMessageInfo>), ExecutionError>;
+
+    /// Get final ledger entry values at the output of recent final slots,
+    /// or an error if a requested slot is not covered by the final state history
+    fn get_historical_ledger_entries(
+        &self,
+        requests: Vec<HistoricalLedgerEntryRequest>,
+    ) -> Result<Vec<HistoricalLedgerEntry>, ExecutionError>;
+
+    /// Get final ledger entry values along with proofs of those values against the final state root
+    /// at the output of the requested recent final slots.
+    /// The proofs requested without slot are all built at the output of the same last final slot.
+    /// Returns an error if a requested slot is not covered by the final state history.
+    fn get_final_ledger_entry_proofs(
+        &self,
+        requests: Vec<LedgerEntryProofRequest>,
+    ) -> Result<Vec<LedgerEntryProof>, ExecutionError>;
+
+    /// Get the root hashes of the final state tree at the output of recent final slots, in the same order.
+    /// Nodes that finalized the same slots have the same state roots at those slots.
+    fn get_final_state_roots(&self, slots: Vec<Slot>) -> Vec<FinalStateRoot>;
+
     /// Execute read-only SC function call without causing modifications to the consensus state
     ///
     /// # arguments
@@ -56,6 +116,19 @@ pub trait ExecutionController: Send + Sync {
         req: ReadOnlyExecutionRequest,
     ) -> Result<ExecutionOutput, ExecutionError>;
 
+    /// Execute a batch of read-only requests one after the other, without any change of the state in between
+    ///
+    /// # arguments
+    /// * `reqs`: the read-only execution requests, each with its own parameters
+    ///
+    /// # returns
+    /// The result of each execution, in the order of the requests,
+    /// or an error if the batch could not be executed.
+    fn execute_readonly_requests(
+        &self,
+        reqs: Vec<ReadOnlyExecutionRequest>,
+    ) -> Result<Vec<Result<ExecutionOutput, ExecutionError>>, ExecutionError>;
+
     /// Returns a boxed clone of self.
     /// Useful to allow cloning `Box<dyn ExecutionController>`.
     fn clone_box(&self) -> Box<dyn ExecutionController>;
@@ -3,6 +3,7 @@
 //! this file defines all possible execution error categories
 
 use displaydoc::Display;
+use massa_models::execution::ExecutionTraceFrame;
 use thiserror::Error;
 
 /// Errors of the execution component.
@@ -15,9 +16,20 @@ pub enum ExecutionError {
     /// Runtime error: {0}
     RuntimeError(String),
 
+    /// Runtime error: {error}
+    TracedRuntimeError {
+        /// error of the execution
+        error: String,
+        /// trace of the execution recorded until the failure
+        trace: Box<ExecutionTraceFrame>,
+    },
+
     /// `MassaHashError`: {0}
     MassaHashError(#[from] massa_hash::MassaHashError),
 
     /// `ModelsError`: {0}
     ModelsError(#[from] massa_models::ModelsError),
+
+    /// Final state error: {0}
+    FinalStateError(String),
 }
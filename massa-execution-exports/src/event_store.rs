@@ -34,6 +34,11 @@ impl EventStore {
         }
     }
 
+    /// Iterate over the events of the store, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &SCOutputEvent> {
+        self.0.iter()
+    }
+
     /// Extend the event store with another store
     pub fn extend(&mut self, other: EventStore) {
         self.0.extend(other.0.into_iter());
@@ -45,6 +50,7 @@ impl EventStore {
     /// * emitter address
     /// * original caller address
     /// * operation id
+    /// * asynchronous message ID and status, for the outcomes of asynchronous messages
     pub fn get_filtered_sc_output_event(&self, filter: &EventFilter) -> VecDeque<SCOutputEvent> {
         self.0
             .iter()
@@ -74,6 +80,16 @@ impl EventStore {
                     (Some(_), None) => return false,
                     _ => (),
                 }
+                match (&filter.async_message_id, &x.async_message_outcome) {
+                    (Some(id), Some(outcome)) if *id != outcome.message_id => return false,
+                    (Some(_), None) => return false,
+                    _ => (),
+                }
+                match (filter.async_message_status, &x.async_message_outcome) {
+                    (Some(status), Some(outcome)) if status != outcome.status => return false,
+                    (Some(_), None) => return false,
+                    _ => (),
+                }
                 true
             })
             .cloned()
@@ -98,6 +114,7 @@ fn test_prune() {
                 origin_operation_id: None,
             },
             data: i.to_string(),
+            async_message_outcome: None,
         });
     }
     assert_eq!(store.0.len(), 10);
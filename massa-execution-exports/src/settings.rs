@@ -2,6 +2,7 @@
 
 //! This module provides the structures used to provide configuration parameters to the Execution system
 
+use massa_models::Amount;
 use massa_time::MassaTime;
 
 /// Execution module configuration
@@ -13,6 +14,11 @@ pub struct ExecutionConfig {
     pub max_final_events: usize,
     /// maximum available gas for asynchronous messages execution
     pub max_async_gas: u64,
+    /// maximum gas available to the asynchronous messages of a single sender at each slot
+    pub max_async_gas_per_sender: u64,
+    /// deposit locked from the payer's balance for each byte of bytecode or datastore entry written in the ledger,
+    /// and refunded when that storage is freed
+    pub storage_byte_cost: Amount,
     /// number of threads
     pub thread_count: u8,
     /// extra lag to add on the execution cursor to improve performance
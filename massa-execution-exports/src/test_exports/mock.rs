@@ -3,8 +3,17 @@
 //! This file defines utilities to mock the crate for testing purposes
 
 use crate::{ExecutionController, ExecutionError, ExecutionOutput, ReadOnlyExecutionRequest};
+use massa_hash::Hash;
 use massa_ledger::LedgerEntry;
-use massa_models::{api::EventFilter, output_event::SCOutputEvent, Address, BlockId, Slot};
+use massa_models::{
+    api::{
+        AsyncMessageFilter, AsyncMessageInfo, EventFilter, FinalStateRoot, HistoricalLedgerEntry,
+        HistoricalLedgerEntryRequest, LedgerEntryProof, LedgerEntryProofRequest,
+    },
+    execution::ExecutionReceipt,
+    output_event::SCOutputEvent,
+    Address, BlockId, OperationId, Slot,
+};
 use std::{
     collections::HashMap,
     sync::{
@@ -13,6 +22,10 @@ use std::{
     },
 };
 
+/// Final and active asynchronous messages returned by `get_final_and_active_async_messages`
+type FinalAndActiveAsyncMessages =
+    Result<(Vec<AsyncMessageInfo>, Vec<AsyncMessageInfo>), ExecutionError>;
+
 /// List of possible messages coming from the mock.
 /// Each variant corresponds to a unique method in `ExecutionController`,
 /// and is emitted in a thread-safe way by the mock whenever that method is called.
@@ -34,6 +47,13 @@ pub enum MockExecutionControllerMessage {
         /// response channel
         response_tx: mpsc::Sender<Vec<SCOutputEvent>>,
     },
+    /// get operation receipts
+    GetOperationReceipts {
+        /// operation IDs
+        operation_ids: Vec<OperationId>,
+        /// response channel
+        response_tx: mpsc::Sender<Vec<Option<ExecutionReceipt>>>,
+    },
     /// get full ledger entry
     GetFullLedgerEntry {
         /// address
@@ -41,6 +61,47 @@ pub enum MockExecutionControllerMessage {
         /// response channel
         response_tx: mpsc::Sender<(Option<LedgerEntry>, Option<LedgerEntry>)>,
     },
+    /// get datastore keys
+    GetDatastoreKeys {
+        /// address
+        addr: Address,
+        /// key prefix
+        prefix: Vec<u8>,
+        /// list the keys after this one
+        start_after: Option<Hash>,
+        /// maximal number of keys
+        max_count: usize,
+        /// response channel
+        response_tx: mpsc::Sender<(Vec<Hash>, Vec<Hash>)>,
+    },
+    /// get async messages
+    GetAsyncMessages {
+        /// message filter
+        filter: AsyncMessageFilter,
+        /// response channel
+        response_tx: mpsc::Sender<FinalAndActiveAsyncMessages>,
+    },
+    /// get historical ledger entries
+    GetHistoricalLedgerEntries {
+        /// requested entries
+        requests: Vec<HistoricalLedgerEntryRequest>,
+        /// response channel
+        response_tx: mpsc::Sender<Result<Vec<HistoricalLedgerEntry>, ExecutionError>>,
+    },
+    /// get final ledger entry proofs
+    GetFinalLedgerEntryProofs {
+        /// requested entries
+        requests: Vec<LedgerEntryProofRequest>,
+        /// response channel
+        response_tx: mpsc::Sender<Result<Vec<LedgerEntryProof>, ExecutionError>>,
+    },
+    /// get final state roots
+    GetFinalStateRoots {
+        /// requested slots
+        slots: Vec<Slot>,
+        /// response channel
+        response_tx: mpsc::Sender<Vec<FinalStateRoot>>,
+    },
     /// read only execution request
     ExecuteReadonlyRequest {
         /// read only execution request
@@ -48,6 +109,14 @@ pub enum MockExecutionControllerMessage {
         /// response channel
         response_tx: mpsc::Sender<Result<ExecutionOutput, ExecutionError>>,
     },
+    /// execute a batch of read-only requests
+    ExecuteReadonlyRequests {
+        /// read only execution requests
+        reqs: Vec<ReadOnlyExecutionRequest>,
+        /// response channel
+        response_tx:
+            mpsc::Sender<Result<Vec<Result<ExecutionOutput, ExecutionError>>, ExecutionError>>,
+    },
 }
 
 /// A mocked execution controller that will intercept calls on its methods
@@ -106,6 +175,22 @@ impl ExecutionController for MockExecutionController {
         response_rx.recv().unwrap()
     }
 
+    fn get_operation_receipts(
+        &self,
+        operation_ids: &[OperationId],
+    ) -> Vec<Option<ExecutionReceipt>> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::GetOperationReceipts {
+                operation_ids: operation_ids.to_vec(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
     fn get_final_and_active_ledger_entry(
         &self,
         addr: &Address,
@@ -122,6 +207,86 @@ impl ExecutionController for MockExecutionController {
         response_rx.recv().unwrap()
     }
 
+    fn get_final_and_active_datastore_keys(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+    ) -> (Vec<Hash>, Vec<Hash>) {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::GetDatastoreKeys {
+                addr: *addr,
+                prefix: prefix.to_vec(),
+                start_after: start_after.copied(),
+                max_count,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_final_and_active_async_messages(
+        &self,
+        filter: AsyncMessageFilter,
+    ) -> FinalAndActiveAsyncMessages {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::GetAsyncMessages {
+                filter,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_historical_ledger_entries(
+        &self,
+        requests: Vec<HistoricalLedgerEntryRequest>,
+    ) -> Result<Vec<HistoricalLedgerEntry>, ExecutionError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::GetHistoricalLedgerEntries {
+                requests,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_final_ledger_entry_proofs(
+        &self,
+        requests: Vec<LedgerEntryProofRequest>,
+    ) -> Result<Vec<LedgerEntryProof>, ExecutionError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::GetFinalLedgerEntryProofs {
+                requests,
+                response_tx,
+            })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
+    fn get_final_state_roots(&self, slots: Vec<Slot>) -> Vec<FinalStateRoot> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::GetFinalStateRoots { slots, response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
     fn execute_readonly_request(
         &self,
         req: ReadOnlyExecutionRequest,
@@ -135,6 +300,19 @@ impl ExecutionController for MockExecutionController {
         response_rx.recv().unwrap()
     }
 
+    fn execute_readonly_requests(
+        &self,
+        reqs: Vec<ReadOnlyExecutionRequest>,
+    ) -> Result<Vec<Result<ExecutionOutput, ExecutionError>>, ExecutionError> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.0
+            .lock()
+            .unwrap()
+            .send(MockExecutionControllerMessage::ExecuteReadonlyRequests { reqs, response_tx })
+            .unwrap();
+        response_rx.recv().unwrap()
+    }
+
     fn clone_box(&self) -> Box<dyn ExecutionController> {
         Box::new(self.clone())
     }
@@ -15,6 +15,8 @@ impl Default for ExecutionConfig {
             readonly_queue_length: READONLY_QUEUE_LENGTH,
             max_final_events: MAX_FINAL_EVENTS,
             max_async_gas: MAX_ASYNC_GAS,
+            max_async_gas_per_sender: MAX_ASYNC_GAS_PER_SENDER,
+            storage_byte_cost: STORAGE_BYTE_COST,
             thread_count: THREAD_COUNT,
             cursor_delay: CURSOR_DELAY,
             clock_compensation: Default::default(),
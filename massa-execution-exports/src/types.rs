@@ -4,6 +4,8 @@
 
 use crate::event_store::EventStore;
 use massa_final_state::StateChanges;
+use massa_models::api::{ReadOnlyBaseState, ReadOnlyStateOverrides};
+use massa_models::execution::{ExecutionReceipt, ExecutionTraceFrame};
 use massa_models::{Address, Amount, BlockId, Slot};
 
 /// structure describing the output of a single execution
@@ -17,6 +19,12 @@ pub struct ExecutionOutput {
     pub state_changes: StateChanges,
     /// events emitted by the execution step
     pub events: EventStore,
+    /// gas used by the bytecode executions of the step, failed executions using all their gas
+    pub gas_used: u64,
+    /// receipts of the operations and asynchronous messages executed by the step, in execution order
+    pub receipts: Vec<ExecutionReceipt>,
+    /// trace of a read-only execution, if requested
+    pub trace: Option<ExecutionTraceFrame>,
 }
 
 /// structure describing different types of read-only execution request
@@ -30,6 +38,12 @@ pub struct ReadOnlyExecutionRequest {
     pub call_stack: Vec<ExecutionStackElement>,
     /// Target of the request
     pub target: ReadOnlyExecutionTarget,
+    /// Whether to record a trace of the execution
+    pub trace: bool,
+    /// Temporary changes of the state, for this execution only
+    pub overrides: ReadOnlyStateOverrides,
+    /// State on top of which the execution happens
+    pub base_state: ReadOnlyBaseState,
 }
 
 /// structure describing different possible targets of a read-only execution request
@@ -46,6 +60,8 @@ pub enum ReadOnlyExecutionTarget {
         target_func: String,
         /// Parameter to pass to the target function
         parameter: String,
+        /// Coins credited to the target before running the function
+        coins: Amount,
     },
 }
 
@@ -9,13 +9,18 @@
 
 use crate::speculative_async_pool::SpeculativeAsyncPool;
 use crate::speculative_ledger::SpeculativeLedger;
-use massa_async_pool::{AsyncMessage, AsyncMessageId};
-use massa_execution_exports::{EventStore, ExecutionError, ExecutionOutput, ExecutionStackElement};
+use massa_async_pool::{async_message_id_to_string, AsyncMessage, AsyncMessageId};
+use massa_execution_exports::{
+    EventStore, ExecutionConfig, ExecutionError, ExecutionOutput, ExecutionStackElement,
+};
 use massa_final_state::{FinalState, StateChanges};
 use massa_hash::Hash;
 use massa_ledger::LedgerChanges;
 use massa_models::{
-    output_event::{EventExecutionContext, SCOutputEvent},
+    execution::{
+        ExecutionReceipt, ExecutionReceiptOrigin, ExecutionTraceFrame, ExecutionTraceStep,
+    },
+    output_event::{AsyncMessageOutcome, AsyncMessageStatus, EventExecutionContext, SCOutputEvent},
     Address, Amount, BlockId, OperationId, Slot,
 };
 use parking_lot::RwLock;
@@ -67,6 +72,16 @@ pub(crate) struct ExecutionContext {
     /// gas price of the execution
     pub gas_price: Amount,
 
+    /// gas used so far by the bytecode executions of the slot
+    pub gas_used: u64,
+
+    /// receipts of the operations and asynchronous messages executed so far in the slot
+    pub receipts: Vec<ExecutionReceipt>,
+
+    /// call frames of a traced read-only execution, from the root frame to the current one,
+    /// or None if the execution is not traced
+    pub trace: Option<Vec<ExecutionTraceFrame>>,
+
     /// slot at which the execution happens
     pub slot: Slot,
 
@@ -105,12 +120,14 @@ impl ExecutionContext {
     /// (see read-only and `active_slot` methods).
     ///
     /// # arguments
+    /// * `config`: execution configuration
     /// * `final_state`: thread-safe access to the final state. Note that this will be used only for reading, never for writing
     /// * `previous_changes`: list of ledger changes that happened since the final ledger state and before the current execution
     ///
     /// # returns
     /// A new (empty) `ExecutionContext` instance
     pub(crate) fn new(
+        config: &ExecutionConfig,
         final_state: Arc<RwLock<FinalState>>,
         previous_changes: StateChanges,
     ) -> Self {
@@ -118,6 +135,7 @@ impl ExecutionContext {
             speculative_ledger: SpeculativeLedger::new(
                 final_state.clone(),
                 previous_changes.ledger_changes,
+                config.storage_byte_cost,
             ),
             speculative_async_pool: SpeculativeAsyncPool::new(
                 final_state.read().async_pool.clone(),
@@ -125,6 +143,9 @@ impl ExecutionContext {
             ),
             max_gas: Default::default(),
             gas_price: Default::default(),
+            gas_used: Default::default(),
+            receipts: Default::default(),
+            trace: Default::default(),
             slot: Slot::new(0, 0),
             created_addr_index: Default::default(),
             created_event_index: Default::default(),
@@ -170,6 +191,7 @@ impl ExecutionContext {
     /// This should be used before performing a read-only execution.
     ///
     /// # arguments
+    /// * `config`: execution configuration
     /// * `slot`: slot at which the execution will happen
     /// * `req`: parameters of the read only execution
     /// * `previous_changes`: list of state changes that happened since the `final_state` state and before this execution
@@ -177,11 +199,14 @@ impl ExecutionContext {
     ///
     /// # returns
     /// A `ExecutionContext` instance ready for a read-only execution
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn readonly(
+        config: &ExecutionConfig,
         slot: Slot,
         max_gas: u64,
         gas_price: Amount,
         call_stack: Vec<ExecutionStackElement>,
+        trace: bool,
         previous_changes: StateChanges,
         final_state: Arc<RwLock<FinalState>>,
     ) -> Self {
@@ -200,6 +225,16 @@ impl ExecutionContext {
         // but not cryptographically secure (and that's ok because the internal state is exposed anyways)
         let unsafe_rng = Xoshiro256PlusPlus::from_seed(seed);
 
+        // the root frame of the trace runs on the top of the call stack
+        let trace = match (trace, call_stack.last()) {
+            (true, Some(top)) => Some(vec![ExecutionTraceFrame {
+                address: top.address,
+                coins: top.coins,
+                steps: Vec::new(),
+            }]),
+            _ => None,
+        };
+
         // return readonly context
         ExecutionContext {
             max_gas,
@@ -208,7 +243,8 @@ impl ExecutionContext {
             stack: call_stack,
             read_only: true,
             unsafe_rng,
-            ..ExecutionContext::new(final_state, previous_changes)
+            trace,
+            ..ExecutionContext::new(config, final_state, previous_changes)
         }
     }
 
@@ -216,6 +252,7 @@ impl ExecutionContext {
     ///
     /// # Arguments
     /// * `max_gas`: maximal amount of asynchronous gas available
+    /// * `max_gas_per_sender`: maximal amount of asynchronous gas available to the messages of a single sender
     ///
     /// # Returns
     /// A vector of `(Option<Vec<u8>>, AsyncMessage)` pairs where:
@@ -224,9 +261,10 @@ impl ExecutionContext {
     pub(crate) fn take_async_batch(
         &mut self,
         max_gas: u64,
+        max_gas_per_sender: u64,
     ) -> Vec<(Option<Vec<u8>>, AsyncMessage)> {
         self.speculative_async_pool
-            .take_batch_to_execute(self.slot, max_gas)
+            .take_batch_to_execute(self.slot, max_gas, max_gas_per_sender)
             .into_iter()
             .map(|(_id, msg)| (self.get_bytecode(&msg.destination), msg))
             .collect()
@@ -236,6 +274,7 @@ impl ExecutionContext {
     /// This should be used before performing any executions at that slot.
     ///
     /// # arguments
+    /// * `config`: execution configuration
     /// * `slot`: slot at which the execution will happen
     /// * `opt_block_id`: optional ID of the block at that slot
     /// * `previous_changes`: list of state changes that happened since the final state state and before this execution
@@ -244,6 +283,7 @@ impl ExecutionContext {
     /// # returns
     /// A `ExecutionContext` instance
     pub(crate) fn active_slot(
+        config: &ExecutionConfig,
         slot: Slot,
         opt_block_id: Option<BlockId>,
         previous_changes: StateChanges,
@@ -269,7 +309,7 @@ impl ExecutionContext {
             slot,
             opt_block_id,
             unsafe_rng,
-            ..ExecutionContext::new(final_state, previous_changes)
+            ..ExecutionContext::new(config, final_state, previous_changes)
         }
     }
 
@@ -342,9 +382,11 @@ impl ExecutionContext {
         // hash the seed to get a unique address
         let address = Address(massa_hash::Hash::compute_from(&data));
 
-        // add this address with its bytecode to the speculative ledger
+        // add this address with its bytecode to the speculative ledger,
+        // the current address paying for the storage of the bytecode
+        let payer = self.get_current_address()?;
         self.speculative_ledger
-            .create_new_sc_address(address, bytecode)?;
+            .create_new_sc_address(&payer, address, bytecode)?;
 
         // add the address to owned addresses
         // so that the current call has write access to it
@@ -409,8 +451,10 @@ impl ExecutionContext {
             )));
         }
 
-        // set data entry
-        self.speculative_ledger.set_data_entry(address, key, data)
+        // set data entry, the current address paying for its storage
+        let payer = self.get_current_address()?;
+        self.speculative_ledger
+            .set_data_entry(&payer, address, key, data)
     }
 
     /// Transfers parallel coins from one address to another.
@@ -438,7 +482,13 @@ impl ExecutionContext {
         }
         // do the transfer
         self.speculative_ledger
-            .transfer_parallel_coins(from_addr, to_addr, amount)
+            .transfer_parallel_coins(from_addr, to_addr, amount)?;
+        self.trace_step(ExecutionTraceStep::CoinTransfer {
+            from: from_addr,
+            to: to_addr,
+            amount,
+        });
+        Ok(())
     }
 
     /// Add a new asynchronous message to speculative pool
@@ -449,16 +499,29 @@ impl ExecutionContext {
         self.speculative_async_pool.push_new_message(msg);
     }
 
-    /// Cancels an asynchronous message, reimbursing `msg.coins` to the sender
+    /// Cancels an asynchronous message, reimbursing to its sender everything spent on it and not consumed:
+    /// `msg.coins` and the gas deposit `msg.max_gas * msg.gas_price`.
+    /// This is used by every path through which a message is not executed successfully:
+    /// expiry, eviction and execution failure.
     ///
     /// # Arguments
     /// * `msg`: the asynchronous message to cancel
-    pub fn cancel_async_message(&mut self, msg: &AsyncMessage) {
-        if let Err(e) = self.transfer_parallel_coins(None, Some(msg.sender), msg.coins) {
-            debug!(
-                "async message cancel: reimbursement of {} failed: {}",
-                msg.sender, e
-            );
+    ///
+    /// # Returns
+    /// The amount reimbursed to the sender
+    pub fn cancel_async_message(&mut self, msg: &AsyncMessage) -> Amount {
+        let refund = msg
+            .coins
+            .saturating_add(msg.gas_price.saturating_mul_u64(msg.max_gas));
+        match self.transfer_parallel_coins(None, Some(msg.sender), refund) {
+            Ok(()) => refund,
+            Err(e) => {
+                debug!(
+                    "async message cancel: reimbursement of {} failed: {}",
+                    msg.sender, e
+                );
+                Amount::zero()
+            }
         }
     }
 
@@ -473,7 +536,13 @@ impl ExecutionContext {
         // settle emitted async messages and reimburse the senders of deleted messages
         let deleted_messages = self.speculative_async_pool.settle_slot(self.slot);
         for (_msg_id, msg) in deleted_messages {
-            self.cancel_async_message(&msg);
+            let refund = self.cancel_async_message(&msg);
+            let status = if self.slot >= msg.validity_end {
+                AsyncMessageStatus::Expired
+            } else {
+                AsyncMessageStatus::Evicted
+            };
+            self.generate_async_message_outcome_event(&msg, status, None, refund);
         }
 
         // generate the execution output
@@ -481,11 +550,15 @@ impl ExecutionContext {
             ledger_changes: self.speculative_ledger.take(),
             async_pool_changes: self.speculative_async_pool.take(),
         };
+        let trace = self.take_trace();
         ExecutionOutput {
             slot: self.slot,
             block_id: std::mem::take(&mut self.opt_block_id),
             state_changes,
             events: std::mem::take(&mut self.events),
+            gas_used: std::mem::take(&mut self.gas_used),
+            receipts: std::mem::take(&mut self.receipts),
+            trace,
         }
     }
 
@@ -508,8 +581,10 @@ impl ExecutionContext {
             )));
         }
 
-        // set data entry
-        self.speculative_ledger.set_bytecode(address, bytecode)
+        // set bytecode, the current address paying for its storage
+        let payer = self.get_current_address()?;
+        self.speculative_ledger
+            .set_bytecode(&payer, address, bytecode)
     }
 
     /// Emits an execution event to be stored.
@@ -528,7 +603,11 @@ impl ExecutionContext {
         };
 
         // Generate the event
-        let event = SCOutputEvent { context, data };
+        let event = SCOutputEvent {
+            context,
+            data,
+            async_message_outcome: None,
+        };
 
         // Increment the event counter fot this slot
         self.created_event_index += 1;
@@ -538,4 +617,115 @@ impl ExecutionContext {
 
         Ok(())
     }
+
+    /// Appends a step to the current frame of the trace, if the execution is traced
+    pub fn trace_step(&mut self, step: ExecutionTraceStep) {
+        if let Some(frame) = self.trace.as_mut().and_then(|frames| frames.last_mut()) {
+            frame.steps.push(step);
+        }
+    }
+
+    /// Opens a trace frame for a nested call, if the execution is traced
+    pub fn trace_enter_call(&mut self, address: Address, coins: Amount) {
+        if let Some(frames) = self.trace.as_mut() {
+            frames.push(ExecutionTraceFrame {
+                address,
+                coins,
+                steps: Vec::new(),
+            });
+        }
+    }
+
+    /// Closes the trace frame of a nested call, adding it as a step of its caller's frame.
+    /// The root frame is never closed.
+    pub fn trace_exit_call(&mut self) {
+        if let Some(frames) = self.trace.as_mut() {
+            if frames.len() > 1 {
+                let frame = frames.pop().expect("trace frames cannot be empty");
+                self.trace_step(ExecutionTraceStep::Call(frame));
+            }
+        }
+    }
+
+    /// Takes the trace of the execution, closing the frames left open by a failure.
+    pub fn take_trace(&mut self) -> Option<ExecutionTraceFrame> {
+        while matches!(&self.trace, Some(frames) if frames.len() > 1) {
+            self.trace_exit_call();
+        }
+        self.trace.take()?.pop()
+    }
+
+    /// Records the receipt of an execution that just ended.
+    /// The gas used and the events emitted are those accumulated in the context since the execution started.
+    ///
+    /// # Arguments
+    /// * `origin`: what was executed
+    /// * `result`: result of the execution
+    /// * `start_gas_used`: gas used in the slot when the execution started
+    /// * `start_event_index`: index of the next event of the slot when the execution started
+    /// * `coins`: coins credited to the address the execution ran on
+    pub fn push_receipt(
+        &mut self,
+        origin: ExecutionReceiptOrigin,
+        result: &Result<(), ExecutionError>,
+        start_gas_used: u64,
+        start_event_index: u64,
+        coins: Amount,
+    ) {
+        let events = self
+            .events
+            .iter()
+            .filter(|event| event.context.index_in_slot >= start_event_index)
+            .cloned()
+            .collect();
+        self.receipts.push(ExecutionReceipt {
+            origin,
+            slot: self.slot,
+            block: self.opt_block_id,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            gas_used: self.gas_used.saturating_sub(start_gas_used),
+            coins,
+            events,
+        });
+    }
+
+    /// Generates the event signaling the outcome of an asynchronous message.
+    /// Its call stack holds the sender and the destination of the message,
+    /// so that senders can find the outcomes of their messages by filtering on their address.
+    ///
+    /// # Arguments
+    /// * `msg`: the asynchronous message
+    /// * `status`: the way the message left the pool
+    /// * `reason`: the reason of the failure, for failed executions
+    /// * `refund`: the amount reimbursed to the sender
+    pub fn generate_async_message_outcome_event(
+        &mut self,
+        msg: &AsyncMessage,
+        status: AsyncMessageStatus,
+        reason: Option<String>,
+        refund: Amount,
+    ) {
+        let outcome = AsyncMessageOutcome {
+            message_id: async_message_id_to_string(&msg.compute_id())
+                .expect("critical: asynchronous message ID serialization failed"),
+            status,
+            reason,
+            refund,
+        };
+        let context = EventExecutionContext {
+            slot: self.slot,
+            block: self.opt_block_id,
+            call_stack: [msg.sender, msg.destination].into_iter().collect(),
+            read_only: self.read_only,
+            index_in_slot: self.created_event_index,
+            origin_operation_id: None,
+        };
+        self.created_event_index += 1;
+        self.events.push(SCOutputEvent {
+            context,
+            data: outcome.to_string(),
+            async_message_outcome: Some(outcome),
+        });
+    }
 }
@@ -9,11 +9,16 @@ use massa_execution_exports::{
     ExecutionConfig, ExecutionController, ExecutionError, ExecutionManager, ExecutionOutput,
     ReadOnlyExecutionRequest,
 };
+use massa_hash::Hash;
 use massa_ledger::LedgerEntry;
-use massa_models::api::EventFilter;
+use massa_models::api::{
+    AsyncMessageFilter, AsyncMessageInfo, EventFilter, FinalStateRoot, HistoricalLedgerEntry,
+    HistoricalLedgerEntryRequest, LedgerEntryProof, LedgerEntryProofRequest,
+};
+use massa_models::execution::ExecutionReceipt;
 use massa_models::output_event::SCOutputEvent;
 use massa_models::Address;
-use massa_models::{BlockId, Slot};
+use massa_models::{BlockId, OperationId, Slot};
 use parking_lot::{Condvar, Mutex, RwLock};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -27,8 +32,9 @@ pub(crate) struct ExecutionInputData {
     pub finalized_blocks: HashMap<Slot, BlockId>,
     /// new blockclique (if there is a new one), blocks indexed by slot
     pub new_blockclique: Option<HashMap<Slot, BlockId>>,
-    /// queue for read-only execution requests and response MPSCs to send back their outputs
-    pub readonly_requests: RequestQueue<ReadOnlyExecutionRequest, ExecutionOutput>,
+    /// queue for batches of read-only execution requests and response MPSCs to send back their outputs
+    pub readonly_requests:
+        RequestQueue<Vec<ReadOnlyExecutionRequest>, Vec<Result<ExecutionOutput, ExecutionError>>>,
 }
 
 impl ExecutionInputData {
@@ -88,12 +94,23 @@ impl ExecutionController for ExecutionControllerImpl {
     /// * emitter address
     /// * original caller address
     /// * operation id
+    /// * asynchronous message ID and status, for the outcomes of asynchronous messages
     fn get_filtered_sc_output_event(&self, filter: EventFilter) -> Vec<SCOutputEvent> {
         self.execution_state
             .read()
             .get_filtered_sc_output_event(filter)
     }
 
+    /// gets the execution receipts of a list of operations, in the same order
+    fn get_operation_receipts(
+        &self,
+        operation_ids: &[OperationId],
+    ) -> Vec<Option<ExecutionReceipt>> {
+        self.execution_state
+            .read()
+            .get_operation_receipts(operation_ids)
+    }
+
     /// gets a copy of a full ledger entry
     ///
     /// # return value
@@ -107,12 +124,78 @@ impl ExecutionController for ExecutionControllerImpl {
             .get_final_and_active_ledger_entry(addr)
     }
 
+    /// lists the datastore keys of an address
+    ///
+    /// # return value
+    /// * `(final_keys, active_keys)`
+    fn get_final_and_active_datastore_keys(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+    ) -> (Vec<Hash>, Vec<Hash>) {
+        self.execution_state
+            .read()
+            .get_final_and_active_datastore_keys(addr, prefix, start_after, max_count)
+    }
+
+    /// Lists the pending asynchronous messages of the final pool and of the pool at the latest executed active slot
+    fn get_final_and_active_async_messages(
+        &self,
+        filter: AsyncMessageFilter,
+    ) -> Result<(Vec<AsyncMessageInfo>, Vec<AsyncMessageInfo>), ExecutionError> {
+        self.execution_state
+            .read()
+            .get_final_and_active_async_messages(&filter)
+    }
+
+    /// Gets final ledger entry values at the output of recent final slots
+    fn get_historical_ledger_entries(
+        &self,
+        requests: Vec<HistoricalLedgerEntryRequest>,
+    ) -> Result<Vec<HistoricalLedgerEntry>, ExecutionError> {
+        self.execution_state
+            .read()
+            .get_historical_ledger_entries(requests)
+    }
+
+    /// Gets final ledger entry values along with their proofs against the final state root of recent final slots
+    fn get_final_ledger_entry_proofs(
+        &self,
+        requests: Vec<LedgerEntryProofRequest>,
+    ) -> Result<Vec<LedgerEntryProof>, ExecutionError> {
+        self.execution_state
+            .read()
+            .get_final_ledger_entry_proofs(requests)
+    }
+
+    /// Gets the root hashes of the final state tree at the output of recent final slots
+    fn get_final_state_roots(&self, slots: Vec<Slot>) -> Vec<FinalStateRoot> {
+        self.execution_state.read().get_final_state_roots(slots)
+    }
+
     /// Executes a read-only request
     /// Read-only requests do not modify consensus state
     fn execute_readonly_request(
         &self,
         req: ReadOnlyExecutionRequest,
     ) -> Result<ExecutionOutput, ExecutionError> {
+        self.execute_readonly_requests(vec![req])?
+            .pop()
+            .unwrap_or_else(|| {
+                Err(ExecutionError::ChannelError(
+                    "missing readonly execution result".into(),
+                ))
+            })
+    }
+
+    /// Executes a batch of read-only requests
+    /// The batch is queued as a single item and executed at once, so that all requests see the same state
+    fn execute_readonly_requests(
+        &self,
+        reqs: Vec<ReadOnlyExecutionRequest>,
+    ) -> Result<Vec<Result<ExecutionOutput, ExecutionError>>, ExecutionError> {
         let resp_rx = {
             let mut input_data = self.input_data.1.lock();
 
@@ -123,14 +206,15 @@ impl ExecutionController for ExecutionControllerImpl {
                 ));
             }
 
-            // prepare the channel to send back the result of the read-only execution
-            let (resp_tx, resp_rx) =
-                std::sync::mpsc::channel::<Result<ExecutionOutput, ExecutionError>>();
+            // prepare the channel to send back the results of the read-only executions
+            let (resp_tx, resp_rx) = std::sync::mpsc::channel::<
+                Result<Vec<Result<ExecutionOutput, ExecutionError>>, ExecutionError>,
+            >();
 
-            // append the request to the queue of input read-only requests
+            // append the batch to the queue of input read-only requests
             input_data
                 .readonly_requests
-                .push(RequestWithResponseSender::new(req, resp_tx));
+                .push(RequestWithResponseSender::new(reqs, resp_tx));
 
             // wake up the execution main loop
             self.input_data.0.notify_one();
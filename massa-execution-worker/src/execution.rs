@@ -10,23 +10,32 @@
 
 use crate::context::ExecutionContext;
 use crate::interface_impl::InterfaceImpl;
-use massa_async_pool::AsyncMessage;
+use massa_async_pool::{
+    async_message_id_from_str, async_message_id_to_string, AsyncMessage, AsyncMessageId, Change,
+};
 use massa_execution_exports::{
     EventStore, ExecutionConfig, ExecutionError, ExecutionOutput, ExecutionStackElement,
     ReadOnlyExecutionRequest, ReadOnlyExecutionTarget,
 };
-use massa_final_state::{FinalState, StateChanges};
+use massa_final_state::{FinalState, FinalStateError, StateChanges};
+use massa_hash::Hash;
 use massa_ledger::{Applicable, LedgerEntry, SetUpdateOrDelete};
-use massa_models::api::EventFilter;
-use massa_models::output_event::SCOutputEvent;
+use massa_models::api::{
+    AsyncMessageFilter, AsyncMessageInfo, EventFilter, FinalStateRoot, HistoricalLedgerEntry,
+    HistoricalLedgerEntryRequest, LedgerEntryProof, LedgerEntryProofRequest, ReadOnlyBaseState,
+    ReadOnlyStateOverrides,
+};
+use massa_models::execution::{ExecutionReceipt, ExecutionReceiptOrigin};
+use massa_models::output_event::{AsyncMessageStatus, SCOutputEvent};
+use massa_models::prehash::{Map, Set};
 use massa_models::signed::Signable;
 use massa_models::Slot;
-use massa_models::{Address, BlockId, OperationId, OperationType, SignedOperation};
+use massa_models::{Address, Amount, BlockId, OperationId, OperationType, SignedOperation};
 use massa_sc_runtime::Interface;
 use massa_storage::Storage;
 use parking_lot::{Mutex, RwLock};
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, VecDeque},
     sync::Arc,
 };
 use tracing::debug;
@@ -54,6 +63,8 @@ pub(crate) struct ExecutionState {
     pub final_cursor: Slot,
     // store containing execution events that became final
     final_events: EventStore,
+    // receipts of the executions that became final, oldest first, limited to max_final_events
+    final_receipts: VecDeque<ExecutionReceipt>,
     // final state with atomic R/W access
     final_state: Arc<RwLock<FinalState>>,
     // execution context (see documentation in context.rs)
@@ -85,6 +96,7 @@ impl ExecutionState {
 
         // Create an empty placeholder execution context, with shared atomic access
         let execution_context = Arc::new(Mutex::new(ExecutionContext::new(
+            &config,
             final_state.clone(),
             Default::default(),
         )));
@@ -105,6 +117,8 @@ impl ExecutionState {
             active_history: Default::default(),
             // empty final event store: it is not recovered through bootstrap
             final_events: Default::default(),
+            // empty final receipts: they are not recovered through bootstrap either
+            final_receipts: Default::default(),
             // no active slots executed yet: set active_cursor to the last final block
             active_cursor: last_final_slot,
             final_cursor: last_final_slot,
@@ -146,6 +160,12 @@ impl ExecutionState {
         // append generated events to the final event store
         self.final_events.extend(exec_out.events);
         self.final_events.prune(self.config.max_final_events);
+
+        // append execution receipts to the final ones, with the same retention as events
+        self.final_receipts.extend(exec_out.receipts);
+        while self.final_receipts.len() > self.config.max_final_events {
+            self.final_receipts.pop_front();
+        }
     }
 
     /// Applies an execution output to the active (non-final) state
@@ -261,6 +281,21 @@ impl ExecutionState {
         accumulated_changes
     }
 
+    /// Adds the gas used by a bytecode execution to the gas used during the slot of the execution context.
+    /// The runtime returns the remaining gas of a successful execution, while a failed execution uses all its gas.
+    ///
+    /// # Arguments
+    /// * `max_gas`: maximum gas of the execution
+    /// * `run_result`: result of the execution
+    fn record_gas_usage<E>(&self, max_gas: u64, run_result: &Result<u64, E>) {
+        let gas_used = match run_result {
+            Ok(remaining_gas) => max_gas.saturating_sub(*remaining_gas),
+            Err(_) => max_gas,
+        };
+        let mut context = context_guard!(self);
+        context.gas_used = context.gas_used.saturating_add(gas_used);
+    }
+
     /// Execute an operation in the context of a block.
     /// Assumes the execution context was initialized at the beginning of the slot.
     ///
@@ -291,8 +326,14 @@ impl ExecutionState {
             .compute_id()
             .expect("could not compute operation ID");
 
+        // remember where the slot stands to isolate the gas and events of this operation in its receipt
+        let (start_gas_used, start_event_index) = {
+            let context = context_guard!(self);
+            (context.gas_used, context.created_event_index)
+        };
+
         // call the execution process specific to the operation type
-        match &operation.content.op {
+        let result = match &operation.content.op {
             OperationType::ExecuteSC { .. } => self.execute_executesc_op(
                 &operation.content.op,
                 block_creator_addr,
@@ -306,7 +347,30 @@ impl ExecutionState {
                 sender_addr,
             ),
             _ => panic!("unexpected operation type"), // checked at the beginning of the function
-        }
+        };
+
+        // ExecuteSC coins are credited to the sender before the execution and are kept on failure,
+        // while the CallSC transfer to the target is reverted on failure
+        let coins = match (&operation.content.op, &result) {
+            (OperationType::ExecuteSC { coins, .. }, _) => *coins,
+            (
+                OperationType::CallSC {
+                    parallel_coins,
+                    sequential_coins,
+                    ..
+                },
+                Ok(()),
+            ) => sequential_coins.saturating_add(*parallel_coins),
+            _ => Amount::zero(),
+        };
+        context_guard!(self).push_receipt(
+            ExecutionReceiptOrigin::Operation(operation_id),
+            &result,
+            start_gas_used,
+            start_event_index,
+            coins,
+        );
+        result
     }
 
     /// Execute an operation of type `ExecuteSC`
@@ -389,6 +453,7 @@ impl ExecutionState {
 
         // run the VM on the bytecode contained in the operation
         let run_result = massa_sc_runtime::run_main(bytecode, *max_gas, &*self.execution_interface);
+        self.record_gas_usage(*max_gas, &run_result);
         if let Err(err) = run_result {
             // there was an error during bytecode execution:
             // cancel the effects of the execution by resetting the context to the previously saved snapshot
@@ -536,6 +601,7 @@ impl ExecutionState {
             param,
             &*self.execution_interface,
         );
+        self.record_gas_usage(max_gas, &run_result);
         if let Err(err) = run_result {
             // there was an error during bytecode execution:
             // cancel the effects of the execution by resetting the context to the previously saved snapshot
@@ -553,6 +619,8 @@ impl ExecutionState {
 
     /// Tries to execute an asynchronous message
     /// If the execution failed reimburse the message sender.
+    /// In both cases, an event signaling the outcome of the message is generated,
+    /// and a receipt of the execution is recorded.
     ///
     /// # Arguments
     /// * message: message information
@@ -562,22 +630,64 @@ impl ExecutionState {
         message: AsyncMessage,
         bytecode: Option<Vec<u8>>,
     ) -> Result<(), ExecutionError> {
-        // If there is no target bytecode or if message data is invalid,
-        // directly reimburse sender with coins and quit
-        let (bytecode, data) = match (bytecode, std::str::from_utf8(&message.data)) {
-            (Some(bc), Ok(d)) => (bc, d),
-            (bc, _d) => {
-                context_guard!(self).cancel_async_message(&message);
-                if bc.is_none() {
-                    return Err(ExecutionError::RuntimeError(
-                        "no target bytecode found".into(),
-                    ));
-                }
-                return Err(ExecutionError::RuntimeError(
-                    "message data does not convert to utf-8".into(),
-                ));
+        let (start_gas_used, start_event_index) = {
+            let context = context_guard!(self);
+            (context.gas_used, context.created_event_index)
+        };
+        let result = self.run_async_message(&message, bytecode);
+        let mut context = context_guard!(self);
+        let coins = match &result {
+            Ok(()) => {
+                context.generate_async_message_outcome_event(
+                    &message,
+                    AsyncMessageStatus::Executed,
+                    None,
+                    Amount::zero(),
+                );
+                message.coins
+            }
+            Err(err) => {
+                let refund = context.cancel_async_message(&message);
+                context.generate_async_message_outcome_event(
+                    &message,
+                    AsyncMessageStatus::ExecutionFailed,
+                    Some(err.to_string()),
+                    refund,
+                );
+                Amount::zero()
             }
         };
+        context.push_receipt(
+            ExecutionReceiptOrigin::AsyncMessage(
+                async_message_id_to_string(&message.compute_id())
+                    .expect("critical: asynchronous message ID serialization failed"),
+            ),
+            &result,
+            start_gas_used,
+            start_event_index,
+            coins,
+        );
+        result
+    }
+
+    /// Runs the handler of an asynchronous message.
+    /// On failure, the effects of the execution are cancelled.
+    /// Reimbursing the sender is left to the caller (see `execute_async_message`).
+    ///
+    /// # Arguments
+    /// * message: message information
+    /// * bytecode: executable target bytecode, or None if unavailable
+    fn run_async_message(
+        &self,
+        message: &AsyncMessage,
+        bytecode: Option<Vec<u8>>,
+    ) -> Result<(), ExecutionError> {
+        // If there is no target bytecode or if message data is invalid, quit
+        let bytecode = bytecode
+            .ok_or_else(|| ExecutionError::RuntimeError("no target bytecode found".into()))?;
+        let data = std::str::from_utf8(&message.data).map_err(|_| {
+            ExecutionError::RuntimeError("message data does not convert to utf-8".into())
+        })?;
 
         // prepare execution context
         let context_snapshot;
@@ -603,9 +713,8 @@ impl ExecutionState {
             if let Err(err) =
                 context.transfer_parallel_coins(None, Some(message.destination), message.coins)
             {
-                // coin crediting failed: reset context to snapshot and reimburse sender
+                // coin crediting failed: reset context to snapshot
                 context.reset_to_snapshot(context_snapshot);
-                context.cancel_async_message(&message);
                 return Err(ExecutionError::RuntimeError(format!(
                     "could not credit coins to target of async execution: {}",
                     err
@@ -614,17 +723,17 @@ impl ExecutionState {
         }
 
         // run the target function
-        if let Err(err) = massa_sc_runtime::run_function(
+        let run_result = massa_sc_runtime::run_function(
             &bytecode,
             message.max_gas,
             &message.handler,
             data,
             &*self.execution_interface,
-        ) {
-            // execution failed: reset context to snapshot and reimburse sender
-            let mut context = context_guard!(self);
-            context.reset_to_snapshot(context_snapshot);
-            context.cancel_async_message(&message);
+        );
+        self.record_gas_usage(message.max_gas, &run_result);
+        if let Err(err) = run_result {
+            // execution failed: reset context to snapshot
+            context_guard!(self).reset_to_snapshot(context_snapshot);
             Err(ExecutionError::RuntimeError(format!(
                 "async message runtime execution error: {}",
                 err
@@ -649,6 +758,7 @@ impl ExecutionState {
 
         // create a new execution context for the whole active slot
         let mut execution_context = ExecutionContext::active_slot(
+            &self.config,
             slot,
             opt_block_id,
             previous_changes,
@@ -658,7 +768,10 @@ impl ExecutionState {
         // note that here, some pre-operations (like crediting block producers) can be performed before the lock
 
         // get asynchronous messages to execute
-        let messages = execution_context.take_async_batch(self.config.max_async_gas);
+        let messages = execution_context.take_async_batch(
+            self.config.max_async_gas,
+            self.config.max_async_gas_per_sender,
+        );
 
         // apply the created execution context for slot execution
         *context_guard!(self) = execution_context;
@@ -697,38 +810,106 @@ impl ExecutionState {
         context_guard!(self).settle_slot()
     }
 
-    /// Runs a read-only execution request.
-    /// The executed bytecode appears to be able to read and write the consensus state,
-    /// but all accumulated changes are simply returned as an `ExecutionOutput` object,
-    /// and not actually applied to the consensus state.
+    /// Creates the execution context of a read-only request,
+    /// on top of the base state chosen by the request and with its state overrides applied.
     ///
     /// # Arguments
     /// * `req`: a read-only execution request
     ///
     /// # Returns
-    ///  `ExecutionOutput` describing the output of the execution, or an error
-    pub(crate) fn execute_readonly_request(
+    /// The read-only execution context, or an error if the base state or the overriding slot is invalid
+    pub(crate) fn create_readonly_context(
         &self,
-        req: ReadOnlyExecutionRequest,
-    ) -> Result<ExecutionOutput, ExecutionError> {
-        // set the execution slot to be the one after the latest executed active slot
-        let slot = self
-            .active_cursor
+        req: &ReadOnlyExecutionRequest,
+    ) -> Result<ExecutionContext, ExecutionError> {
+        // get the slot after which the execution sees the state
+        let base_slot = match req.base_state {
+            ReadOnlyBaseState::Candidate => self.active_cursor,
+            ReadOnlyBaseState::Final => self.final_cursor,
+            ReadOnlyBaseState::AfterSlot(slot) => {
+                if slot < self.final_cursor || slot > self.active_cursor {
+                    return Err(ExecutionError::RuntimeError(format!(
+                        "slot {} is out of the execution history, which spans from {} to {}",
+                        slot, self.final_cursor, self.active_cursor
+                    )));
+                }
+                slot
+            }
+        };
+        let next_slot = base_slot
             .get_next_slot(self.config.thread_count)
             .expect("slot overflow in readonly execution");
 
         // accumulate state changes that happened in the output history before this slot
-        let previous_changes = self.get_accumulated_active_changes_at_slot(slot);
+        let mut previous_changes = self.get_accumulated_active_changes_at_slot(next_slot);
+
+        // apply the overrides of the request on top of those changes
+        let ReadOnlyStateOverrides {
+            slot: slot_override,
+            addresses,
+        } = &req.overrides;
+        for address_override in addresses {
+            let changes = &mut previous_changes.ledger_changes;
+            let addr = address_override.address;
+            if let Some(balance) = address_override.balance {
+                changes.set_parallel_balance(addr, balance);
+            }
+            if let Some(bytecode) = &address_override.bytecode {
+                changes.set_bytecode(addr, bytecode.clone());
+            }
+            for (key, value) in address_override.datastore.iter() {
+                let key = Hash::compute_from(key.as_bytes());
+                match value {
+                    Some(value) => changes.set_data_entry(addr, key, value.clone()),
+                    None => changes.delete_data_entry(addr, key),
+                }
+            }
+        }
+
+        // run at the overriding slot if any, otherwise at the slot after the base state
+        let slot = match *slot_override {
+            Some(slot) if slot.thread >= self.config.thread_count => {
+                return Err(ExecutionError::RuntimeError(format!(
+                    "overriding slot {} has an invalid thread",
+                    slot
+                )))
+            }
+            Some(slot) => slot,
+            None => next_slot,
+        };
 
         // create a readonly execution context
-        let execution_context = ExecutionContext::readonly(
+        Ok(ExecutionContext::readonly(
+            &self.config,
             slot,
             req.max_gas,
             req.simulated_gas_price,
-            req.call_stack,
+            req.call_stack.clone(),
+            req.trace,
             previous_changes,
             self.final_state.clone(),
-        );
+        ))
+    }
+
+    /// Runs a read-only execution request.
+    /// The executed bytecode appears to be able to read and write the consensus state,
+    /// but all accumulated changes are simply returned as an `ExecutionOutput` object,
+    /// and not actually applied to the consensus state.
+    /// The execution happens on top of the base state chosen by the request: the latest candidate state,
+    /// the latest final state, or the state after a slot of the execution history.
+    /// The state overrides of the request are applied before the execution and are not part of its output.
+    ///
+    /// # Arguments
+    /// * `req`: a read-only execution request
+    ///
+    /// # Returns
+    ///  `ExecutionOutput` describing the output of the execution, or an error
+    pub(crate) fn execute_readonly_request(
+        &self,
+        req: ReadOnlyExecutionRequest,
+    ) -> Result<ExecutionOutput, ExecutionError> {
+        // create a readonly execution context on top of the requested state
+        let mut execution_context = self.create_readonly_context(&req)?;
 
         // run the intepreter according to the target type
         match req.target {
@@ -737,14 +918,30 @@ impl ExecutionState {
                 *context_guard!(self) = execution_context;
 
                 // run the bytecode's main function
-                massa_sc_runtime::run_main(&bytecode, req.max_gas, &*self.execution_interface)
-                    .map_err(|err| ExecutionError::RuntimeError(err.to_string()))?;
+                let run_result =
+                    massa_sc_runtime::run_main(&bytecode, req.max_gas, &*self.execution_interface);
+                self.record_gas_usage(req.max_gas, &run_result);
+                if let Err(err) = run_result {
+                    return Err(self.readonly_failure(err.to_string()));
+                }
             }
             ReadOnlyExecutionTarget::FunctionCall {
                 target_addr,
                 target_func,
                 parameter,
+                coins,
             } => {
+                // credit the coins sent with the call to the target,
+                // without debiting the caller which is often a random address
+                if let Err(err) =
+                    execution_context.transfer_parallel_coins(None, Some(target_addr), coins)
+                {
+                    return Err(ExecutionError::RuntimeError(format!(
+                        "failed to credit {} call coins to {}: {}",
+                        coins, target_addr, err
+                    )));
+                }
+
                 // get the bytecode, default to an empty vector
                 let bytecode = execution_context
                     .get_bytecode(&target_addr)
@@ -754,14 +951,17 @@ impl ExecutionState {
                 *context_guard!(self) = execution_context;
 
                 // run the target function in the bytecode
-                massa_sc_runtime::run_function(
+                let run_result = massa_sc_runtime::run_function(
                     &bytecode,
                     req.max_gas,
                     &target_func,
                     &parameter,
                     &*self.execution_interface,
-                )
-                .map_err(|err| ExecutionError::RuntimeError(err.to_string()))?;
+                );
+                self.record_gas_usage(req.max_gas, &run_result);
+                if let Err(err) = run_result {
+                    return Err(self.readonly_failure(err.to_string()));
+                }
             }
         }
 
@@ -769,6 +969,74 @@ impl ExecutionState {
         Ok(context_guard!(self).settle_slot())
     }
 
+    /// Builds the error of a failed read-only execution,
+    /// with the trace recorded until the failure if the execution was traced.
+    fn readonly_failure(&self, error: String) -> ExecutionError {
+        match context_guard!(self).take_trace() {
+            Some(trace) => ExecutionError::TracedRuntimeError {
+                error,
+                trace: Box::new(trace),
+            },
+            None => ExecutionError::RuntimeError(error),
+        }
+    }
+
+    /// Gets final ledger entry values along with their proofs against the final state root of recent final slots.
+    /// The final state is locked once so that all the proofs requested without slot
+    /// are built at the output of the same last final slot.
+    pub fn get_final_ledger_entry_proofs(
+        &self,
+        requests: Vec<LedgerEntryProofRequest>,
+    ) -> Result<Vec<LedgerEntryProof>, ExecutionError> {
+        let final_state = self.final_state.read();
+        requests
+            .into_iter()
+            .map(|req| {
+                final_state.get_ledger_entry_proof(&req.address, req.datastore_key, req.slot)
+            })
+            .collect::<Result<_, FinalStateError>>()
+            .map_err(|err| ExecutionError::FinalStateError(err.to_string()))
+    }
+
+    /// Gets the root hashes of the final state tree at the output of recent final slots
+    pub fn get_final_state_roots(&self, slots: Vec<Slot>) -> Vec<FinalStateRoot> {
+        let final_state = self.final_state.read();
+        slots
+            .into_iter()
+            .map(|slot| FinalStateRoot {
+                slot,
+                state_root: final_state.get_state_root_at(slot),
+            })
+            .collect()
+    }
+
+    /// Gets final ledger entry values at the output of recent final slots.
+    /// The final state is locked once so that all the values are read from the same history.
+    pub fn get_historical_ledger_entries(
+        &self,
+        requests: Vec<HistoricalLedgerEntryRequest>,
+    ) -> Result<Vec<HistoricalLedgerEntry>, ExecutionError> {
+        let final_state = self.final_state.read();
+        requests
+            .into_iter()
+            .map(|req| {
+                let datastore_value = match &req.datastore_key {
+                    Some(key) => final_state.get_data_entry_at(&req.address, key, req.slot)?,
+                    None => None,
+                };
+                Ok(HistoricalLedgerEntry {
+                    address: req.address,
+                    slot: req.slot,
+                    balance: final_state.get_parallel_balance_at(&req.address, req.slot)?,
+                    bytecode: final_state.get_bytecode_at(&req.address, req.slot)?,
+                    datastore_key: req.datastore_key,
+                    datastore_value,
+                })
+            })
+            .collect::<Result<_, FinalStateError>>()
+            .map_err(|err| ExecutionError::FinalStateError(err.to_string()))
+    }
+
     /// Gets a full ledger entry both at the latest final and active executed slots
     /// TODO: this can be heavily optimized, see comments and `https://github.com/massalabs/massa/issues/2343`
     ///
@@ -816,12 +1084,124 @@ impl ExecutionState {
         (final_entry, active_entry)
     }
 
+    /// Lists the datastore keys of an address both at the latest final and active executed slots
+    ///
+    /// # Arguments
+    /// * `addr`: address to query
+    /// * `prefix`: only the keys whose bytes start with `prefix` are listed
+    /// * `start_after`: only the keys strictly greater than `start_after` are listed, if provided
+    /// * `max_count`: maximal number of keys listed at each slot
+    ///
+    /// # returns
+    /// `(final_keys, active_keys)`, both in increasing order
+    pub fn get_final_and_active_datastore_keys(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+    ) -> (Vec<Hash>, Vec<Hash>) {
+        // see get_final_and_active_ledger_entry for the choice of the slot
+        let next_slot = self
+            .active_cursor
+            .get_next_slot(self.config.thread_count)
+            .expect("slot overflow when getting speculative ledger");
+        let active_changes = self
+            .get_accumulated_active_changes_at_slot(next_slot)
+            .ledger_changes;
+
+        // hold the final state lock so that both lists are computed on the same final ledger
+        let final_state = self.final_state.read();
+        let final_keys =
+            final_state
+                .ledger
+                .get_datastore_keys(addr, prefix, start_after, max_count);
+        let active_keys = active_changes.get_datastore_keys_or_else(
+            addr,
+            prefix,
+            start_after,
+            max_count,
+            |count| {
+                final_state
+                    .ledger
+                    .get_datastore_keys(addr, prefix, start_after, count)
+            },
+        );
+        (final_keys, active_keys)
+    }
+
+    /// Lists the pending asynchronous messages matching a filter,
+    /// in the final pool and in the pool at the output of the latest executed active slot
+    ///
+    /// # returns
+    /// `(final_messages, active_messages)`, or an error if the `start_after` message ID of the filter is invalid
+    pub fn get_final_and_active_async_messages(
+        &self,
+        filter: &AsyncMessageFilter,
+    ) -> Result<(Vec<AsyncMessageInfo>, Vec<AsyncMessageInfo>), ExecutionError> {
+        // see get_final_and_active_ledger_entry for the choice of the slot
+        let next_slot = self
+            .active_cursor
+            .get_next_slot(self.config.thread_count)
+            .expect("slot overflow when getting speculative asynchronous pool");
+        let active_changes = self
+            .get_accumulated_active_changes_at_slot(next_slot)
+            .async_pool_changes;
+
+        // each active change removes at most one of the listed final messages,
+        // list as many more final messages to still have enough once the changes are applied
+        let changes_count = active_changes.get_changes().len() as u64;
+        let final_filter = AsyncMessageFilter {
+            max_count: filter.max_count.saturating_add(changes_count),
+            ..filter.clone()
+        };
+        let final_candidates = self
+            .final_state
+            .read()
+            .async_pool
+            .get_filtered_messages(&final_filter)?;
+        let final_messages = get_async_messages_info(
+            final_candidates
+                .iter()
+                .take(filter.max_count as usize)
+                .cloned(),
+        )?;
+
+        // apply the active changes to the listed final messages only
+        let start_after = filter
+            .start_after
+            .as_deref()
+            .map(async_message_id_from_str)
+            .transpose()?;
+        let mut active_candidates: BTreeMap<AsyncMessageId, AsyncMessage> =
+            final_candidates.into_iter().collect();
+        for change in active_changes.get_changes() {
+            match change {
+                Change::Add(id, msg)
+                    if msg.matches_filter(filter) && start_after.iter().all(|start| id > start) =>
+                {
+                    active_candidates.insert(*id, msg.clone());
+                }
+                Change::Add(id, _) | Change::Delete(id) => {
+                    active_candidates.remove(id);
+                }
+            }
+        }
+        let active_messages = get_async_messages_info(
+            active_candidates
+                .into_iter()
+                .take(filter.max_count as usize),
+        )?;
+        Ok((final_messages, active_messages))
+    }
+
     /// Gets execution events optionally filtered by:
     /// * start slot
     /// * end slot
     /// * emitter address
     /// * original caller address
     /// * operation id
+    /// * asynchronous message ID and status, for the outcomes of asynchronous messages
     pub fn get_filtered_sc_output_event(&self, filter: EventFilter) -> Vec<SCOutputEvent> {
         self.final_events
             .get_filtered_sc_output_event(&filter)
@@ -833,4 +1213,55 @@ impl ExecutionState {
             )
             .collect()
     }
+
+    /// Gets the execution receipts of a list of operations, in the same order.
+    /// Receipts from the active history take precedence over final ones,
+    /// and the most recent receipt is returned if an operation was executed several times.
+    pub fn get_operation_receipts(
+        &self,
+        operation_ids: &[OperationId],
+    ) -> Vec<Option<ExecutionReceipt>> {
+        let wanted: Set<OperationId> = operation_ids.iter().copied().collect();
+        let mut found: Map<OperationId, &ExecutionReceipt> = Map::default();
+        let receipts = self.final_receipts.iter().chain(
+            self.active_history
+                .iter()
+                .flat_map(|out| out.receipts.iter()),
+        );
+        for receipt in receipts {
+            if let ExecutionReceiptOrigin::Operation(id) = &receipt.origin {
+                if wanted.contains(id) {
+                    found.insert(*id, receipt);
+                }
+            }
+        }
+        operation_ids
+            .iter()
+            .map(|id| found.get(id).map(|receipt| (*receipt).clone()))
+            .collect()
+    }
+}
+
+/// Converts listed asynchronous messages into their API representation
+fn get_async_messages_info(
+    messages: impl Iterator<Item = (AsyncMessageId, AsyncMessage)>,
+) -> Result<Vec<AsyncMessageInfo>, ExecutionError> {
+    messages
+        .map(|(id, msg)| {
+            Ok(AsyncMessageInfo {
+                id: async_message_id_to_string(&id)?,
+                emission_slot: msg.emission_slot,
+                emission_index: msg.emission_index,
+                sender: msg.sender,
+                destination: msg.destination,
+                handler: msg.handler,
+                max_gas: msg.max_gas,
+                gas_price: msg.gas_price,
+                coins: msg.coins,
+                validity_start: msg.validity_start,
+                validity_end: msg.validity_end,
+                data: msg.data,
+            })
+        })
+        .collect()
 }
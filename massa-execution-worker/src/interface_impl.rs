@@ -7,9 +7,10 @@
 
 use crate::context::ExecutionContext;
 use anyhow::{bail, Result};
-use massa_async_pool::AsyncMessage;
+use massa_async_pool::{async_message_id_to_string, AsyncMessage};
 use massa_execution_exports::ExecutionConfig;
 use massa_execution_exports::ExecutionStackElement;
+use massa_models::execution::ExecutionTraceStep;
 use massa_models::{timeslots::get_block_slot_timestamp, Address, Amount, Slot};
 use massa_sc_runtime::{Interface, InterfaceClone};
 use parking_lot::Mutex;
@@ -57,9 +58,12 @@ impl InterfaceClone for InterfaceImpl {
 /// See the massa-sc-runtime crate for a functional description of the trait and its methods.
 /// Note that massa-sc-runtime uses basic types (`str` for addresses, `u64` for amounts...) for genericity.
 impl Interface for InterfaceImpl {
-    /// prints a message in the node logs at log level 3 (debug)
+    /// prints a message in the node logs at log level 3 (debug), and in the trace of a traced execution
     fn print(&self, message: &str) -> Result<()> {
         debug!("SC print: {}", message);
+        context_guard!(self).trace_step(ExecutionTraceStep::Print {
+            message: message.to_string(),
+        });
         Ok(())
     }
 
@@ -113,6 +117,7 @@ impl Interface for InterfaceImpl {
             coins,
             owned_addresses: vec![to_address],
         });
+        context.trace_enter_call(to_address, coins);
 
         // return the target bytecode
         Ok(bytecode)
@@ -126,6 +131,7 @@ impl Interface for InterfaceImpl {
         if context.stack.pop().is_none() {
             bail!("call stack out of bounds")
         }
+        context.trace_exit_call();
 
         Ok(())
     }
@@ -184,10 +190,16 @@ impl Interface for InterfaceImpl {
     /// # Returns
     /// The datastore value matching the provided key, if found, otherwise an error.
     fn raw_get_data_for(&self, address: &str, key: &str) -> Result<Vec<u8>> {
-        let addr = &massa_models::Address::from_bs58_check(address)?;
-        let key = massa_hash::Hash::compute_from(key.as_bytes());
-        let context = context_guard!(self);
-        match context.get_data_entry(addr, &key) {
+        let addr = massa_models::Address::from_bs58_check(address)?;
+        let key_hash = massa_hash::Hash::compute_from(key.as_bytes());
+        let mut context = context_guard!(self);
+        let value = context.get_data_entry(&addr, &key_hash);
+        context.trace_step(ExecutionTraceStep::DatastoreRead {
+            address: addr,
+            key: key.to_string(),
+            value: value.clone(),
+        });
+        match value {
             Some(value) => Ok(value),
             _ => bail!("data entry not found"),
         }
@@ -201,9 +213,14 @@ impl Interface for InterfaceImpl {
     /// * value: new value to set
     fn raw_set_data_for(&self, address: &str, key: &str, value: &[u8]) -> Result<()> {
         let addr = massa_models::Address::from_str(address)?;
-        let key = massa_hash::Hash::compute_from(key.as_bytes());
+        let key_hash = massa_hash::Hash::compute_from(key.as_bytes());
         let mut context = context_guard!(self);
-        context.set_data_entry(&addr, key, value.to_vec())?;
+        context.set_data_entry(&addr, key_hash, value.to_vec())?;
+        context.trace_step(ExecutionTraceStep::DatastoreWrite {
+            address: addr,
+            key: key.to_string(),
+            value: value.to_vec(),
+        });
         Ok(())
     }
 
@@ -230,10 +247,16 @@ impl Interface for InterfaceImpl {
     /// # Returns
     /// The datastore value matching the provided key, if found, otherwise an error.
     fn raw_get_data(&self, key: &str) -> Result<Vec<u8>> {
-        let key = massa_hash::Hash::compute_from(key.as_bytes());
-        let context = context_guard!(self);
+        let key_hash = massa_hash::Hash::compute_from(key.as_bytes());
+        let mut context = context_guard!(self);
         let addr = context.get_current_address()?;
-        match context.get_data_entry(&addr, &key) {
+        let value = context.get_data_entry(&addr, &key_hash);
+        context.trace_step(ExecutionTraceStep::DatastoreRead {
+            address: addr,
+            key: key.to_string(),
+            value: value.clone(),
+        });
+        match value {
             Some(data) => Ok(data),
             _ => bail!("data entry not found"),
         }
@@ -246,10 +269,15 @@ impl Interface for InterfaceImpl {
     /// * key: string key of the datastore entry to set
     /// * value: new value to set
     fn raw_set_data(&self, key: &str, value: &[u8]) -> Result<()> {
-        let key = massa_hash::Hash::compute_from(key.as_bytes());
+        let key_hash = massa_hash::Hash::compute_from(key.as_bytes());
         let mut context = context_guard!(self);
         let addr = context.get_current_address()?;
-        context.set_data_entry(&addr, key, value.to_vec())?;
+        context.set_data_entry(&addr, key_hash, value.to_vec())?;
+        context.trace_step(ExecutionTraceStep::DatastoreWrite {
+            address: addr,
+            key: key.to_string(),
+            value: value.to_vec(),
+        });
         Ok(())
     }
 
@@ -416,7 +444,8 @@ impl Interface for InterfaceImpl {
         Ok(context_guard!(self).unsafe_rng.sample(distr))
     }
 
-    /// Adds an asynchronous message to the context speculative asynchronous pool
+    /// Adds an asynchronous message to the context speculative asynchronous pool.
+    /// The coins of the message and its gas deposit (`max_gas * gas_price`) are spent by the sender.
     ///
     /// # Arguments
     /// * `target_address`: Destination address hash in format string
@@ -444,15 +473,21 @@ impl Interface for InterfaceImpl {
         if validity_end.1 >= self.config.thread_count {
             bail!("validity end thread exceeds the configuration thread count")
         }
+        if max_gas
+            > self
+                .config
+                .max_async_gas_per_sender
+                .min(self.config.max_async_gas)
+        {
+            bail!("message max gas exceeds the asynchronous gas available to a sender at each slot")
+        }
+        let destination = Address::from_str(target_address)?;
         let mut execution_context = context_guard!(self);
-        let emission_slot = execution_context.slot;
-        let emission_index = execution_context.created_message_index;
-        let sender = execution_context.get_current_address()?;
-        execution_context.push_new_message(AsyncMessage {
-            emission_slot,
-            emission_index,
-            sender,
-            destination: Address::from_str(target_address)?,
+        let msg = AsyncMessage {
+            emission_slot: execution_context.slot,
+            emission_index: execution_context.created_message_index,
+            sender: execution_context.get_current_address()?,
+            destination,
             handler: target_handler.to_string(),
             validity_start: Slot::new(validity_start.0, validity_start.1),
             validity_end: Slot::new(validity_end.0, validity_end.1),
@@ -460,7 +495,33 @@ impl Interface for InterfaceImpl {
             gas_price: Amount::from_raw(gas_price),
             coins: Amount::from_raw(raw_coins),
             data: data.to_vec(),
-        });
+        };
+        // the message ID is computed before spending coins so that a failure leaves no effect
+        let traced_message_id = match execution_context.trace {
+            Some(_) => Some(async_message_id_to_string(&msg.compute_id())?),
+            None => None,
+        };
+        let spent = match msg
+            .gas_price
+            .checked_mul_u64(msg.max_gas)
+            .and_then(|gas_deposit| gas_deposit.checked_add(msg.coins))
+        {
+            Some(spent) => spent,
+            None => bail!("message coins and gas deposit overflow"),
+        };
+        if let Err(err) = execution_context.transfer_parallel_coins(Some(msg.sender), None, spent) {
+            bail!("couldn't spend message coins and gas deposit: {}", err)
+        }
+        if let Some(message_id) = traced_message_id {
+            execution_context.trace_step(ExecutionTraceStep::MessageSent {
+                message_id,
+                destination: msg.destination,
+                handler: msg.handler.clone(),
+                max_gas: msg.max_gas,
+                coins: msg.coins,
+            });
+        }
+        execution_context.push_new_message(msg);
         execution_context.created_message_index += 1;
         Ok(())
     }
@@ -64,6 +64,7 @@ impl SpeculativeAsyncPool {
     /// # Arguments
     /// * `slot`: slot at which the batch is taken (allows filtering by validity interval)
     /// * `max_gas`: maximum amount of gas available
+    /// * `max_gas_per_sender`: maximum amount of gas available to the messages of a single sender
     ///
     /// # Returns
     /// A vector of `AsyncMessage` to execute
@@ -71,9 +72,12 @@ impl SpeculativeAsyncPool {
         &mut self,
         slot: Slot,
         max_gas: u64,
+        max_gas_per_sender: u64,
     ) -> Vec<(AsyncMessageId, AsyncMessage)> {
         // take a batch of messages, removing it from the async pool
-        let msgs = self.async_pool.take_batch_to_execute(slot, max_gas);
+        let msgs = self
+            .async_pool
+            .take_batch_to_execute(slot, max_gas, max_gas_per_sender);
 
         // settle deletions
         for (msg_id, _msg) in &msgs {
@@ -4,13 +4,20 @@
 //! the state of the ledger at an arbitrary execution slot.
 //! It never actually writes to the consensus state
 //! but keeps track of the changes that were applied to it since its creation.
+//!
+//! Writing bytecode or datastore entries locks a storage deposit,
+//! proportional to the number of stored bytes, from the balance of a payer address.
+//! The locked coins are recorded in the ledger entry storing the bytes.
+//! Shrinking that storage refunds the deposit of the freed bytes to the payer,
+//! without exceeding the deposit recorded in the entry.
 
 use massa_execution_exports::ExecutionError;
 use massa_final_state::FinalState;
 use massa_hash::Hash;
-use massa_ledger::{Applicable, LedgerChanges};
+use massa_ledger::{datastore_entry_storage_size, Applicable, LedgerChanges};
 use massa_models::{Address, Amount};
 use parking_lot::RwLock;
+use std::cmp::Ordering;
 use std::sync::Arc;
 
 /// The `SpeculativeLedger` contains an thread-safe shared reference to the final ledger (read-only),
@@ -33,6 +40,9 @@ pub struct SpeculativeLedger {
 
     /// list of ledger changes that were applied to this `SpeculativeLedger` since its creation
     added_changes: LedgerChanges,
+
+    /// deposit locked for each stored byte of bytecode or datastore entry
+    storage_byte_cost: Amount,
 }
 
 impl SpeculativeLedger {
@@ -41,11 +51,17 @@ impl SpeculativeLedger {
     /// # Arguments
     /// * `final_state`: thread-safe shared access to the final state (for reading only)
     /// * `previous_changes`: accumulation of changes that previously happened to the ledger since finality
-    pub fn new(final_state: Arc<RwLock<FinalState>>, previous_changes: LedgerChanges) -> Self {
+    /// * `storage_byte_cost`: deposit locked for each stored byte of bytecode or datastore entry
+    pub fn new(
+        final_state: Arc<RwLock<FinalState>>,
+        previous_changes: LedgerChanges,
+        storage_byte_cost: Amount,
+    ) -> Self {
         SpeculativeLedger {
             final_state,
             previous_changes,
             added_changes: Default::default(),
+            storage_byte_cost,
         }
     }
 
@@ -160,16 +176,97 @@ impl SpeculativeLedger {
         })
     }
 
+    /// Gets the effective storage deposit of an address
+    ///
+    /// # Arguments:
+    /// `addr`: the address to query
+    ///
+    /// # Returns
+    /// The coins locked by the storage of the address, zero if the address was not found
+    pub fn get_storage_deposit(&self, addr: &Address) -> Amount {
+        // try to read from added_changes, then previous_changes, then ledger in final_state
+        self.added_changes
+            .get_storage_deposit_or_else(addr, || {
+                self.previous_changes.get_storage_deposit_or_else(addr, || {
+                    self.final_state.read().ledger.get_storage_deposit(addr)
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Locks or refunds the storage deposit of a change in the number of bytes stored by an address.
+    /// The locked coins are recorded in the storage deposit of the address,
+    /// and freed bytes are refunded within the limit of that deposit
+    /// so that storage written without paying a deposit is never refunded.
+    /// No changes are retained in case of failure.
+    ///
+    /// # Arguments
+    /// * `payer`: address paying the deposit of new bytes and refunded for freed bytes
+    /// * `addr`: address storing the bytes
+    /// * `old_size`: number of stored bytes before the change
+    /// * `new_size`: number of stored bytes after the change
+    fn update_storage_deposit(
+        &mut self,
+        payer: &Address,
+        addr: &Address,
+        old_size: usize,
+        new_size: usize,
+    ) -> Result<(), ExecutionError> {
+        // deposit of a number of bytes
+        let bytes_deposit = |byte_count: usize| {
+            self.storage_byte_cost
+                .checked_mul_u64(byte_count as u64)
+                .ok_or_else(|| ExecutionError::RuntimeError("storage deposit overflow".into()))
+        };
+        let storage_deposit = self.get_storage_deposit(addr);
+        let (from_addr, to_addr, amount, new_storage_deposit) = match new_size.cmp(&old_size) {
+            Ordering::Equal => return Ok(()),
+            Ordering::Greater => {
+                let deposit = bytes_deposit(new_size - old_size)?;
+                let new_storage_deposit =
+                    storage_deposit.checked_add(deposit).ok_or_else(|| {
+                        ExecutionError::RuntimeError("storage deposit overflow".into())
+                    })?;
+                (Some(*payer), None, deposit, new_storage_deposit)
+            }
+            Ordering::Less => {
+                let refund = std::cmp::min(bytes_deposit(old_size - new_size)?, storage_deposit);
+                (
+                    None,
+                    Some(*payer),
+                    refund,
+                    storage_deposit.saturating_sub(refund),
+                )
+            }
+        };
+        self.transfer_parallel_coins(from_addr, to_addr, amount)
+            .map_err(|err| {
+                ExecutionError::RuntimeError(format!(
+                    "could not update the storage deposit of {} from {} to {} bytes: {}",
+                    addr, old_size, new_size, err
+                ))
+            })?;
+        self.added_changes
+            .set_storage_deposit(*addr, new_storage_deposit);
+        Ok(())
+    }
+
     /// Creates a new smart contract address with initial bytecode.
+    /// The storage deposit of the bytecode is paid by `payer`.
     ///
     /// # Arguments
+    /// * `payer`: address paying the storage deposit
     /// * `addr`: address to create
     /// * `bytecode`: bytecode to set in the new ledger entry
     pub fn create_new_sc_address(
         &mut self,
+        payer: &Address,
         addr: Address,
         bytecode: Vec<u8>,
     ) -> Result<(), ExecutionError> {
+        // lock the storage deposit of the bytecode
+        self.update_storage_deposit(payer, &addr, 0, bytecode.len())?;
+
         // set bytecode (create if do not exist)
         self.added_changes.set_bytecode(addr, bytecode);
         Ok(())
@@ -177,13 +274,15 @@ impl SpeculativeLedger {
 
     /// Sets the bytecode associated to an address in the ledger.
     /// Fails if the address doesn't exist.
+    /// The storage deposit of the size difference with the previous bytecode is paid by or refunded to `payer`.
     ///
     /// # Arguments
+    /// * `payer`: address paying the storage deposit
     /// * `addr`: target address
     /// * `bytecode`: bytecode to set for that address
-    #[allow(dead_code)] // TODO remove when it is used
     pub fn set_bytecode(
         &mut self,
+        payer: &Address,
         addr: &Address,
         bytecode: Vec<u8>,
     ) -> Result<(), ExecutionError> {
@@ -195,6 +294,10 @@ impl SpeculativeLedger {
             )));
         }
 
+        // update the storage deposit
+        let old_size = self.get_bytecode(addr).unwrap_or_default().len();
+        self.update_storage_deposit(payer, addr, old_size, bytecode.len())?;
+
         // set the bytecode of that address
         self.added_changes.set_bytecode(*addr, bytecode);
 
@@ -238,13 +341,16 @@ impl SpeculativeLedger {
     /// Sets a data set entry for a given address in the ledger.
     /// Fails if the address doesn't exist.
     /// If the datastore entry does not exist, it is created.
+    /// The storage deposit of the size difference with the previous entry is paid by or refunded to `payer`.
     ///
     /// # Arguments
+    /// * `payer`: address paying the storage deposit
     /// * `addr`: target address
     /// * `key`: datastore key
     /// * `data`: value to associate to the datastore key
     pub fn set_data_entry(
         &mut self,
+        payer: &Address,
         addr: &Address,
         key: Hash,
         data: Vec<u8>,
@@ -257,6 +363,12 @@ impl SpeculativeLedger {
             )));
         }
 
+        // update the storage deposit
+        let old_size = self
+            .get_data_entry(addr, &key)
+            .map_or(0, |value| datastore_entry_storage_size(&value));
+        self.update_storage_deposit(payer, addr, old_size, datastore_entry_storage_size(&data))?;
+
         // set data
         self.added_changes.set_data_entry(*addr, key, data);
 
@@ -1,22 +1,30 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
+use crate::context::ExecutionContext;
+use crate::execution::ExecutionState;
+use crate::interface_impl::InterfaceImpl;
 use crate::start_execution_worker;
-use massa_async_pool::AsyncPoolConfig;
+use massa_async_pool::{async_message_id_to_string, AsyncMessage, AsyncPoolConfig};
 use massa_execution_exports::{
-    ExecutionConfig, ExecutionError, ReadOnlyExecutionRequest, ReadOnlyExecutionTarget,
+    ExecutionConfig, ExecutionError, ExecutionOutput, ExecutionStackElement,
+    ReadOnlyExecutionRequest, ReadOnlyExecutionTarget,
 };
-use massa_final_state::{FinalState, FinalStateConfig};
+use massa_final_state::{FinalState, FinalStateConfig, StateChanges};
 use massa_hash::Hash;
-use massa_ledger::{LedgerConfig, LedgerError};
+use massa_ledger::{LedgerConfig, LedgerEntry, LedgerError, SetUpdateOrDelete};
 use massa_models::{
-    api::EventFilter,
+    api::{AddressStateOverride, EventFilter, ReadOnlyBaseState, ReadOnlyStateOverrides},
     constants::{AMOUNT_DECIMAL_FACTOR, FINAL_HISTORY_LENGTH, THREAD_COUNT},
+    execution::{ExecutionReceiptOrigin, ExecutionTraceStep},
+    output_event::{AsyncMessageOutcome, AsyncMessageStatus},
+    signed::Signable,
     Block, BlockHeader, BlockId, Operation, OperationType, SerializeCompact, SignedHeader,
     SignedOperation,
 };
 use massa_models::{Address, Amount, Slot};
+use massa_sc_runtime::Interface;
 use massa_signature::{derive_public_key, generate_random_private_key, PrivateKey, PublicKey};
 use massa_storage::Storage;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serial_test::serial;
 use std::{
     cmp::Reverse,
@@ -25,7 +33,7 @@ use std::{
     sync::Arc,
     time::Duration,
 };
-use tempfile::NamedTempFile;
+use tempfile::{NamedTempFile, TempDir};
 
 /// Same as `get_random_address()` and return `priv_key` and `pub_key` associated
 /// to the address.
@@ -40,12 +48,19 @@ pub fn get_random_address() -> Address {
     get_random_address_full().0
 }
 
-fn get_sample_state() -> Result<(Arc<RwLock<FinalState>>, NamedTempFile), LedgerError> {
+fn get_sample_state() -> Result<(Arc<RwLock<FinalState>>, NamedTempFile, TempDir), LedgerError> {
     let mut initial: BTreeMap<Address, Amount> = Default::default();
     initial.insert(get_random_address(), Amount::from_str("129").unwrap());
     initial.insert(get_random_address(), Amount::from_str("878").unwrap());
-    let (ledger_config, tempfile) = LedgerConfig::sample(&initial);
-    let async_pool_config = AsyncPoolConfig { max_length: 100 };
+    get_initial_state(&initial, AsyncPoolConfig { max_length: 100 })
+}
+
+/// Get a final state starting from the `initial` ledger balances
+fn get_initial_state(
+    initial: &BTreeMap<Address, Amount>,
+    async_pool_config: AsyncPoolConfig,
+) -> Result<(Arc<RwLock<FinalState>>, NamedTempFile, TempDir), LedgerError> {
+    let (ledger_config, tempfile, tempdir) = LedgerConfig::sample(initial);
     let cfg = FinalStateConfig {
         ledger_config,
         async_pool_config,
@@ -55,13 +70,14 @@ fn get_sample_state() -> Result<(Arc<RwLock<FinalState>>, NamedTempFile), Ledger
     Ok((
         Arc::new(RwLock::new(FinalState::new(cfg).unwrap())),
         tempfile,
+        tempdir,
     ))
 }
 
 #[test]
 #[serial]
 fn test_execution_basic() {
-    let (sample_state, _keep) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     let (_, _) =
         start_execution_worker(ExecutionConfig::default(), sample_state, Default::default());
 }
@@ -69,7 +85,7 @@ fn test_execution_basic() {
 #[test]
 #[serial]
 fn test_execution_shutdown() {
-    let (sample_state, _keep) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     let (mut manager, _) =
         start_execution_worker(ExecutionConfig::default(), sample_state, Default::default());
     manager.stop()
@@ -78,7 +94,7 @@ fn test_execution_shutdown() {
 #[test]
 #[serial]
 fn test_sending_command() {
-    let (sample_state, _keep) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     let (mut manager, controller) =
         start_execution_worker(ExecutionConfig::default(), sample_state, Default::default());
     controller.update_blockclique_status(Default::default(), Default::default());
@@ -88,7 +104,7 @@ fn test_sending_command() {
 #[test]
 #[serial]
 fn test_sending_read_only_execution_command() {
-    let (sample_state, _keep) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     let (mut manager, controller) =
         start_execution_worker(ExecutionConfig::default(), sample_state, Default::default());
     controller
@@ -99,11 +115,43 @@ fn test_sending_read_only_execution_command() {
             target: ReadOnlyExecutionTarget::BytecodeExecution(
                 include_bytes!("./wasm/event_test.wasm").to_vec(),
             ),
+            trace: false,
+            overrides: Default::default(),
+            base_state: Default::default(),
         })
         .unwrap();
     manager.stop()
 }
 
+#[test]
+#[serial]
+fn test_sending_read_only_execution_batch() {
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
+    let (mut manager, controller) =
+        start_execution_worker(ExecutionConfig::default(), sample_state, Default::default());
+    let req = ReadOnlyExecutionRequest {
+        max_gas: 1_000_000,
+        simulated_gas_price: Amount::from_raw(1_000_000 * AMOUNT_DECIMAL_FACTOR),
+        call_stack: vec![],
+        target: ReadOnlyExecutionTarget::BytecodeExecution(
+            include_bytes!("./wasm/event_test.wasm").to_vec(),
+        ),
+        trace: false,
+        overrides: Default::default(),
+        base_state: Default::default(),
+    };
+    let outputs = controller
+        .execute_readonly_requests(vec![req.clone(), req])
+        .unwrap();
+    assert_eq!(outputs.len(), 2);
+    let slots: Vec<Slot> = outputs
+        .into_iter()
+        .map(|output| output.unwrap().slot)
+        .collect();
+    assert_eq!(slots[0], slots[1]);
+    manager.stop()
+}
+
 /// Test the gas usage in nested calls using call SC operation
 ///
 /// Create a smart contract and send it in the blockclique.
@@ -121,7 +169,7 @@ fn test_nested_call_gas_usage() {
         ..ExecutionConfig::default()
     };
     // get a sample final state
-    let (sample_state, _) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     // init the storage
     let storage = Storage::default();
     // start the execution worker
@@ -236,7 +284,7 @@ fn send_and_receive_async_message() {
         ..ExecutionConfig::default()
     };
     // get a sample final state
-    let (sample_state, _) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     // init the storage
     let storage = Storage::default();
     // start the execution worker
@@ -272,7 +320,13 @@ fn send_and_receive_async_message() {
     });
     // match the events
     assert!(!events.is_empty(), "One event was expected");
-    assert_eq!(events[0].data, "message received: hello my good friend!")
+    assert_eq!(events[0].data, "message received: hello my good friend!");
+    // the execution of the message is signaled by an outcome event
+    let outcomes = controller.get_filtered_sc_output_event(EventFilter {
+        async_message_status: Some(AsyncMessageStatus::Executed),
+        ..Default::default()
+    });
+    assert_eq!(outcomes.len(), 1, "One message outcome event was expected");
 }
 
 #[test]
@@ -286,7 +340,7 @@ fn generate_events() {
         ..ExecutionConfig::default()
     };
     let storage: Storage = Default::default();
-    let (sample_state, _keep) = get_sample_state().unwrap();
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
     let (mut manager, controller) = start_execution_worker(exec_cfg, sample_state, storage.clone());
 
     let (sender_address, sender_private_key, sender_public_key) = get_random_address_full();
@@ -320,6 +374,844 @@ fn generate_events() {
     assert!(!events.is_empty(), "At least one event was expected")
 }
 
+/// Initial balance of the senders of the asynchronous messages sent in the following tests
+const SENDER_BALANCE: &str = "1000";
+
+/// Create an active execution context at `slot`, on top of the `previous_changes` of the slots executed before it,
+/// and in which `sender` is the current address.
+/// Return an interface to that context, as used by the bytecode executed at that slot.
+fn create_sender_interface(
+    final_state: Arc<RwLock<FinalState>>,
+    slot: Slot,
+    previous_changes: StateChanges,
+    sender: Address,
+) -> (InterfaceImpl, Arc<Mutex<ExecutionContext>>) {
+    let config = ExecutionConfig::default();
+    let mut context =
+        ExecutionContext::active_slot(&config, slot, None, previous_changes, final_state);
+    context.stack = vec![ExecutionStackElement {
+        address: sender,
+        coins: Amount::zero(),
+        owned_addresses: vec![sender],
+    }];
+    let context = Arc::new(Mutex::new(context));
+    (InterfaceImpl::new(config, context.clone()), context)
+}
+
+/// Send a message carrying 10 raw coins, with a gas deposit of `1000 * raw_gas_price` raw coins,
+/// and valid from slot (1, 0) until `validity_end`.
+fn send_sample_message(interface: &InterfaceImpl, raw_gas_price: u64, validity_end: (u64, u8)) {
+    let destination = get_random_address();
+    interface
+        .send_message(
+            &destination.to_bs58_check(),
+            "receive",
+            (1, 0),
+            validity_end,
+            1000,
+            raw_gas_price,
+            10,
+            b"hello",
+        )
+        .unwrap();
+}
+
+/// Get the parallel balance of `addr` set by the ledger changes of an execution output, if any
+fn get_output_balance(output: &ExecutionOutput, addr: &Address) -> Option<Amount> {
+    output
+        .state_changes
+        .ledger_changes
+        .get_parallel_balance_or_else(addr, || None)
+}
+
+/// An expired message is refunded with its coins and its gas deposit
+#[test]
+#[serial]
+fn async_message_expiry_refund() {
+    let (sender, _, _) = get_random_address_full();
+    let initial_balance = Amount::from_str(SENDER_BALANCE).unwrap();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(sender, initial_balance)]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+
+    // the message is valid at slot (1, 0) only
+    let (interface, context) = create_sender_interface(
+        final_state.clone(),
+        Slot::new(1, 0),
+        Default::default(),
+        sender,
+    );
+    send_sample_message(&interface, 2, (1, 1));
+    let output = context.lock().settle_slot();
+    assert_eq!(
+        get_output_balance(&output, &sender),
+        Some(initial_balance.saturating_sub(Amount::from_raw(2010)))
+    );
+
+    // the message expires when slot (1, 1) is settled
+    let (_interface, context) =
+        create_sender_interface(final_state, Slot::new(1, 1), output.state_changes, sender);
+    let output = context.lock().settle_slot();
+    assert_eq!(get_output_balance(&output, &sender), Some(initial_balance));
+}
+
+/// A message evicted from the full pool is refunded with its coins and its gas deposit
+#[test]
+#[serial]
+fn async_message_eviction_refund() {
+    let (sender, _, _) = get_random_address_full();
+    let initial_balance = Amount::from_str(SENDER_BALANCE).unwrap();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(sender, initial_balance)]),
+        AsyncPoolConfig { max_length: 1 },
+    )
+    .unwrap();
+
+    // the pool only keeps the message with the highest gas price
+    let (interface, context) =
+        create_sender_interface(final_state, Slot::new(1, 0), Default::default(), sender);
+    send_sample_message(&interface, 2, (10, 0));
+    send_sample_message(&interface, 1, (10, 0));
+    let output = context.lock().settle_slot();
+    assert_eq!(
+        get_output_balance(&output, &sender),
+        Some(initial_balance.saturating_sub(Amount::from_raw(2010)))
+    );
+}
+
+/// Create a message from `sender` to the `handler` of `destination` carrying 10 raw coins,
+/// with a gas deposit of `100_000 * 2` raw coins, and valid from slot (0, 0) until `validity_end`.
+fn create_sample_message(
+    sender: Address,
+    destination: Address,
+    handler: &str,
+    emission_index: u64,
+    validity_end: Slot,
+) -> AsyncMessage {
+    AsyncMessage {
+        emission_slot: Slot::new(0, 0),
+        emission_index,
+        sender,
+        destination,
+        handler: handler.to_string(),
+        max_gas: 100_000,
+        gas_price: Amount::from_raw(2),
+        coins: Amount::from_raw(10),
+        validity_start: Slot::new(0, 0),
+        validity_end,
+        data: b"hello".to_vec(),
+    }
+}
+
+/// Finalize slot (1, 0) with `messages` added to the pool and the given `(address, bytecode)` entries created,
+/// and return an execution state on top of it, ready to execute slot (1, 1).
+fn create_message_execution_state(
+    final_state: Arc<RwLock<FinalState>>,
+    bytecodes: Vec<(Address, Vec<u8>)>,
+    messages: Vec<AsyncMessage>,
+) -> ExecutionState {
+    let mut changes = StateChanges::default();
+    for (addr, bytecode) in bytecodes {
+        changes.ledger_changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Set(LedgerEntry {
+                bytecode,
+                ..Default::default()
+            }),
+        );
+    }
+    for msg in messages {
+        changes.async_pool_changes.push_add(msg.compute_id(), msg);
+    }
+    final_state.write().finalize(Slot::new(1, 0), changes);
+    ExecutionState::new(ExecutionConfig::default(), final_state, Default::default())
+}
+
+/// Get the outcomes of the asynchronous messages reported by the events of an execution output
+fn get_output_message_outcomes(output: &ExecutionOutput) -> Vec<AsyncMessageOutcome> {
+    output
+        .events
+        .iter()
+        .filter_map(|event| event.async_message_outcome.clone())
+        .collect()
+}
+
+/// A message that fails to execute and a message that expires are reported by events with their refund
+#[test]
+#[serial]
+fn async_message_failure_and_expiry_events() {
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let sender = get_random_address();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(sender, Amount::from_str(SENDER_BALANCE).unwrap())]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+
+    // the first message targets an address without ledger entry,
+    // the second one is not valid anymore at slot (1, 1)
+    let destination = get_random_address();
+    let failed = create_sample_message(sender, destination, "receive", 0, Slot::new(10, 0));
+    let expired = create_sample_message(sender, destination, "receive", 1, Slot::new(1, 1));
+    let execution_state = create_message_execution_state(
+        final_state,
+        Vec::new(),
+        vec![failed.clone(), expired.clone()],
+    );
+    let output = execution_state.execute_slot(Slot::new(1, 1), None);
+
+    // both are refunded with their coins and their gas deposit
+    let refund = Amount::from_raw(200_010);
+    let outcomes = get_output_message_outcomes(&output);
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(
+        outcomes[0].message_id,
+        async_message_id_to_string(&failed.compute_id()).unwrap()
+    );
+    assert_eq!(outcomes[0].status, AsyncMessageStatus::ExecutionFailed);
+    assert!(outcomes[0]
+        .reason
+        .as_ref()
+        .unwrap()
+        .contains("no target bytecode found"));
+    assert_eq!(outcomes[0].refund, refund);
+    assert_eq!(
+        outcomes[1].message_id,
+        async_message_id_to_string(&expired.compute_id()).unwrap()
+    );
+    assert_eq!(outcomes[1].status, AsyncMessageStatus::Expired);
+    assert_eq!(outcomes[1].reason, None);
+    assert_eq!(outcomes[1].refund, refund);
+    assert_eq!(
+        get_output_balance(&output, &sender),
+        Some(
+            Amount::from_str(SENDER_BALANCE)
+                .unwrap()
+                .saturating_add(refund)
+                .saturating_add(refund)
+        )
+    );
+}
+
+/// A message whose handler runs successfully is reported by an event, without refund
+#[test]
+#[serial]
+fn async_message_executed_event() {
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let sender = get_random_address();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(sender, Amount::from_str(SENDER_BALANCE).unwrap())]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+
+    // the destination stores a bytecode exporting the `receive` handler
+    let destination = get_random_address();
+    let message = create_sample_message(sender, destination, "receive", 0, Slot::new(10, 0));
+    let execution_state = create_message_execution_state(
+        final_state,
+        vec![(
+            destination,
+            include_bytes!("./wasm/receive_message.wasm").to_vec(),
+        )],
+        vec![message.clone()],
+    );
+    let output = execution_state.execute_slot(Slot::new(1, 1), None);
+
+    let outcomes = get_output_message_outcomes(&output);
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(
+        outcomes[0].message_id,
+        async_message_id_to_string(&message.compute_id()).unwrap()
+    );
+    assert_eq!(outcomes[0].status, AsyncMessageStatus::Executed);
+    assert_eq!(outcomes[0].reason, None);
+    assert_eq!(outcomes[0].refund, Amount::zero());
+    assert_eq!(get_output_balance(&output, &sender), None);
+    assert!(output
+        .events
+        .iter()
+        .any(|event| event.data.starts_with("message received")));
+}
+
+/// Each operation of a block gets a receipt telling whether it succeeded and which coins it moved
+#[test]
+#[serial]
+fn operation_receipts() {
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let (sender, sender_private_key, sender_public_key) = get_random_address_full();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(sender, Amount::from_str(SENDER_BALANCE).unwrap())]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+
+    // the first operation sends coins the sender has, the second one sends more than what is left
+    let target = get_random_address();
+    let sent = create_coin_call_operation(
+        sender_private_key,
+        sender_public_key,
+        target,
+        Amount::from_str("100").unwrap(),
+    )
+    .unwrap();
+    let overdrawn = create_coin_call_operation(
+        sender_private_key,
+        sender_public_key,
+        target,
+        Amount::from_str(SENDER_BALANCE).unwrap(),
+    )
+    .unwrap();
+    let storage = Storage::default();
+    let (block_id, block) =
+        create_block(vec![sent.clone(), overdrawn.clone()], Slot::new(1, 0)).unwrap();
+    storage.store_block(block_id, block, Vec::new());
+    let execution_state = ExecutionState::new(ExecutionConfig::default(), final_state, storage);
+    let output = execution_state.execute_slot(Slot::new(1, 0), Some(block_id));
+
+    let receipts = &output.receipts;
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(
+        receipts[0].origin,
+        ExecutionReceiptOrigin::Operation(sent.content.compute_id().unwrap())
+    );
+    assert_eq!(receipts[0].slot, Slot::new(1, 0));
+    assert_eq!(receipts[0].block, Some(block_id));
+    assert!(receipts[0].success);
+    assert_eq!(receipts[0].error, None);
+    assert_eq!(receipts[0].coins, Amount::from_str("100").unwrap());
+    assert_eq!(
+        receipts[1].origin,
+        ExecutionReceiptOrigin::Operation(overdrawn.content.compute_id().unwrap())
+    );
+    assert!(!receipts[1].success);
+    assert!(receipts[1]
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("failed to transfer"));
+    assert_eq!(receipts[1].coins, Amount::zero());
+    assert_eq!(
+        get_output_balance(&output, &target),
+        Some(Amount::from_str("100").unwrap())
+    );
+}
+
+/// Create a read-only execution context at slot (1, 0) running on top of the `caller` address,
+/// as `execute_readonly_request` does before running the requested bytecode.
+/// Return an interface to that context, as used by the executed bytecode.
+fn create_readonly_interface(
+    final_state: Arc<RwLock<FinalState>>,
+    caller: Address,
+    trace: bool,
+) -> (InterfaceImpl, Arc<Mutex<ExecutionContext>>) {
+    let config = ExecutionConfig::default();
+    let context = ExecutionContext::readonly(
+        &config,
+        Slot::new(1, 0),
+        1_000_000,
+        Amount::zero(),
+        vec![ExecutionStackElement {
+            address: caller,
+            coins: Amount::zero(),
+            owned_addresses: vec![caller],
+        }],
+        trace,
+        Default::default(),
+        final_state,
+    );
+    let context = Arc::new(Mutex::new(context));
+    (InterfaceImpl::new(config, context.clone()), context)
+}
+
+/// A traced read-only execution records its datastore accesses, coin transfers, sent messages and prints in order,
+/// and an untraced one records nothing
+#[test]
+#[serial]
+fn traced_execution_steps() {
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let (caller, _, _) = get_random_address_full();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(caller, Amount::from_str(SENDER_BALANCE).unwrap())]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+    let target = get_random_address();
+
+    let (interface, context) = create_readonly_interface(final_state.clone(), caller, true);
+    interface.raw_set_data("key", b"value").unwrap();
+    interface.raw_get_data("key").unwrap();
+    assert!(interface.raw_get_data("missing").is_err());
+    interface
+        .transfer_coins(&target.to_bs58_check(), 10)
+        .unwrap();
+    interface
+        .send_message(
+            &target.to_bs58_check(),
+            "receive",
+            (1, 0),
+            (10, 0),
+            1000,
+            2,
+            10,
+            b"hello",
+        )
+        .unwrap();
+    interface.print("done").unwrap();
+    let trace = context.lock().settle_slot().trace.unwrap();
+    let message_id = async_message_id_to_string(
+        &AsyncMessage {
+            emission_slot: Slot::new(1, 0),
+            emission_index: 0,
+            sender: caller,
+            destination: target,
+            handler: "receive".to_string(),
+            validity_start: Slot::new(1, 0),
+            validity_end: Slot::new(10, 0),
+            max_gas: 1000,
+            gas_price: Amount::from_raw(2),
+            coins: Amount::from_raw(10),
+            data: b"hello".to_vec(),
+        }
+        .compute_id(),
+    )
+    .unwrap();
+
+    assert_eq!(trace.address, caller);
+    assert_eq!(trace.coins, Amount::zero());
+    match trace.steps.as_slice() {
+        [ExecutionTraceStep::DatastoreWrite {
+            address: write_address,
+            key: write_key,
+            value: write_value,
+        }, ExecutionTraceStep::DatastoreRead {
+            address: read_address,
+            key: read_key,
+            value: Some(read_value),
+        }, ExecutionTraceStep::DatastoreRead {
+            key: missing_key,
+            value: None,
+            ..
+        }, ExecutionTraceStep::CoinTransfer {
+            from: Some(transfer_from),
+            to: Some(transfer_to),
+            amount: transfer_amount,
+        }, ExecutionTraceStep::CoinTransfer {
+            from: Some(spender),
+            to: None,
+            amount: spent,
+        }, ExecutionTraceStep::MessageSent {
+            message_id: sent_id,
+            destination,
+            handler,
+            max_gas,
+            coins,
+        }, ExecutionTraceStep::Print { message }] => {
+            assert_eq!((write_address, write_key.as_str()), (&caller, "key"));
+            assert_eq!(write_value, b"value");
+            assert_eq!((read_address, read_key.as_str()), (&caller, "key"));
+            assert_eq!(read_value, b"value");
+            assert_eq!(missing_key, "missing");
+            assert_eq!((transfer_from, transfer_to), (&caller, &target));
+            assert_eq!(*transfer_amount, Amount::from_raw(10));
+            assert_eq!(spender, &caller);
+            assert_eq!(*spent, Amount::from_raw(2010));
+            assert_eq!(sent_id, &message_id);
+            assert_eq!((destination, handler.as_str()), (&target, "receive"));
+            assert_eq!((*max_gas, *coins), (1000, Amount::from_raw(10)));
+            assert_eq!(message, "done");
+        }
+        steps => panic!("unexpected trace steps: {:?}", steps),
+    }
+
+    let (interface, context) = create_readonly_interface(final_state, caller, false);
+    interface.raw_set_data("key", b"value").unwrap();
+    interface.print("done").unwrap();
+    assert!(context.lock().settle_slot().trace.is_none());
+}
+
+/// The steps of a nested call are recorded in a frame of its own, added to the frame of the caller when the call ends
+#[test]
+#[serial]
+fn traced_nested_call() {
+    let (caller, _, _) = get_random_address_full();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(caller, Amount::from_str(SENDER_BALANCE).unwrap())]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+
+    let (interface, context) = create_readonly_interface(final_state, caller, true);
+    let callee = interface.create_module(b"callee").unwrap();
+    interface.init_call(&callee, 0).unwrap();
+    interface.print("in callee").unwrap();
+    interface.finish_call().unwrap();
+    interface.print("back in caller").unwrap();
+    let trace = context.lock().settle_slot().trace.unwrap();
+
+    assert_eq!(trace.address, caller);
+    match trace.steps.as_slice() {
+        [.., ExecutionTraceStep::Call(frame), ExecutionTraceStep::Print { message }] => {
+            assert_eq!(frame.address.to_bs58_check(), callee);
+            assert_eq!(frame.coins, Amount::zero());
+            assert!(matches!(
+                frame.steps.as_slice(),
+                [ExecutionTraceStep::Print { message }] if message == "in callee"
+            ));
+            assert_eq!(message, "back in caller");
+        }
+        steps => panic!("unexpected trace steps: {:?}", steps),
+    }
+}
+
+/// Create a read-only request executing an empty bytecode on top of `base_state`, with `overrides` applied
+fn create_readonly_request(
+    overrides: ReadOnlyStateOverrides,
+    base_state: ReadOnlyBaseState,
+) -> ReadOnlyExecutionRequest {
+    ReadOnlyExecutionRequest {
+        max_gas: 1_000_000,
+        simulated_gas_price: Amount::zero(),
+        call_stack: vec![],
+        target: ReadOnlyExecutionTarget::BytecodeExecution(Vec::new()),
+        trace: false,
+        overrides,
+        base_state,
+    }
+}
+
+/// The state overrides of a read-only request change the state seen by the execution,
+/// without being part of its output
+#[test]
+#[serial]
+fn readonly_state_overrides() {
+    let (final_state, _keep_file, _keep_dir) =
+        get_initial_state(&BTreeMap::new(), AsyncPoolConfig { max_length: 100 }).unwrap();
+    let target = get_random_address();
+    let hash = |key: &str| Hash::compute_from(key.as_bytes());
+    let mut changes = StateChanges::default();
+    changes.ledger_changes.0.insert(
+        target,
+        SetUpdateOrDelete::Set(LedgerEntry {
+            parallel_balance: Amount::from_str("1000").unwrap(),
+            bytecode: b"old code".to_vec(),
+            datastore: BTreeMap::from([
+                (hash("key"), b"old".to_vec()),
+                (hash("gone"), b"old".to_vec()),
+            ]),
+            ..Default::default()
+        }),
+    );
+    final_state.write().finalize(Slot::new(1, 0), changes);
+    let execution_state =
+        ExecutionState::new(ExecutionConfig::default(), final_state, Default::default());
+
+    // without overrides, the execution sees the state after the latest slot
+    let context = execution_state
+        .create_readonly_context(&create_readonly_request(
+            Default::default(),
+            Default::default(),
+        ))
+        .unwrap();
+    assert_eq!(context.slot, Slot::new(1, 1));
+    assert_eq!(
+        context.get_parallel_balance(&target),
+        Some(Amount::from_str("1000").unwrap())
+    );
+    assert_eq!(context.get_bytecode(&target), Some(b"old code".to_vec()));
+    assert_eq!(
+        context.get_data_entry(&target, &hash("key")),
+        Some(b"old".to_vec())
+    );
+    assert_eq!(
+        context.get_data_entry(&target, &hash("gone")),
+        Some(b"old".to_vec())
+    );
+
+    // with overrides, it sees the overridden entry and slot
+    let overrides = ReadOnlyStateOverrides {
+        slot: Some(Slot::new(7, 1)),
+        addresses: vec![AddressStateOverride {
+            address: target,
+            balance: Some(Amount::from_raw(5)),
+            bytecode: Some(b"new code".to_vec()),
+            datastore: BTreeMap::from([
+                ("key".to_string(), Some(b"new".to_vec())),
+                ("gone".to_string(), None),
+                ("added".to_string(), Some(b"new".to_vec())),
+            ]),
+        }],
+    };
+    let mut context = execution_state
+        .create_readonly_context(&create_readonly_request(overrides, Default::default()))
+        .unwrap();
+    assert_eq!(context.slot, Slot::new(7, 1));
+    assert_eq!(
+        context.get_parallel_balance(&target),
+        Some(Amount::from_raw(5))
+    );
+    assert_eq!(context.get_bytecode(&target), Some(b"new code".to_vec()));
+    assert_eq!(
+        context.get_data_entry(&target, &hash("key")),
+        Some(b"new".to_vec())
+    );
+    assert_eq!(context.get_data_entry(&target, &hash("gone")), None);
+    assert_eq!(
+        context.get_data_entry(&target, &hash("added")),
+        Some(b"new".to_vec())
+    );
+    let output = context.settle_slot();
+    assert!(output.state_changes.ledger_changes.0.is_empty());
+
+    // the overriding slot must be in an existing thread
+    let overrides = ReadOnlyStateOverrides {
+        slot: Some(Slot::new(7, THREAD_COUNT)),
+        addresses: Vec::new(),
+    };
+    assert!(execution_state
+        .create_readonly_context(&create_readonly_request(overrides, Default::default()))
+        .is_err());
+}
+
+/// A read-only execution sees the latest candidate state, the latest final state,
+/// or the state after a slot of the execution history, depending on the base state it requests
+#[test]
+#[serial]
+fn readonly_base_state() {
+    massa_models::init_serialization_context(massa_models::SerializationContext::default());
+    let (sender, sender_private_key, sender_public_key) = get_random_address_full();
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(sender, Amount::from_str(SENDER_BALANCE).unwrap())]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+    let storage = Storage::default();
+    let mut execution_state =
+        ExecutionState::new(ExecutionConfig::default(), final_state, storage.clone());
+
+    // the sender sends 100 coins at slot (1, 0) then 200 coins at slot (1, 1), in blocks that are not final yet
+    let target = get_random_address();
+    for (slot, coins) in [(Slot::new(1, 0), "100"), (Slot::new(1, 1), "200")] {
+        let operation = create_coin_call_operation(
+            sender_private_key,
+            sender_public_key,
+            target,
+            Amount::from_str(coins).unwrap(),
+        )
+        .unwrap();
+        let (block_id, block) = create_block(vec![operation], slot).unwrap();
+        storage.store_block(block_id, block, Vec::new());
+        let output = execution_state.execute_slot(slot, Some(block_id));
+        execution_state.apply_active_execution_output(output);
+    }
+
+    for (base_state, slot, balance) in [
+        (ReadOnlyBaseState::Candidate, Slot::new(2, 0), "700"),
+        (ReadOnlyBaseState::Final, Slot::new(1, 0), "1000"),
+        (
+            ReadOnlyBaseState::AfterSlot(Slot::new(0, 1)),
+            Slot::new(1, 0),
+            "1000",
+        ),
+        (
+            ReadOnlyBaseState::AfterSlot(Slot::new(1, 0)),
+            Slot::new(1, 1),
+            "900",
+        ),
+        (
+            ReadOnlyBaseState::AfterSlot(Slot::new(1, 1)),
+            Slot::new(2, 0),
+            "700",
+        ),
+    ] {
+        let context = execution_state
+            .create_readonly_context(&create_readonly_request(Default::default(), base_state))
+            .unwrap();
+        assert_eq!(context.slot, slot);
+        assert_eq!(
+            context.get_parallel_balance(&sender),
+            Some(Amount::from_str(balance).unwrap())
+        );
+    }
+
+    // slots out of the execution history are rejected
+    for slot in [Slot::new(0, 0), Slot::new(2, 0)] {
+        assert!(execution_state
+            .create_readonly_context(&create_readonly_request(
+                Default::default(),
+                ReadOnlyBaseState::AfterSlot(slot),
+            ))
+            .is_err());
+    }
+}
+
+/// The coins sent with a read-only call are credited to the target before running the called function
+#[test]
+#[serial]
+fn readonly_call_coins() {
+    let (sample_state, _keep_file, _keep_dir) = get_sample_state().unwrap();
+    let (mut manager, controller) =
+        start_execution_worker(ExecutionConfig::default(), sample_state, Default::default());
+    let (caller, target) = (get_random_address(), get_random_address());
+    let coins = Amount::from_raw(10);
+    let output = controller
+        .execute_readonly_request(ReadOnlyExecutionRequest {
+            max_gas: 1_000_000,
+            simulated_gas_price: Amount::zero(),
+            call_stack: vec![
+                ExecutionStackElement {
+                    address: caller,
+                    coins: Amount::zero(),
+                    owned_addresses: vec![caller],
+                },
+                ExecutionStackElement {
+                    address: target,
+                    coins,
+                    owned_addresses: vec![target],
+                },
+            ],
+            target: ReadOnlyExecutionTarget::FunctionCall {
+                target_addr: target,
+                target_func: "receive".into(),
+                parameter: "hello".into(),
+                coins,
+            },
+            trace: false,
+            overrides: ReadOnlyStateOverrides {
+                slot: None,
+                addresses: vec![AddressStateOverride {
+                    address: target,
+                    balance: Some(Amount::from_raw(5)),
+                    bytecode: Some(include_bytes!("./wasm/receive_message.wasm").to_vec()),
+                    datastore: Default::default(),
+                }],
+            },
+            base_state: Default::default(),
+        })
+        .unwrap();
+    assert_eq!(
+        get_output_balance(&output, &target),
+        Some(Amount::from_raw(15))
+    );
+    manager.stop();
+}
+
+/// Get the storage deposit of `addr` set by the ledger changes of an execution output, if any
+fn get_output_storage_deposit(output: &ExecutionOutput, addr: &Address) -> Option<Amount> {
+    output
+        .state_changes
+        .ledger_changes
+        .get_storage_deposit_or_else(addr, || None)
+}
+
+/// Storing bytes locks a deposit that is refunded when they are freed
+#[test]
+#[serial]
+fn storage_deposit_growth_shrink() {
+    let (addr, _, _) = get_random_address_full();
+    let initial_balance = Amount::from_str(SENDER_BALANCE).unwrap();
+    let byte_cost = ExecutionConfig::default().storage_byte_cost;
+    let (final_state, _keep_file, _keep_dir) = get_initial_state(
+        &BTreeMap::from([(addr, initial_balance)]),
+        AsyncPoolConfig { max_length: 100 },
+    )
+    .unwrap();
+    let (interface, context) =
+        create_sender_interface(final_state, Slot::new(1, 0), Default::default(), addr);
+    let deposit_of = |byte_count: u64| byte_cost.checked_mul_u64(byte_count).unwrap();
+    let balance_after = |deposit: Amount| initial_balance.checked_sub(deposit).unwrap().to_raw();
+
+    // a datastore entry locks the deposit of its key and its value
+    interface.raw_set_data("key", &[1; 10]).unwrap();
+    assert_eq!(
+        interface.get_balance().unwrap(),
+        balance_after(deposit_of(42))
+    );
+
+    // growing it locks the deposit of the new bytes
+    interface.raw_set_data("key", &[1; 20]).unwrap();
+    assert_eq!(
+        interface.get_balance().unwrap(),
+        balance_after(deposit_of(52))
+    );
+
+    // shrinking it refunds the deposit of the freed bytes
+    interface.raw_set_data("key", &[1; 5]).unwrap();
+    assert_eq!(
+        interface.get_balance().unwrap(),
+        balance_after(deposit_of(37))
+    );
+
+    // the bytecode locks a deposit as well
+    interface.raw_set_bytecode(&[1; 100]).unwrap();
+    assert_eq!(
+        interface.get_balance().unwrap(),
+        balance_after(deposit_of(137))
+    );
+
+    // the paid deposit is recorded in the ledger entry
+    let output = context.lock().settle_slot();
+    assert_eq!(
+        get_output_storage_deposit(&output, &addr),
+        Some(deposit_of(137))
+    );
+}
+
+/// Freeing bytes stored without paying a deposit refunds nothing
+#[test]
+#[serial]
+fn storage_deposit_pre_existing_data() {
+    let (addr, _, _) = get_random_address_full();
+    let initial_balance = Amount::from_str(SENDER_BALANCE).unwrap();
+    let byte_cost = ExecutionConfig::default().storage_byte_cost;
+    let (final_state, _keep_file, _keep_dir) =
+        get_initial_state(&BTreeMap::new(), AsyncPoolConfig { max_length: 100 }).unwrap();
+
+    // the entry already stores bytecode and data, without deposit
+    let key = Hash::compute_from(b"key");
+    let mut changes = StateChanges::default();
+    changes.ledger_changes.0.insert(
+        addr,
+        SetUpdateOrDelete::Set(LedgerEntry {
+            parallel_balance: initial_balance,
+            bytecode: vec![1; 100],
+            datastore: BTreeMap::from([(key, vec![1; 50])]),
+            ..Default::default()
+        }),
+    );
+    let slot = {
+        let mut final_state = final_state.write();
+        let slot = final_state.slot.get_next_slot(THREAD_COUNT).unwrap();
+        final_state.finalize(slot, changes);
+        slot.get_next_slot(THREAD_COUNT).unwrap()
+    };
+    let (interface, context) = create_sender_interface(final_state, slot, Default::default(), addr);
+
+    // shrinking pre-existing storage refunds nothing
+    interface.raw_set_data("key", &[1; 5]).unwrap();
+    interface.raw_set_bytecode(&[]).unwrap();
+    assert_eq!(interface.get_balance().unwrap(), initial_balance.to_raw());
+
+    // growing it locks the deposit of the new bytes
+    interface.raw_set_data("key", &[1; 10]).unwrap();
+    let deposit = byte_cost.checked_mul_u64(5).unwrap();
+    assert_eq!(
+        interface.get_balance().unwrap(),
+        initial_balance.checked_sub(deposit).unwrap().to_raw()
+    );
+
+    // shrinking it again only refunds the paid deposit
+    interface.raw_set_data("key", &[1; 5]).unwrap();
+    assert_eq!(interface.get_balance().unwrap(), initial_balance.to_raw());
+    let output = context.lock().settle_slot();
+    assert_eq!(
+        get_output_storage_deposit(&output, &addr),
+        Some(Amount::zero())
+    );
+}
+
 /// Create an operation for the given sender with `data` as bytecode.
 /// Return a result that should be unwrapped in the root `#[test]` routine.
 fn create_execute_sc_operation(
@@ -377,6 +1269,36 @@ fn create_call_sc_operation(
     Ok(op)
 }
 
+/// Create a `CallSC` operation for the given sender that sends `parallel_coins` to `target_addr`
+/// without calling any function.
+/// Return a result that should be unwrapped in the root `#[test]` routine.
+fn create_coin_call_operation(
+    sender_private_key: PrivateKey,
+    sender_public_key: PublicKey,
+    target_addr: Address,
+    parallel_coins: Amount,
+) -> Result<SignedOperation, ExecutionError> {
+    let op = OperationType::CallSC {
+        max_gas: 0,
+        target_addr,
+        parallel_coins,
+        sequential_coins: Amount::zero(),
+        gas_price: Amount::zero(),
+        target_func: String::new(),
+        param: String::new(),
+    };
+    let (_, op) = SignedOperation::new_signed(
+        Operation {
+            sender_public_key,
+            fee: Amount::zero(),
+            expire_period: 10,
+            op,
+        },
+        &sender_private_key,
+    )?;
+    Ok(op)
+}
+
 /// Create an almost empty block with a vector `operations` and a random
 /// creator.
 ///
@@ -46,8 +46,9 @@ pub(crate) struct ExecutionThread {
     last_active_slot: Slot,
     // Execution state (see execution.rs) to which execution requests are sent
     execution_state: Arc<RwLock<ExecutionState>>,
-    /// queue for read-only requests and response MPSCs to send back their outputs
-    readonly_requests: RequestQueue<ReadOnlyExecutionRequest, ExecutionOutput>,
+    /// queue for batches of read-only requests and response MPSCs to send back their outputs
+    readonly_requests:
+        RequestQueue<Vec<ReadOnlyExecutionRequest>, Vec<Result<ExecutionOutput, ExecutionError>>>,
 }
 
 impl ExecutionThread {
@@ -373,26 +374,35 @@ impl ExecutionThread {
     /// Cancel those that are in excess if there are too many.
     fn update_readonly_requests(
         &mut self,
-        new_requests: RequestQueue<ReadOnlyExecutionRequest, ExecutionOutput>,
+        new_requests: RequestQueue<
+            Vec<ReadOnlyExecutionRequest>,
+            Vec<Result<ExecutionOutput, ExecutionError>>,
+        >,
     ) {
         // Append incoming readonly requests to our readonly request queue
         // Excess requests are cancelled
         self.readonly_requests.extend(new_requests);
     }
 
-    /// Executes a read-only request from the queue, if any.
-    /// The result of the execution is sent asynchronously through the response channel provided with the request.
+    /// Executes a batch of read-only requests from the queue, if any.
+    /// The results of the executions are sent asynchronously through the response channel provided with the batch.
     ///
     /// # Returns
-    /// true if a request was executed, false otherwise
+    /// true if a batch was executed, false otherwise
     fn execute_one_readonly_request(&mut self) -> bool {
         if let Some(req_resp) = self.readonly_requests.pop() {
-            let (req, resp_tx) = req_resp.into_request_sender_pair();
-
-            // Acquire read access to the execution state and execute the read-only request
-            let outcome = self.execution_state.read().execute_readonly_request(req);
-
-            // Send the execution output through resp_tx.
+            let (reqs, resp_tx) = req_resp.into_request_sender_pair();
+
+            // Acquire read access to the execution state once for the whole batch,
+            // so that all its requests are executed on top of the same state
+            let exec_state = self.execution_state.read();
+            let outcome = Ok(reqs
+                .into_iter()
+                .map(|req| exec_state.execute_readonly_request(req))
+                .collect());
+            drop(exec_state);
+
+            // Send the execution outputs through resp_tx.
             // Ignore errors because they just mean that the request emitter dropped the received
             // because it doesn't need the response anymore.
             let _ = resp_tx.send(outcome);
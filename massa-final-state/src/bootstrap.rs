@@ -1,64 +1,142 @@
 //! Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-//! Provides serializable structures for bootstrapping the `FinalState`
+//! Provides serializable structures for streaming the `FinalState` to bootstrapping nodes
 
+use crate::StateChanges;
 use massa_async_pool::AsyncPoolBootstrap;
+use massa_hash::{Hash, HASH_SIZE_BYTES};
 use massa_ledger::FinalLedgerBootstrapState;
-use massa_models::{DeserializeCompact, SerializeCompact, Slot};
-use serde::{Deserialize, Serialize};
-
-/// Represents a snapshot of the final state,
-/// which is enough to fully bootstrap a `FinalState`
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FinalStateBootstrap {
-    /// slot at the output of which the state is attached
+use massa_models::{
+    array_from_slice, u8_from_slice, DeserializeCompact, DeserializeVarInt, ModelsError,
+    SerializeCompact, SerializeVarInt, Slot,
+};
+
+/// Represents a part of the final state streamed to a bootstrapping node.
+///
+/// The ledger is sent in consecutive address-ordered parts.
+/// Each part also carries the final state changes that happened since the previous part was sent,
+/// so that the client ends up with a final state attached to a single slot.
+#[derive(Debug, Clone)]
+pub struct FinalStatePart {
+    /// slot at the output of which the ledger part and the changes are attached
     pub(crate) slot: Slot,
-    /// final ledger
-    pub(crate) ledger: FinalLedgerBootstrapState,
-    /// asynchronous pool
-    pub(crate) async_pool: AsyncPoolBootstrap,
+    /// next ledger entries, in address order
+    pub(crate) ledger_part: FinalLedgerBootstrapState,
+    /// final state changes between the slot of the previous part and `slot`, sorted by slot
+    pub(crate) final_state_changes: Vec<(Slot, StateChanges)>,
+    /// asynchronous pool, only sent with the last part once the whole ledger was sent
+    pub(crate) async_pool: Option<AsyncPoolBootstrap>,
+    /// root hash of the state tree at the output of `slot`, sent along with the asynchronous pool
+    /// so that the client can check the final state it rebuilt
+    pub(crate) state_root: Option<Hash>,
+}
+
+impl FinalStatePart {
+    /// Gets the slot at the output of which the part is attached
+    pub fn get_slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Checks whether this is the last part of the final state
+    pub fn is_last(&self) -> bool {
+        self.async_pool.is_some()
+    }
 }
 
-/// Allows serializing the `FinalStateBootstrap` to a compact binary representation
-impl SerializeCompact for FinalStateBootstrap {
-    fn to_bytes_compact(&self) -> Result<Vec<u8>, massa_models::ModelsError> {
+/// Allows serializing the `FinalStatePart` to a compact binary representation
+impl SerializeCompact for FinalStatePart {
+    fn to_bytes_compact(&self) -> Result<Vec<u8>, ModelsError> {
         let mut res: Vec<u8> = Vec::new();
 
-        // final slot
+        // slot
         res.extend(self.slot.to_bytes_compact()?);
 
-        // final ledger
-        res.extend(self.ledger.to_bytes_compact()?);
+        // ledger part
+        res.extend(self.ledger_part.to_bytes_compact()?);
 
-        // final async pool
-        res.extend(self.async_pool.to_bytes_compact()?);
+        // final state changes
+        let changes_count: u64 = self.final_state_changes.len().try_into().map_err(|_| {
+            ModelsError::SerializeError("could not represent changes count as u64".into())
+        })?;
+        res.extend(changes_count.to_varint_bytes());
+        for (slot, changes) in &self.final_state_changes {
+            res.extend(slot.to_bytes_compact()?);
+            res.extend(changes.to_bytes_compact()?);
+        }
+
+        // async pool and state root
+        match (&self.async_pool, &self.state_root) {
+            (Some(async_pool), Some(state_root)) => {
+                res.push(1u8);
+                res.extend(async_pool.to_bytes_compact()?);
+                res.extend(state_root.to_bytes());
+            }
+            (None, None) => res.push(0u8),
+            _ => {
+                return Err(ModelsError::SerializeError(
+                    "the async pool and the state root must be sent together".into(),
+                ))
+            }
+        }
 
         Ok(res)
     }
 }
 
-/// Allows deserializing a `FinalStateBootstrap` from its compact binary representation
-impl DeserializeCompact for FinalStateBootstrap {
-    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), massa_models::ModelsError> {
+/// Allows deserializing a `FinalStatePart` from its compact binary representation
+impl DeserializeCompact for FinalStatePart {
+    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), ModelsError> {
         let mut cursor = 0usize;
 
-        // final slot
+        // slot
         let (slot, delta) = Slot::from_bytes_compact(&buffer[cursor..])?;
         cursor += delta;
 
-        // final ledger
-        let (ledger, delta) = FinalLedgerBootstrapState::from_bytes_compact(&buffer[cursor..])?;
+        // ledger part
+        let (ledger_part, delta) =
+            FinalLedgerBootstrapState::from_bytes_compact(&buffer[cursor..])?;
         cursor += delta;
 
-        // final async pool
-        let (async_pool, delta) = AsyncPoolBootstrap::from_bytes_compact(&buffer[cursor..])?;
+        // final state changes
+        let (changes_count, delta) = u64::from_varint_bytes(&buffer[cursor..])?;
+        // TODO cap changes count https://github.com/massalabs/massa/issues/1200
         cursor += delta;
+        let mut final_state_changes = Vec::new();
+        for _ in 0..changes_count {
+            let (changes_slot, delta) = Slot::from_bytes_compact(&buffer[cursor..])?;
+            cursor += delta;
+            let (changes, delta) = StateChanges::from_bytes_compact(&buffer[cursor..])?;
+            cursor += delta;
+            final_state_changes.push((changes_slot, changes));
+        }
+
+        // async pool and state root
+        let has_async_pool = u8_from_slice(&buffer[cursor..])?;
+        cursor += 1;
+        let (async_pool, state_root) = match has_async_pool {
+            0u8 => (None, None),
+            1u8 => {
+                let (async_pool, delta) =
+                    AsyncPoolBootstrap::from_bytes_compact(&buffer[cursor..])?;
+                cursor += delta;
+                let state_root = Hash::from_bytes(&array_from_slice(&buffer[cursor..])?)?;
+                cursor += HASH_SIZE_BYTES;
+                (Some(async_pool), Some(state_root))
+            }
+            _ => {
+                return Err(ModelsError::DeserializeError(
+                    "invalid async pool flag".into(),
+                ))
+            }
+        };
 
         Ok((
-            FinalStateBootstrap {
+            FinalStatePart {
                 slot,
-                ledger,
+                ledger_part,
+                final_state_changes,
                 async_pool,
+                state_root,
             },
             cursor,
         ))
@@ -11,4 +11,12 @@ use thiserror::Error;
 pub enum FinalStateError {
     /// ledger error: {0}
     LedgerError(String),
+    /// could not load the final state from disk: {0}
+    LoadError(String),
+    /// the received final state does not match the state root of the bootstrap server: {0}
+    StateRootMismatch(String),
+    /// the final state history does not allow answering the query: {0}
+    HistoryError(String),
+    /// invalid final state snapshot: {0}
+    SnapshotError(String),
 }
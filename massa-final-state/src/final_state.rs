@@ -4,20 +4,99 @@
 //! the final ledger and asynchronous message pool that are kept at
 //! the output of a given final slot (the latest executed final slot),
 //! and need to be bootstrapped by nodes joining the network.
+//!
+//! The final state is persisted on disk along with the final ledger:
+//! its slot, asynchronous pool and changes history are stored as ledger metadata
+//! which are written in the same atomic batch as the ledger changes of each finalized slot.
+//! This allows a node to resume from its last final slot after a restart.
+//!
+//! Along with the changes history, the final state keeps the reverse ledger changes of the same recent slots.
+//! Applying them backwards from the current ledger gives the final ledger values at the output of those past slots.
+//!
+//! The final state also maintains a state tree committing to its contents (see `state_tree.rs`),
+//! whose root hash is kept for each recent final slot. Nodes can compare those roots to detect a divergence.
+//! The tree nodes overwritten by the recent slots are kept as well, so that proofs can be built against the past roots.
 
 use crate::{
-    bootstrap::FinalStateBootstrap, config::FinalStateConfig, error::FinalStateError,
+    bootstrap::FinalStatePart,
+    config::FinalStateConfig,
+    error::FinalStateError,
     state_changes::StateChanges,
+    state_tree::{
+        add_async_pool_changes_leaves, add_entry_leaves, add_ledger_changes_leaves,
+        get_state_proof, get_state_root, get_state_tree_undo, state_tree_undo_from_bytes,
+        state_tree_undo_to_bytes, update_state_tree, StateTreeLeaves, StateTreeUndo,
+    },
 };
-use massa_async_pool::AsyncPool;
-use massa_ledger::{Applicable, FinalLedger};
-use massa_models::Slot;
-use std::collections::VecDeque;
+use massa_async_pool::{AsyncMessage, AsyncMessageId, AsyncPool, AsyncPoolChanges, Change};
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_ledger::{Applicable, FinalLedger, LedgerChanges, SetUpdateOrDelete};
+use massa_models::{
+    api::LedgerEntryProof,
+    constants::SLOT_KEY_SIZE,
+    state_proof::{balance_leaf_key, bytecode_leaf_key, data_leaf_key},
+    Address, Amount, DeserializeCompact, SerializeCompact, Slot,
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Metadata key of the slot at the output of which the final state is attached
+const SLOT_KEY: [u8; 1] = [0u8];
+/// Metadata key prefix of the messages of the asynchronous pool
+const ASYNC_POOL_PREFIX: u8 = 1u8;
+/// Metadata key prefix of the elements of the changes history
+const HISTORY_PREFIX: u8 = 2u8;
+// the metadata key prefix 3 is used by the nodes of the state tree
+/// Metadata key prefix of the state roots of the recent final slots
+const STATE_ROOT_PREFIX: u8 = 4u8;
+/// Metadata key prefix of the elements of the reverse ledger changes history
+const LEDGER_UNDO_PREFIX: u8 = 5u8;
+/// Metadata key prefix of the elements of the reverse state tree changes history
+const STATE_TREE_UNDO_PREFIX: u8 = 6u8;
+
+/// Metadata key of an asynchronous message
+fn async_message_key(id: &AsyncMessageId) -> Vec<u8> {
+    let (Reverse(score), slot, index) = id;
+    let mut key = vec![ASYNC_POOL_PREFIX];
+    key.extend(score.to_raw().to_be_bytes());
+    key.extend(slot.to_bytes_key());
+    key.extend(index.to_be_bytes());
+    key
+}
+
+/// Metadata key of the changes history element of a slot.
+/// Keys are sorted by slot so that the history can be read back in order.
+fn history_key(slot: &Slot) -> Vec<u8> {
+    let mut key = vec![HISTORY_PREFIX];
+    key.extend(slot.to_bytes_key());
+    key
+}
+
+/// Metadata key of the state root of a slot
+fn state_root_key(slot: &Slot) -> Vec<u8> {
+    let mut key = vec![STATE_ROOT_PREFIX];
+    key.extend(slot.to_bytes_key());
+    key
+}
+
+/// Metadata key of the reverse ledger changes of a slot
+fn ledger_undo_key(slot: &Slot) -> Vec<u8> {
+    let mut key = vec![LEDGER_UNDO_PREFIX];
+    key.extend(slot.to_bytes_key());
+    key
+}
+
+/// Metadata key of the reverse state tree changes of a slot
+fn state_tree_undo_key(slot: &Slot) -> Vec<u8> {
+    let mut key = vec![STATE_TREE_UNDO_PREFIX];
+    key.extend(slot.to_bytes_key());
+    key
+}
 
 /// Represents a final state `(ledger, async pool)`
 pub struct FinalState {
     /// execution state configuration
-    config: FinalStateConfig,
+    pub(crate) config: FinalStateConfig,
     /// slot at the output of which the state is attached
     pub slot: Slot,
     /// final ledger associating addresses to their balance, executable bytecode and data
@@ -27,6 +106,15 @@ pub struct FinalState {
     /// history of recent final state changes, useful for streaming bootstrap
     /// `front = oldest`, `back = newest`
     changes_history: VecDeque<(Slot, StateChanges)>,
+    /// changes reverting the ledger changes of the slots of the changes history, useful for historical queries
+    /// `front = oldest`, `back = newest`
+    ledger_undo_history: VecDeque<(Slot, LedgerChanges)>,
+    /// state tree nodes overwritten by the slots of the changes history, useful for historical proofs
+    /// `front = oldest`, `back = newest`
+    state_tree_undo_history: VecDeque<(Slot, StateTreeUndo)>,
+    /// root hashes of the state tree at the output of the recent final slots, the current one included
+    /// `front = oldest`, `back = newest`
+    state_roots: VecDeque<(Slot, Hash)>,
 }
 
 impl FinalState {
@@ -35,59 +123,604 @@ impl FinalState {
     /// # Arguments
     /// * `config`: the configuration of the execution state
     pub fn new(config: FinalStateConfig) -> Result<Self, FinalStateError> {
-        // attach at the output of the latest initial final slot, that is the last genesis slot
-        let slot = Slot::new(0, config.thread_count.saturating_sub(1));
-
         // load the initial final ledger from file
         let ledger = FinalLedger::new(config.ledger_config.clone()).map_err(|err| {
             FinalStateError::LedgerError(format!("could not initialize ledger: {}", err))
         })?;
 
+        Ok(FinalState::from_initial_ledger(config, ledger))
+    }
+
+    /// Builds the `FinalState` of a ledger that was just reset to the initial ledger
+    fn from_initial_ledger(config: FinalStateConfig, ledger: FinalLedger) -> Self {
+        // attach at the output of the latest initial final slot, that is the last genesis slot
+        let slot = Slot::new(0, config.thread_count.saturating_sub(1));
+
         // create the async pool
         let async_pool = AsyncPool::new(config.async_pool_config.clone());
 
+        // build the state tree of the initial ledger
+        let mut leaves = StateTreeLeaves::new();
+        for (addr, entry) in ledger.get_ledger_part(None, usize::MAX).get_entries() {
+            add_entry_leaves(addr, entry, &mut leaves);
+        }
+        let mut metadata = BTreeMap::new();
+        let state_root = update_state_tree(&ledger, leaves, &mut metadata);
+
         // generate the final ledger
-        Ok(FinalState {
+        let mut final_state = FinalState {
             slot,
             ledger,
             async_pool,
             config,
             changes_history: Default::default(), // no changes in history
-        })
+            ledger_undo_history: Default::default(),
+            state_tree_undo_history: Default::default(),
+            state_roots: VecDeque::from([(slot, state_root)]),
+        };
+        final_state.save_metadata(metadata);
+        final_state
     }
 
-    /// Initialize a `FinalState` from a bootstrap state
+    /// Loads the `FinalState` that was saved on disk by a previous run of the node.
     ///
     /// # Arguments
     /// * `config`: final state configuration
-    /// * `state`: bootstrap state
-    pub fn from_bootstrap_state(config: FinalStateConfig, state: FinalStateBootstrap) -> Self {
-        FinalState {
-            slot: state.slot,
-            ledger: FinalLedger::from_bootstrap_state(config.ledger_config.clone(), state.ledger),
-            async_pool: AsyncPool::from_bootstrap_snapshot(
-                config.async_pool_config.clone(),
-                state.async_pool,
-            ),
+    ///
+    /// # Returns
+    /// The final state attached at the output of the last slot finalized before the node stopped,
+    /// or an error if no complete final state was found on disk.
+    pub fn load(config: FinalStateConfig) -> Result<Self, FinalStateError> {
+        // open the final ledger without resetting it
+        let ledger = FinalLedger::load(config.ledger_config.clone()).map_err(|err| {
+            FinalStateError::LedgerError(format!("could not open ledger: {}", err))
+        })?;
+
+        FinalState::from_saved_ledger(config, ledger).map_err(|(err, _ledger)| err)
+    }
+
+    /// Loads the `FinalState` that was saved on disk by a previous run of the node if there is a complete one,
+    /// otherwise initializes a new `FinalState` from the initial ledger.
+    /// The ledger database is opened only once in both cases:
+    /// sled releases the lock of a closed database asynchronously, so it cannot be reopened right away.
+    ///
+    /// # Arguments
+    /// * `config`: final state configuration
+    ///
+    /// # Returns
+    /// The final state, along with the reason why the saved final state could not be resumed from, if any
+    pub fn load_or_new(
+        config: FinalStateConfig,
+    ) -> Result<(Self, Option<FinalStateError>), FinalStateError> {
+        let ledger = FinalLedger::load(config.ledger_config.clone()).map_err(|err| {
+            FinalStateError::LedgerError(format!("could not open ledger: {}", err))
+        })?;
+
+        match FinalState::from_saved_ledger(config.clone(), ledger) {
+            Ok(final_state) => Ok((final_state, None)),
+            Err((load_err, mut ledger)) => {
+                ledger.reset_to_initial_ledger().map_err(|err| {
+                    FinalStateError::LedgerError(format!("could not initialize ledger: {}", err))
+                })?;
+                Ok((
+                    FinalState::from_initial_ledger(config, ledger),
+                    Some(load_err),
+                ))
+            }
+        }
+    }
+
+    /// Reads the final state saved along with an opened ledger.
+    /// On failure, the ledger is given back with the error so that its database can be reused.
+    fn from_saved_ledger(
+        config: FinalStateConfig,
+        ledger: FinalLedger,
+    ) -> Result<Self, (FinalStateError, FinalLedger)> {
+        let async_pool = AsyncPool::new(config.async_pool_config.clone());
+        let mut final_state = FinalState {
+            slot: Slot::new(0, 0),
+            ledger,
+            async_pool,
             config,
-            changes_history: Default::default(), // no changes in history
+            changes_history: Default::default(),
+            ledger_undo_history: Default::default(),
+            state_tree_undo_history: Default::default(),
+            state_roots: Default::default(),
+        };
+        match final_state.read_saved_state() {
+            Ok(()) => Ok(final_state),
+            Err(err) => Err((err, final_state.ledger)),
         }
     }
 
-    /// Gets a snapshot of the state to bootstrap other nodes
-    pub fn get_bootstrap_state(&self) -> FinalStateBootstrap {
-        FinalStateBootstrap {
+    /// Reads the slot, the asynchronous pool, the histories and the state roots saved as ledger metadata
+    fn read_saved_state(&mut self) -> Result<(), FinalStateError> {
+        let ledger = &self.ledger;
+
+        // read the slot at the output of which the saved state is attached
+        let slot_bytes = ledger
+            .get_metadata(&SLOT_KEY)
+            .ok_or_else(|| FinalStateError::LoadError("no final slot found on disk".into()))?;
+        let (slot, _) = Slot::from_bytes_compact(&slot_bytes)
+            .map_err(|err| FinalStateError::LoadError(format!("invalid final slot: {}", err)))?;
+        if slot.thread >= self.config.thread_count {
+            return Err(FinalStateError::LoadError(format!(
+                "final slot {} does not match the thread count",
+                slot
+            )));
+        }
+
+        // read the asynchronous pool
+        let mut async_pool_changes = AsyncPoolChanges::default();
+        for (_key, msg_bytes) in ledger.get_metadata_by_prefix(&[ASYNC_POOL_PREFIX]) {
+            let (msg, _) = AsyncMessage::from_bytes_compact(&msg_bytes).map_err(|err| {
+                FinalStateError::LoadError(format!("invalid async message: {}", err))
+            })?;
+            async_pool_changes.push_add(msg.compute_id(), msg);
+        }
+        let mut async_pool = AsyncPool::new(self.config.async_pool_config.clone());
+        async_pool.apply_changes_unchecked(async_pool_changes);
+
+        // read the changes history, sorted by slot
+        let mut changes_history = VecDeque::new();
+        for (key, changes_bytes) in ledger.get_metadata_by_prefix(&[HISTORY_PREFIX]) {
+            let slot_key: [u8; SLOT_KEY_SIZE] = key[1..]
+                .try_into()
+                .map_err(|_| FinalStateError::LoadError("invalid changes history key".into()))?;
+            let (changes, _) = StateChanges::from_bytes_compact(&changes_bytes).map_err(|err| {
+                FinalStateError::LoadError(format!("invalid changes history element: {}", err))
+            })?;
+            changes_history.push_back((Slot::from_bytes_key(&slot_key), changes));
+        }
+
+        // read the reverse ledger changes history, sorted by slot
+        let mut ledger_undo_history = VecDeque::new();
+        for (key, changes_bytes) in ledger.get_metadata_by_prefix(&[LEDGER_UNDO_PREFIX]) {
+            let slot_key: [u8; SLOT_KEY_SIZE] = key[1..].try_into().map_err(|_| {
+                FinalStateError::LoadError("invalid reverse ledger changes key".into())
+            })?;
+            let (changes, _) =
+                LedgerChanges::from_bytes_compact(&changes_bytes).map_err(|err| {
+                    FinalStateError::LoadError(format!("invalid reverse ledger changes: {}", err))
+                })?;
+            ledger_undo_history.push_back((Slot::from_bytes_key(&slot_key), changes));
+        }
+
+        // read the reverse state tree changes history, sorted by slot
+        let mut state_tree_undo_history = VecDeque::new();
+        for (key, undo_bytes) in ledger.get_metadata_by_prefix(&[STATE_TREE_UNDO_PREFIX]) {
+            let slot_key: [u8; SLOT_KEY_SIZE] = key[1..].try_into().map_err(|_| {
+                FinalStateError::LoadError("invalid reverse state tree changes key".into())
+            })?;
+            let undo = state_tree_undo_from_bytes(&undo_bytes).ok_or_else(|| {
+                FinalStateError::LoadError("invalid reverse state tree changes".into())
+            })?;
+            state_tree_undo_history.push_back((Slot::from_bytes_key(&slot_key), undo));
+        }
+
+        // read the recent state roots, sorted by slot
+        let mut state_roots = VecDeque::new();
+        for (key, root_bytes) in ledger.get_metadata_by_prefix(&[STATE_ROOT_PREFIX]) {
+            let slot_key: [u8; SLOT_KEY_SIZE] = key[1..]
+                .try_into()
+                .map_err(|_| FinalStateError::LoadError("invalid state root key".into()))?;
+            let root_bytes: [u8; HASH_SIZE_BYTES] = root_bytes[..]
+                .try_into()
+                .map_err(|_| FinalStateError::LoadError("invalid state root".into()))?;
+            let state_root = Hash::from_bytes(&root_bytes).map_err(|err| {
+                FinalStateError::LoadError(format!("invalid state root: {}", err))
+            })?;
+            state_roots.push_back((Slot::from_bytes_key(&slot_key), state_root));
+        }
+        match state_roots.back() {
+            Some((root_slot, state_root))
+                if *root_slot == slot && *state_root == get_state_root(ledger) => {}
+            _ => {
+                return Err(FinalStateError::LoadError(
+                    "the state tree does not match the final slot".into(),
+                ))
+            }
+        }
+
+        self.slot = slot;
+        self.async_pool = async_pool;
+        self.changes_history = changes_history;
+        self.ledger_undo_history = ledger_undo_history;
+        self.state_tree_undo_history = state_tree_undo_history;
+        self.state_roots = state_roots;
+        Ok(())
+    }
+
+    /// Writes the slot, the whole asynchronous pool and the current state root as ledger metadata,
+    /// along with additional metadata writes.
+    /// Used when the final state is (re)initialized.
+    fn save_metadata(&mut self, mut metadata: BTreeMap<Vec<u8>, Option<Vec<u8>>>) {
+        metadata.insert(
+            SLOT_KEY.to_vec(),
+            Some(
+                self.slot
+                    .to_bytes_compact()
+                    .expect("critical: could not serialize final slot"),
+            ),
+        );
+        for (id, msg) in self.async_pool.get_messages() {
+            metadata.insert(
+                async_message_key(id),
+                Some(
+                    msg.to_bytes_compact()
+                        .expect("critical: could not serialize async message"),
+                ),
+            );
+        }
+        for (slot, state_root) in &self.state_roots {
+            metadata.insert(state_root_key(slot), Some(state_root.to_bytes().to_vec()));
+        }
+        self.ledger
+            .apply_with_metadata(LedgerChanges::default(), metadata);
+    }
+
+    /// Gets the root hash of the state tree at the output of the current final slot.
+    /// Two nodes whose final states are attached to the same slot have the same state root
+    /// if and only if their final states are identical.
+    pub fn get_state_root(&self) -> Hash {
+        self.state_roots
+            .back()
+            .map(|(_slot, state_root)| *state_root)
+            .expect("critical: missing current state root")
+    }
+
+    /// Gets the root hash of the state tree at the output of a recent final slot.
+    ///
+    /// # Returns
+    /// The state root, or `None` if `slot` is not final yet or too old to be remembered
+    pub fn get_state_root_at(&self, slot: Slot) -> Option<Hash> {
+        self.state_roots
+            .iter()
+            .find(|(root_slot, _)| *root_slot == slot)
+            .map(|(_slot, state_root)| *state_root)
+    }
+
+    /// Checks that a history of reverse changes allows to get the final state at the output of a past slot.
+    ///
+    /// # Arguments
+    /// * `slot`: the queried slot
+    /// * `front_slot`: slot of the oldest element of the history, `None` if it is empty
+    fn check_history_covers(
+        &self,
+        slot: Slot,
+        front_slot: Option<&Slot>,
+    ) -> Result<(), FinalStateError> {
+        if slot > self.slot {
+            return Err(FinalStateError::HistoryError(format!(
+                "slot {} is not final yet, the last final slot is {}",
+                slot, self.slot
+            )));
+        }
+        if slot < self.slot {
+            // the history must reach the slot following the queried one
+            let next_slot = slot
+                .get_next_slot(self.config.thread_count)
+                .map_err(|err| FinalStateError::HistoryError(err.to_string()))?;
+            match front_slot {
+                Some(front_slot) if *front_slot <= next_slot => {}
+                _ => {
+                    return Err(FinalStateError::HistoryError(format!(
+                        "slot {} is older than the history kept by the node",
+                        slot
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the reverse ledger changes to apply to the current ledger, newest first,
+    /// to get the final ledger at the output of a past slot.
+    ///
+    /// # Returns
+    /// The reverse changes, or an error if `slot` is not final yet or older than the history
+    fn get_ledger_undo_until(
+        &self,
+        slot: Slot,
+    ) -> Result<impl Iterator<Item = &LedgerChanges>, FinalStateError> {
+        self.check_history_covers(slot, self.ledger_undo_history.front().map(|(s, _)| s))?;
+        Ok(self
+            .ledger_undo_history
+            .iter()
+            .rev()
+            .take_while(move |(undo_slot, _)| *undo_slot > slot)
+            .map(|(_, changes)| changes))
+    }
+
+    /// Gets the node writes to apply over the current state tree to get the state tree at the output of a past slot
+    ///
+    /// # Returns
+    /// The node writes, or an error if `slot` is not final yet or older than the history
+    fn get_state_tree_overlay_at(&self, slot: Slot) -> Result<StateTreeUndo, FinalStateError> {
+        self.check_history_covers(slot, self.state_tree_undo_history.front().map(|(s, _)| s))?;
+        let mut overlay = StateTreeUndo::new();
+        // older writes overwrite newer ones
+        for (_, undo) in self
+            .state_tree_undo_history
+            .iter()
+            .rev()
+            .take_while(|(undo_slot, _)| *undo_slot > slot)
+        {
+            overlay.extend(undo.clone());
+        }
+        Ok(overlay)
+    }
+
+    /// Gets the final parallel balance of an address at the output of a recent final slot
+    ///
+    /// # Returns
+    /// The balance (`None` if the address did not exist), or an error if the history does not cover `slot`
+    pub fn get_parallel_balance_at(
+        &self,
+        addr: &Address,
+        slot: Slot,
+    ) -> Result<Option<Amount>, FinalStateError> {
+        let mut balance = self.ledger.get_parallel_balance(addr);
+        for changes in self.get_ledger_undo_until(slot)? {
+            balance = changes.get_parallel_balance_or_else(addr, || balance);
+        }
+        Ok(balance)
+    }
+
+    /// Gets the final bytecode of an address at the output of a recent final slot
+    ///
+    /// # Returns
+    /// The bytecode (`None` if the address did not exist), or an error if the history does not cover `slot`
+    pub fn get_bytecode_at(
+        &self,
+        addr: &Address,
+        slot: Slot,
+    ) -> Result<Option<Vec<u8>>, FinalStateError> {
+        let undo = self.get_ledger_undo_until(slot)?;
+        let mut bytecode = self.ledger.get_bytecode(addr);
+        for changes in undo {
+            bytecode = changes.get_bytecode_or_else(addr, || bytecode);
+        }
+        Ok(bytecode)
+    }
+
+    /// Gets a final datastore value of an address at the output of a recent final slot
+    ///
+    /// # Returns
+    /// The value (`None` if it did not exist), or an error if the history does not cover `slot`
+    pub fn get_data_entry_at(
+        &self,
+        addr: &Address,
+        key: &Hash,
+        slot: Slot,
+    ) -> Result<Option<Vec<u8>>, FinalStateError> {
+        let undo = self.get_ledger_undo_until(slot)?;
+        let mut value = self.ledger.get_data_entry(addr, key);
+        for changes in undo {
+            value = changes.get_data_entry_or_else(addr, key, || value);
+        }
+        Ok(value)
+    }
+
+    /// Gets the final values of a ledger entry at the output of a recent final slot,
+    /// along with the proofs of those values against the state root of that slot.
+    ///
+    /// # Arguments
+    /// * `addr`: address of the entry
+    /// * `datastore_key`: optional datastore key whose value is also proven
+    /// * `slot`: final slot at the output of which the values are read, `None` for the current one
+    ///
+    /// # Returns
+    /// The values and their proofs, or an error if the history does not cover `slot`
+    pub fn get_ledger_entry_proof(
+        &self,
+        addr: &Address,
+        datastore_key: Option<Hash>,
+        slot: Option<Slot>,
+    ) -> Result<LedgerEntryProof, FinalStateError> {
+        let slot = slot.unwrap_or(self.slot);
+        let overlay = self.get_state_tree_overlay_at(slot)?;
+        let state_root = self.get_state_root_at(slot).ok_or_else(|| {
+            FinalStateError::HistoryError(format!("no state root is kept for slot {}", slot))
+        })?;
+        let (datastore_value, datastore_proof) = match &datastore_key {
+            Some(key) => (
+                self.get_data_entry_at(addr, key, slot)?,
+                Some(get_state_proof(
+                    &self.ledger,
+                    &overlay,
+                    &data_leaf_key(addr, key),
+                )),
+            ),
+            None => (None, None),
+        };
+        Ok(LedgerEntryProof {
+            address: *addr,
+            slot,
+            state_root,
+            balance: self.get_parallel_balance_at(addr, slot)?,
+            balance_proof: get_state_proof(&self.ledger, &overlay, &balance_leaf_key(addr)),
+            bytecode_hash: self
+                .get_bytecode_at(addr, slot)?
+                .map(|bytecode| Hash::compute_from(&bytecode)),
+            bytecode_proof: get_state_proof(&self.ledger, &overlay, &bytecode_leaf_key(addr)),
+            datastore_key,
+            datastore_value,
+            datastore_proof,
+        })
+    }
+
+    /// Gets the next part of the final state to stream it to a bootstrapping node.
+    ///
+    /// # Arguments
+    /// * `last_slot`: slot of the last part received by the client, `None` if it did not receive any
+    /// * `last_address`: last ledger address received by the client, `None` if it did not receive any
+    /// * `max_ledger_part_size`: approximate maximal size of the ledger part in bytes
+    ///
+    /// # Returns
+    /// The next part, or `None` if the changes history does not go back to `last_slot` anymore
+    /// and the client needs to start over.
+    pub fn get_bootstrap_part(
+        &self,
+        last_slot: Option<Slot>,
+        last_address: Option<&Address>,
+        max_ledger_part_size: usize,
+    ) -> Option<FinalStatePart> {
+        let final_state_changes = match last_slot {
+            Some(last_slot) => self.get_state_changes_part(last_slot, self.slot)?,
+            None => Vec::new(),
+        };
+        let ledger_part = self
+            .ledger
+            .get_ledger_part(last_address, max_ledger_part_size);
+
+        // once the whole ledger was sent, the asynchronous pool is sent to complete the final state
+        let (async_pool, state_root) = if ledger_part.is_empty() {
+            (
+                Some(self.async_pool.get_bootstrap_snapshot()),
+                Some(self.get_state_root()),
+            )
+        } else {
+            (None, None)
+        };
+
+        Some(FinalStatePart {
             slot: self.slot,
-            async_pool: self.async_pool.get_bootstrap_snapshot(),
-            ledger: self.ledger.get_bootstrap_state(),
+            ledger_part,
+            final_state_changes,
+            async_pool,
+            state_root,
+        })
+    }
+
+    /// Removes the whole content of the final state, including the state saved on disk.
+    /// Used before receiving the final state in parts from a bootstrap server:
+    /// if the node stops before the last part is received, no incomplete state is loaded at restart.
+    pub fn reset(&mut self) {
+        self.ledger.reset();
+        self.async_pool = AsyncPool::new(self.config.async_pool_config.clone());
+        self.changes_history.clear();
+        self.ledger_undo_history.clear();
+        self.state_tree_undo_history.clear();
+        self.state_roots.clear();
+    }
+
+    /// Applies a part of the final state received from a bootstrap server.
+    ///
+    /// # Arguments
+    /// * `part`: the received part
+    /// * `last_address`: last ledger address received before this part, `None` if none was received
+    ///
+    /// # Returns
+    /// The last ledger address received once the part is applied,
+    /// or an error if the last part was received and the state root does not match the one of the server.
+    pub fn set_bootstrap_part(
+        &mut self,
+        part: FinalStatePart,
+        last_address: Option<Address>,
+    ) -> Result<Option<Address>, FinalStateError> {
+        // the changes only need to be applied to the entries that were already received:
+        // the next entries are read by the server after the changes, in their up-to-date version
+        let mut ledger_changes = LedgerChanges::default();
+        for (_slot, changes) in part.final_state_changes {
+            ledger_changes.apply(changes.ledger_changes);
+        }
+        ledger_changes
+            .0
+            .retain(|addr, _| matches!(last_address, Some(last) if *addr <= last));
+
+        // update the state tree with the changes and the new entries
+        let mut leaves = StateTreeLeaves::new();
+        add_ledger_changes_leaves(&self.ledger, &ledger_changes, &mut leaves);
+        for (addr, entry) in part.ledger_part.get_entries() {
+            add_entry_leaves(addr, entry, &mut leaves);
+        }
+        let mut metadata = BTreeMap::new();
+        let state_root = update_state_tree(&self.ledger, leaves, &mut metadata);
+        self.ledger.apply_with_metadata(ledger_changes, metadata);
+
+        // write the ledger part
+        let last_address = self
+            .ledger
+            .set_ledger_part(part.ledger_part)
+            .or(last_address);
+        self.slot = part.slot;
+
+        // the last part completes the final state with the asynchronous pool
+        if let Some(async_pool) = part.async_pool {
+            let async_pool = AsyncPool::from_bootstrap_snapshot(
+                self.config.async_pool_config.clone(),
+                async_pool,
+            );
+            let mut async_pool_changes = AsyncPoolChanges::default();
+            for id in self.async_pool.get_messages().keys() {
+                async_pool_changes.push_delete(*id);
+            }
+            for (id, msg) in async_pool.get_messages() {
+                async_pool_changes.push_add(*id, msg.clone());
+            }
+            let mut leaves = StateTreeLeaves::new();
+            add_async_pool_changes_leaves(&async_pool_changes, &mut leaves);
+            let mut metadata = BTreeMap::new();
+            let state_root = update_state_tree(&self.ledger, leaves, &mut metadata);
+            self.async_pool = async_pool;
+
+            // check the rebuilt final state against the server's one
+            if part.state_root != Some(state_root) {
+                return Err(FinalStateError::StateRootMismatch(format!(
+                    "expected {:?} at slot {}, got {}",
+                    part.state_root, self.slot, state_root
+                )));
+            }
+            self.state_roots = VecDeque::from([(self.slot, state_root)]);
+            self.save_metadata(metadata);
+        } else {
+            self.state_roots = VecDeque::from([(self.slot, state_root)]);
         }
+
+        Ok(last_address)
+    }
+
+    /// Gets the state changes of the slots in the range `(from_slot, to_slot]` from the changes history.
+    /// This allows a node that already has the final state at `from_slot` to catch up with `to_slot`.
+    ///
+    /// # Returns
+    /// The list of `(slot, changes)` sorted by slot, or `None` if the history does not cover the whole range.
+    pub fn get_state_changes_part(
+        &self,
+        from_slot: Slot,
+        to_slot: Slot,
+    ) -> Option<Vec<(Slot, StateChanges)>> {
+        if from_slot == to_slot {
+            return Some(Vec::new());
+        }
+        if from_slot > to_slot {
+            return None;
+        }
+
+        // check that the history covers the range
+        let first_slot = from_slot.get_next_slot(self.config.thread_count).ok()?;
+        match (self.changes_history.front(), self.changes_history.back()) {
+            (Some((front_slot, _)), Some((back_slot, _)))
+                if *front_slot <= first_slot && *back_slot >= to_slot => {}
+            _ => return None,
+        }
+
+        Some(
+            self.changes_history
+                .iter()
+                .filter(|(slot, _)| *slot > from_slot && *slot <= to_slot)
+                .cloned()
+                .collect(),
+        )
     }
 
     /// Applies changes to the execution state at a given slot, and settles that slot forever.
     /// Once this is called, the state is attached at the output of the provided slot.
+    /// The ledger entries left empty by the changes are deleted along with them.
     ///
     /// Panics if the new slot is not the one coming just after the current one.
-    pub fn finalize(&mut self, slot: Slot, changes: StateChanges) {
+    pub fn finalize(&mut self, slot: Slot, mut changes: StateChanges) {
         // check slot consistency
         let next_slot = self
             .slot
@@ -100,17 +733,595 @@ impl FinalState {
         // update current slot
         self.slot = slot;
 
-        // apply changes
-        self.ledger.apply(changes.ledger_changes.clone());
-        self.async_pool
-            .apply_changes_unchecked(changes.async_pool_changes.clone());
+        // delete the entries left without balance, bytecode or data so that they do not stay in the ledger forever
+        for addr in self.ledger.get_emptied_entries(&changes.ledger_changes) {
+            changes
+                .ledger_changes
+                .0
+                .insert(addr, SetUpdateOrDelete::Delete);
+        }
+
+        // metadata written to disk along with the ledger changes
+        let mut metadata = BTreeMap::new();
+        metadata.insert(
+            SLOT_KEY.to_vec(),
+            Some(
+                slot.to_bytes_compact()
+                    .expect("critical: could not serialize final slot"),
+            ),
+        );
+        for change in changes.async_pool_changes.get_changes() {
+            match change {
+                Change::Add(id, msg) => metadata.insert(
+                    async_message_key(id),
+                    Some(
+                        msg.to_bytes_compact()
+                            .expect("critical: could not serialize async message"),
+                    ),
+                ),
+                Change::Delete(id) => metadata.insert(async_message_key(id), None),
+            };
+        }
+
+        // update the state tree before applying the changes, whose previous values are needed
+        let mut leaves = StateTreeLeaves::new();
+        add_ledger_changes_leaves(&self.ledger, &changes.ledger_changes, &mut leaves);
+        add_async_pool_changes_leaves(&changes.async_pool_changes, &mut leaves);
+        let state_root = update_state_tree(&self.ledger, leaves, &mut metadata);
+
+        // push the state root and limit the number of remembered roots
+        while self.state_roots.len() >= self.config.final_history_length.max(1) {
+            if let Some((old_slot, _)) = self.state_roots.pop_front() {
+                metadata.insert(state_root_key(&old_slot), None);
+            }
+        }
+        metadata.insert(state_root_key(&slot), Some(state_root.to_bytes().to_vec()));
+        self.state_roots.push_back((slot, state_root));
 
         // push history element and limit history size
         if self.config.final_history_length > 0 {
             while self.changes_history.len() >= self.config.final_history_length {
-                self.changes_history.pop_front();
+                if let Some((old_slot, _)) = self.changes_history.pop_front() {
+                    metadata.insert(history_key(&old_slot), None);
+                }
+            }
+            while self.ledger_undo_history.len() >= self.config.final_history_length {
+                if let Some((old_slot, _)) = self.ledger_undo_history.pop_front() {
+                    metadata.insert(ledger_undo_key(&old_slot), None);
+                }
+            }
+            let ledger_undo = self.ledger.get_reverse_changes(&changes.ledger_changes);
+            metadata.insert(
+                ledger_undo_key(&slot),
+                Some(
+                    ledger_undo
+                        .to_bytes_compact()
+                        .expect("critical: could not serialize reverse ledger changes"),
+                ),
+            );
+            self.ledger_undo_history.push_back((slot, ledger_undo));
+            while self.state_tree_undo_history.len() >= self.config.final_history_length {
+                if let Some((old_slot, _)) = self.state_tree_undo_history.pop_front() {
+                    metadata.insert(state_tree_undo_key(&old_slot), None);
+                }
+            }
+            let state_tree_undo = get_state_tree_undo(&self.ledger, &metadata);
+            metadata.insert(
+                state_tree_undo_key(&slot),
+                Some(state_tree_undo_to_bytes(&state_tree_undo)),
+            );
+            self.state_tree_undo_history
+                .push_back((slot, state_tree_undo));
+            metadata.insert(
+                history_key(&slot),
+                Some(
+                    changes
+                        .to_bytes_compact()
+                        .expect("critical: could not serialize state changes"),
+                ),
+            );
+            self.changes_history.push_back((slot, changes.clone()));
+        }
+
+        // apply changes
+        self.ledger
+            .apply_with_metadata(changes.ledger_changes, metadata);
+        self.async_pool
+            .apply_changes_unchecked(changes.async_pool_changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use massa_ledger::LedgerEntry;
+    use massa_models::{Address, Amount};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_final_state_load() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut final_state = FinalState::new(config.clone()).unwrap();
+
+        // finalize a slot creating a ledger entry and an async message
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Set(LedgerEntry {
+                parallel_balance: Amount::from_str("3").unwrap(),
+                ..Default::default()
+            }),
+        );
+        let msg = AsyncMessage {
+            emission_slot: Slot::new(1, 0),
+            emission_index: 0,
+            sender: addr,
+            destination: addr,
+            handler: "handler".into(),
+            max_gas: 1000,
+            gas_price: Amount::from_str("1").unwrap(),
+            coins: Amount::from_str("1").unwrap(),
+            validity_start: Slot::new(2, 0),
+            validity_end: Slot::new(4, 0),
+            data: vec![1, 2, 3],
+        };
+        changes
+            .async_pool_changes
+            .push_add(msg.compute_id(), msg.clone());
+        final_state.finalize(Slot::new(1, 0), changes);
+        let bootstrap_pool = final_state.async_pool.get_bootstrap_snapshot();
+        let state_root = final_state.get_state_root();
+
+        // reload the final state from what was saved in its ledger database,
+        // keeping the database open as it cannot be reopened right after being closed
+        let final_state = match FinalState::from_saved_ledger(config, final_state.ledger) {
+            Ok(final_state) => final_state,
+            Err((err, _)) => panic!("could not load final state: {}", err),
+        };
+        assert_eq!(final_state.slot, Slot::new(1, 0));
+        assert_eq!(final_state.get_state_root(), state_root);
+        assert_eq!(
+            final_state.ledger.get_parallel_balance(&addr),
+            Some(Amount::from_str("3").unwrap())
+        );
+        massa_async_pool::test_exports::assert_eq_async_pool_bootstrap_state(
+            &bootstrap_pool,
+            &final_state.async_pool.get_bootstrap_snapshot(),
+        );
+        let history = final_state
+            .get_state_changes_part(Slot::new(0, 1), Slot::new(1, 0))
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].0, Slot::new(1, 0));
+    }
+
+    #[test]
+    fn test_state_root() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut final_state = FinalState::new(config).unwrap();
+        let empty_root = final_state.get_state_root();
+
+        // create entries with data and a message
+        let mut changes = StateChanges::default();
+        for i in 1..=3u8 {
+            let mut datastore = BTreeMap::new();
+            datastore.insert(Hash::compute_from(&[i]), vec![i; 10]);
+            datastore.insert(Hash::compute_from(&[i, i]), vec![i; 20]);
+            changes.ledger_changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    parallel_balance: Amount::from_str("3").unwrap(),
+                    bytecode: vec![i],
+                    datastore,
+                    ..Default::default()
+                }),
+            );
+        }
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let msg = AsyncMessage {
+            emission_slot: Slot::new(1, 0),
+            emission_index: 0,
+            sender: addr,
+            destination: addr,
+            handler: "handler".into(),
+            max_gas: 1000,
+            gas_price: Amount::from_str("1").unwrap(),
+            coins: Amount::from_str("1").unwrap(),
+            validity_start: Slot::new(2, 0),
+            validity_end: Slot::new(4, 0),
+            data: vec![1, 2, 3],
+        };
+        changes.async_pool_changes.push_add(msg.compute_id(), msg);
+        final_state.finalize(Slot::new(1, 0), changes);
+        let root_1 = final_state.get_state_root();
+        assert_ne!(root_1, empty_root);
+
+        // delete an entry and replace the datastore of another one
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.0.insert(
+            Address::from_bytes(&[2u8; 32]).unwrap(),
+            SetUpdateOrDelete::Delete,
+        );
+        changes.ledger_changes.0.insert(
+            Address::from_bytes(&[3u8; 32]).unwrap(),
+            SetUpdateOrDelete::Set(LedgerEntry::default()),
+        );
+        final_state.finalize(Slot::new(1, 1), changes);
+        let root_2 = final_state.get_state_root();
+        assert_ne!(root_2, root_1);
+        assert_eq!(final_state.get_state_root_at(Slot::new(1, 0)), Some(root_1));
+        assert_eq!(final_state.get_state_root_at(Slot::new(1, 1)), Some(root_2));
+
+        // a copy of the final state rebuilt from its parts has the same state root
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut copy = FinalState::new(config).unwrap();
+        copy.reset();
+        let mut last_slot = None;
+        let mut last_address = None;
+        loop {
+            let part = final_state
+                .get_bootstrap_part(last_slot, last_address.as_ref(), 100)
+                .unwrap();
+            let is_last = part.is_last();
+            last_slot = Some(part.get_slot());
+            last_address = copy.set_bootstrap_part(part, last_address).unwrap();
+            if is_last {
+                break;
+            }
+        }
+        assert_eq!(copy.get_state_root(), root_2);
+
+        // deleting everything brings the state root back to the empty one
+        let mut changes = StateChanges::default();
+        for i in 1..=3u8 {
+            changes.ledger_changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Delete,
+            );
+        }
+        for id in final_state.async_pool.get_messages().keys() {
+            changes.async_pool_changes.push_delete(*id);
+        }
+        final_state.finalize(Slot::new(2, 0), changes);
+        assert_eq!(final_state.get_state_root(), empty_root);
+    }
+
+    #[test]
+    fn test_state_roots_match_across_nodes() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config_a, _keep_file_a, _keep_dir_a) = FinalStateConfig::sample(10);
+        let mut node_a = FinalState::new(config_a).unwrap();
+        let (config_b, _keep_file_b, _keep_dir_b) = FinalStateConfig::sample(10);
+        let mut node_b = FinalState::new(config_b).unwrap();
+        assert_eq!(node_a.get_state_root(), node_b.get_state_root());
+
+        // the first node finalizes a few slots
+        let slots = [Slot::new(1, 0), Slot::new(1, 1), Slot::new(2, 0)];
+        for (i, slot) in slots.iter().enumerate() {
+            let i = i as u8 + 1;
+            let mut changes = StateChanges::default();
+            changes.ledger_changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    parallel_balance: Amount::from_str("5").unwrap(),
+                    bytecode: vec![i],
+                    datastore: BTreeMap::from([(Hash::compute_from(&[i]), vec![i; 5])]),
+                    ..Default::default()
+                }),
+            );
+            node_a.finalize(*slot, changes);
+        }
+
+        // the second node replays the same slots from the changes history of the first one
+        let history = node_a
+            .get_state_changes_part(node_b.slot, node_a.slot)
+            .unwrap();
+        assert_eq!(history.len(), slots.len());
+        for (slot, changes) in history {
+            node_b.finalize(slot, changes);
+        }
+
+        // both nodes have the same state root at each final slot
+        for slot in slots {
+            let state_root = node_a.get_state_root_at(slot);
+            assert!(state_root.is_some());
+            assert_eq!(node_b.get_state_root_at(slot), state_root);
+        }
+        assert_ne!(
+            node_a.get_state_root_at(slots[0]),
+            node_a.get_state_root_at(slots[1])
+        );
+        assert_eq!(node_a.get_state_root_at(Slot::new(3, 0)), None);
+    }
+
+    #[test]
+    fn test_ledger_entry_proof() {
+        use massa_models::state_proof::balance_leaf_value;
+
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut final_state = FinalState::new(config).unwrap();
+
+        // create a few entries with data
+        let mut changes = StateChanges::default();
+        for i in 1..=5u8 {
+            let mut datastore = BTreeMap::new();
+            datastore.insert(Hash::compute_from(&[i]), vec![i; 10]);
+            changes.ledger_changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    parallel_balance: Amount::from_str("3").unwrap(),
+                    bytecode: vec![i],
+                    datastore,
+                    ..Default::default()
+                }),
+            );
+        }
+        final_state.finalize(Slot::new(1, 0), changes);
+        let state_root = final_state.get_state_root();
+
+        // existing entry and datastore key
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let data_key = Hash::compute_from(&[1u8]);
+        let proof = final_state
+            .get_ledger_entry_proof(&addr, Some(data_key), None)
+            .unwrap();
+        assert_eq!(proof.slot, Slot::new(1, 0));
+        assert_eq!(proof.state_root, state_root);
+        let balance = proof.balance.unwrap();
+        let balance_hash = Hash::compute_from(&balance_leaf_value(&balance));
+        assert_eq!(
+            proof
+                .balance_proof
+                .compute_root(&balance_leaf_key(&addr), Some(&balance_hash)),
+            Some(state_root)
+        );
+        assert_eq!(
+            proof
+                .bytecode_proof
+                .compute_root(&bytecode_leaf_key(&addr), proof.bytecode_hash.as_ref()),
+            Some(state_root)
+        );
+        let datastore_proof = proof.datastore_proof.unwrap();
+        let data_leaf_key = data_leaf_key(&addr, &data_key);
+        let value_hash = Hash::compute_from(&proof.datastore_value.unwrap());
+        assert_eq!(
+            datastore_proof.compute_root(&data_leaf_key, Some(&value_hash)),
+            Some(state_root)
+        );
+
+        // a forged value or a claimed absence does not match the state root
+        let forged_hash = Hash::compute_from(&[0u8; 10]);
+        assert_ne!(
+            datastore_proof.compute_root(&data_leaf_key, Some(&forged_hash)),
+            Some(state_root)
+        );
+        assert_ne!(
+            datastore_proof.compute_root(&data_leaf_key, None),
+            Some(state_root)
+        );
+
+        // absent datastore key and absent entry
+        let absent_key = Hash::compute_from(&[2u8]);
+        let proof = final_state
+            .get_ledger_entry_proof(&addr, Some(absent_key), None)
+            .unwrap();
+        assert_eq!(proof.datastore_value, None);
+        assert_eq!(
+            proof.datastore_proof.unwrap().compute_root(
+                &massa_models::state_proof::data_leaf_key(&addr, &absent_key),
+                None
+            ),
+            Some(state_root)
+        );
+        let absent_addr = Address::from_bytes(&[9u8; 32]).unwrap();
+        let proof = final_state
+            .get_ledger_entry_proof(&absent_addr, None, None)
+            .unwrap();
+        assert_eq!(proof.balance, None);
+        assert_eq!(
+            proof
+                .balance_proof
+                .compute_root(&balance_leaf_key(&absent_addr), None),
+            Some(state_root)
+        );
+    }
+
+    #[test]
+    fn test_historical_ledger_entry_proof() {
+        use massa_models::state_proof::balance_leaf_value;
+
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(2);
+        let mut final_state = FinalState::new(config.clone()).unwrap();
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let other_addr = Address::from_bytes(&[2u8; 32]).unwrap();
+        let key = Hash::compute_from(b"key");
+
+        // create an entry, then update it along with another entry so that the tree changes shape
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Set(LedgerEntry {
+                parallel_balance: Amount::from_str("3").unwrap(),
+                bytecode: vec![1],
+                datastore: BTreeMap::from([(key, vec![1])]),
+                ..Default::default()
+            }),
+        );
+        final_state.finalize(Slot::new(1, 0), changes);
+        let mut changes = StateChanges::default();
+        changes
+            .ledger_changes
+            .set_parallel_balance(addr, Amount::from_str("5").unwrap());
+        changes.ledger_changes.set_data_entry(addr, key, vec![2]);
+        changes
+            .ledger_changes
+            .set_parallel_balance(other_addr, Amount::from_str("1").unwrap());
+        final_state.finalize(Slot::new(1, 1), changes);
+
+        // the proofs at each slot of the history match the state root of that slot
+        let check_proofs = |final_state: &FinalState| {
+            for (slot, balance, data) in [
+                (Slot::new(1, 0), "3", vec![1]),
+                (Slot::new(1, 1), "5", vec![2]),
+            ] {
+                let proof = final_state
+                    .get_ledger_entry_proof(&addr, Some(key), Some(slot))
+                    .unwrap();
+                let state_root = final_state.get_state_root_at(slot).unwrap();
+                assert_eq!(proof.slot, slot);
+                assert_eq!(proof.state_root, state_root);
+                assert_eq!(proof.balance, Some(Amount::from_str(balance).unwrap()));
+                let balance_hash = Hash::compute_from(&balance_leaf_value(&proof.balance.unwrap()));
+                assert_eq!(
+                    proof
+                        .balance_proof
+                        .compute_root(&balance_leaf_key(&addr), Some(&balance_hash)),
+                    Some(state_root)
+                );
+                assert_eq!(proof.datastore_value, Some(data.clone()));
+                assert_eq!(
+                    proof.datastore_proof.unwrap().compute_root(
+                        &data_leaf_key(&addr, &key),
+                        Some(&Hash::compute_from(&data))
+                    ),
+                    Some(state_root)
+                );
             }
-            self.changes_history.push_back((slot, changes));
+            let proof = final_state
+                .get_ledger_entry_proof(&other_addr, None, Some(Slot::new(1, 0)))
+                .unwrap();
+            assert_eq!(proof.balance, None);
+            assert_eq!(
+                proof
+                    .balance_proof
+                    .compute_root(&balance_leaf_key(&other_addr), None),
+                final_state.get_state_root_at(Slot::new(1, 0))
+            );
+        };
+        check_proofs(&final_state);
+
+        // the history is kept across restarts
+        let final_state = match FinalState::from_saved_ledger(config, final_state.ledger) {
+            Ok(final_state) => final_state,
+            Err((err, _)) => panic!("could not load final state: {}", err),
+        };
+        check_proofs(&final_state);
+
+        // slots out of the history or not final yet
+        assert!(final_state
+            .get_ledger_entry_proof(&addr, None, Some(Slot::new(0, 0)))
+            .is_err());
+        assert!(final_state
+            .get_ledger_entry_proof(&addr, None, Some(Slot::new(2, 0)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_historical_ledger_queries() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(2);
+        let mut final_state = FinalState::new(config).unwrap();
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let key = Hash::compute_from(b"key");
+
+        // create an entry, update it, then delete it
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Set(LedgerEntry {
+                parallel_balance: Amount::from_str("3").unwrap(),
+                bytecode: vec![1],
+                datastore: BTreeMap::from([(key, vec![1])]),
+                ..Default::default()
+            }),
+        );
+        final_state.finalize(Slot::new(1, 0), changes);
+        let mut changes = StateChanges::default();
+        changes
+            .ledger_changes
+            .set_parallel_balance(addr, Amount::from_str("5").unwrap());
+        changes.ledger_changes.set_data_entry(addr, key, vec![2]);
+        final_state.finalize(Slot::new(1, 1), changes);
+        let mut changes = StateChanges::default();
+        changes
+            .ledger_changes
+            .0
+            .insert(addr, SetUpdateOrDelete::Delete);
+        final_state.finalize(Slot::new(2, 0), changes);
+
+        // values at the output of each slot covered by the history
+        for (slot, balance, data) in [
+            (Slot::new(1, 0), Some("3"), Some(vec![1])),
+            (Slot::new(1, 1), Some("5"), Some(vec![2])),
+            (Slot::new(2, 0), None, None),
+        ] {
+            assert_eq!(
+                final_state.get_parallel_balance_at(&addr, slot).unwrap(),
+                balance.map(|b| Amount::from_str(b).unwrap())
+            );
+            assert_eq!(
+                final_state.get_bytecode_at(&addr, slot).unwrap(),
+                balance.map(|_| vec![1])
+            );
+            assert_eq!(
+                final_state.get_data_entry_at(&addr, &key, slot).unwrap(),
+                data
+            );
         }
+
+        // slots out of the history or not final yet
+        assert!(final_state
+            .get_parallel_balance_at(&addr, Slot::new(0, 1))
+            .is_err());
+        assert!(final_state
+            .get_parallel_balance_at(&addr, Slot::new(2, 1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_empty_entries_removed() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut final_state = FinalState::new(config).unwrap();
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let key = Hash::compute_from(b"key");
+
+        // create an entry holding coins and data
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Set(LedgerEntry {
+                parallel_balance: Amount::from_str("3").unwrap(),
+                datastore: BTreeMap::from([(key, vec![1])]),
+                ..Default::default()
+            }),
+        );
+        final_state.finalize(Slot::new(1, 0), changes);
+
+        // spending all the coins keeps the entry while it holds data
+        let mut changes = StateChanges::default();
+        changes
+            .ledger_changes
+            .set_parallel_balance(addr, Amount::default());
+        final_state.finalize(Slot::new(1, 1), changes);
+        assert!(final_state.ledger.entry_exists(&addr));
+
+        // deleting the last datastore entry removes the empty entry
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.delete_data_entry(addr, key);
+        final_state.finalize(Slot::new(2, 0), changes);
+        assert!(!final_state.ledger.entry_exists(&addr));
+        let history = final_state
+            .get_state_changes_part(Slot::new(1, 1), Slot::new(2, 0))
+            .unwrap();
+        assert!(matches!(
+            history[0].1.ledger_changes.0.get(&addr),
+            Some(SetUpdateOrDelete::Delete)
+        ));
     }
 }
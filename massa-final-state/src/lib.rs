@@ -15,14 +15,21 @@
 //! Defines the `FinalState` that matches that represents the state of the node at
 //! the latest executed final slot. It contains the final ledger and the asynchronous event pool.
 //! It can be manipulated using `StateChanges` (see `state_changes.rs`).
-//! The `FinalState` is bootstrapped using tooling available in bootstrap.rs
+//! The `FinalState` is streamed to bootstrapping nodes in parts using tooling available in bootstrap.rs
 //!
 //! ## `state_changes.rs`
 //! Represents a list of changes the final state.
 //! It can be modified, combined or applied to the final ledger.
 //!
+//! ## `state_tree.rs`
+//! Defines the sparse Merkle tree committing to the whole final state,
+//! whose root hash allows checking that two final states are identical.
+//!
 //! ## `bootstrap.rs`
-//! Provides serializable structures and tools for bootstrapping the final state.
+//! Provides serializable structures for streaming the final state to bootstrapping nodes.
+//!
+//! ## `snapshot.rs`
+//! Writes the final state to a versioned and checksummed snapshot file, and restores it from such a file.
 //!
 //! ## Test exports
 //!
@@ -38,9 +45,11 @@ mod bootstrap;
 mod config;
 mod error;
 mod final_state;
+mod snapshot;
 mod state_changes;
+mod state_tree;
 
-pub use bootstrap::FinalStateBootstrap;
+pub use bootstrap::FinalStatePart;
 pub use config::FinalStateConfig;
 pub use error::FinalStateError;
 pub use final_state::FinalState;
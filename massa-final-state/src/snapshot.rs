@@ -0,0 +1,276 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! This file allows writing the final state to a snapshot file and restoring it from such a file,
+//! for example to move a node to another machine or to start a test network from a known state.
+//!
+//! A snapshot file is made of:
+//! * a header: the `SNAPSHOT_MAGIC` bytes followed by the format version (`u32`, big endian)
+//! * the final state parts (see `bootstrap.rs`), each one prefixed by its size in bytes (`u64`, big endian),
+//!   the last one holding the asynchronous pool and the state root
+//! * a checksum chaining the hashes of the header and of every part
+//!
+//! Parts are written and read one at a time so that the whole ledger never needs to fit in memory.
+
+use crate::{bootstrap::FinalStatePart, error::FinalStateError, final_state::FinalState};
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_models::{Address, DeserializeCompact, SerializeCompact, Slot};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Bytes identifying a final state snapshot file
+const SNAPSHOT_MAGIC: [u8; 8] = *b"MASSASNP";
+/// Version of the snapshot file format
+const SNAPSHOT_VERSION: u32 = 1;
+/// Approximate maximal size in bytes of the ledger held by a snapshot part
+const SNAPSHOT_LEDGER_PART_SIZE: usize = 1_000_000;
+
+/// Chains the hash of the next element of a snapshot file to the checksum of the previous ones
+fn chain_checksum(checksum: &Hash, data: &[u8]) -> Hash {
+    let mut chained = checksum.to_bytes().to_vec();
+    chained.extend(Hash::compute_from(data).to_bytes());
+    Hash::compute_from(&chained)
+}
+
+/// Header of a snapshot file
+fn snapshot_header() -> Vec<u8> {
+    let mut header = SNAPSHOT_MAGIC.to_vec();
+    header.extend(SNAPSHOT_VERSION.to_be_bytes());
+    header
+}
+
+/// Reader of the parts of a snapshot file, checking its header and computing its checksum along the way
+struct SnapshotReader {
+    /// buffered snapshot file
+    reader: BufReader<File>,
+    /// checksum of the elements read so far
+    checksum: Hash,
+}
+
+impl SnapshotReader {
+    /// Opens a snapshot file and checks its header
+    fn open(path: &Path) -> Result<Self, FinalStateError> {
+        let mut reader = BufReader::new(File::open(path).map_err(snapshot_io_error)?);
+        let mut header = vec![0u8; SNAPSHOT_MAGIC.len() + 4];
+        reader.read_exact(&mut header).map_err(snapshot_io_error)?;
+        if header[..SNAPSHOT_MAGIC.len()] != SNAPSHOT_MAGIC {
+            return Err(FinalStateError::SnapshotError(
+                "not a final state snapshot file".into(),
+            ));
+        }
+        if header != snapshot_header() {
+            return Err(FinalStateError::SnapshotError(format!(
+                "unsupported snapshot version, expected {}",
+                SNAPSHOT_VERSION
+            )));
+        }
+        Ok(SnapshotReader {
+            reader,
+            checksum: Hash::compute_from(&header),
+        })
+    }
+
+    /// Reads the next part of the snapshot
+    fn read_part(&mut self) -> Result<FinalStatePart, FinalStateError> {
+        let mut size_bytes = [0u8; 8];
+        self.reader
+            .read_exact(&mut size_bytes)
+            .map_err(snapshot_io_error)?;
+        let size: usize = u64::from_be_bytes(size_bytes)
+            .try_into()
+            .map_err(|_| FinalStateError::SnapshotError("invalid part size".into()))?;
+        let mut part_bytes = Vec::new();
+        (&mut self.reader)
+            .take(size as u64)
+            .read_to_end(&mut part_bytes)
+            .map_err(snapshot_io_error)?;
+        if part_bytes.len() != size {
+            return Err(FinalStateError::SnapshotError("truncated snapshot".into()));
+        }
+        self.checksum = chain_checksum(&self.checksum, &part_bytes);
+        let (part, _) = FinalStatePart::from_bytes_compact(&part_bytes).map_err(|err| {
+            FinalStateError::SnapshotError(format!("invalid snapshot part: {}", err))
+        })?;
+        Ok(part)
+    }
+
+    /// Reads the checksum written at the end of the snapshot and compares it to the one of the read parts
+    fn check_checksum(mut self) -> Result<(), FinalStateError> {
+        let mut checksum_bytes = [0u8; HASH_SIZE_BYTES];
+        self.reader
+            .read_exact(&mut checksum_bytes)
+            .map_err(snapshot_io_error)?;
+        if checksum_bytes != self.checksum.to_bytes() {
+            return Err(FinalStateError::SnapshotError(
+                "snapshot checksum mismatch".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Converts an I/O error on a snapshot file
+fn snapshot_io_error(err: std::io::Error) -> FinalStateError {
+    FinalStateError::SnapshotError(format!("could not access the snapshot file: {}", err))
+}
+
+impl FinalState {
+    /// Writes the final state to a snapshot file, replacing the file if it exists.
+    ///
+    /// # Returns
+    /// The slot at the output of which the written final state is attached
+    pub fn export_snapshot(&self, path: &Path) -> Result<Slot, FinalStateError> {
+        let mut writer = BufWriter::new(File::create(path).map_err(snapshot_io_error)?);
+        let header = snapshot_header();
+        writer.write_all(&header).map_err(snapshot_io_error)?;
+        let mut checksum = Hash::compute_from(&header);
+
+        let mut last_address: Option<Address> = None;
+        loop {
+            let part = self
+                .get_bootstrap_part(None, last_address.as_ref(), SNAPSHOT_LEDGER_PART_SIZE)
+                .expect("critical: a final state part without changes is always available");
+            if let Some((addr, _)) = part.ledger_part.get_entries().last_key_value() {
+                last_address = Some(*addr);
+            }
+            let part_bytes = part.to_bytes_compact().map_err(|err| {
+                FinalStateError::SnapshotError(format!(
+                    "could not serialize snapshot part: {}",
+                    err
+                ))
+            })?;
+            writer
+                .write_all(&(part_bytes.len() as u64).to_be_bytes())
+                .map_err(snapshot_io_error)?;
+            writer.write_all(&part_bytes).map_err(snapshot_io_error)?;
+            checksum = chain_checksum(&checksum, &part_bytes);
+            if part.is_last() {
+                break;
+            }
+        }
+
+        writer
+            .write_all(&checksum.to_bytes())
+            .map_err(snapshot_io_error)?;
+        writer.flush().map_err(snapshot_io_error)?;
+        Ok(self.slot)
+    }
+
+    /// Replaces the final state by the content of a snapshot file, including the state saved on disk.
+    ///
+    /// The whole file is checked against its checksum before the final state is modified.
+    /// Its parts are then applied like the ones received from a bootstrap server,
+    /// which also checks the rebuilt final state against the state root of the snapshot.
+    /// On error, the final state may be left incomplete and must not be used.
+    ///
+    /// # Returns
+    /// The slot at the output of which the restored final state is attached
+    pub fn import_snapshot(&mut self, path: &Path) -> Result<Slot, FinalStateError> {
+        // check the whole file before touching the final state
+        let mut reader = SnapshotReader::open(path)?;
+        loop {
+            let part = reader.read_part()?;
+            if part.slot.thread >= self.config.thread_count {
+                return Err(FinalStateError::SnapshotError(format!(
+                    "snapshot slot {} does not match the thread count",
+                    part.slot
+                )));
+            }
+            if part.is_last() {
+                break;
+            }
+        }
+        reader.check_checksum()?;
+
+        // apply the parts
+        self.reset();
+        let mut reader = SnapshotReader::open(path)?;
+        let mut last_address = None;
+        loop {
+            let part = reader.read_part()?;
+            let is_last = part.is_last();
+            last_address = self.set_bootstrap_part(part, last_address)?;
+            if is_last {
+                break;
+            }
+        }
+        Ok(self.slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FinalState, FinalStateConfig, StateChanges};
+    use massa_async_pool::AsyncMessage;
+    use massa_hash::Hash;
+    use massa_ledger::{LedgerEntry, SetUpdateOrDelete};
+    use massa_models::{Address, Amount, Slot};
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_snapshot_export_import() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut final_state = FinalState::new(config).unwrap();
+
+        // fill the final state with entries and a message
+        let mut changes = StateChanges::default();
+        for i in 1..=20u8 {
+            changes.ledger_changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    parallel_balance: Amount::from_raw(i as u64),
+                    bytecode: vec![i; 100_000],
+                    datastore: BTreeMap::from([(Hash::compute_from(&[i]), vec![i; 10])]),
+                    ..Default::default()
+                }),
+            );
+        }
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let msg = AsyncMessage {
+            emission_slot: Slot::new(1, 0),
+            emission_index: 0,
+            sender: addr,
+            destination: addr,
+            handler: "handler".into(),
+            max_gas: 1000,
+            gas_price: Amount::from_str("1").unwrap(),
+            coins: Amount::from_str("1").unwrap(),
+            validity_start: Slot::new(2, 0),
+            validity_end: Slot::new(4, 0),
+            data: vec![1, 2, 3],
+        };
+        changes.async_pool_changes.push_add(msg.compute_id(), msg);
+        final_state.finalize(Slot::new(1, 0), changes);
+
+        // export, then import into another final state
+        let snapshot_file = NamedTempFile::new().unwrap();
+        assert_eq!(
+            final_state.export_snapshot(snapshot_file.path()).unwrap(),
+            Slot::new(1, 0)
+        );
+        let (config, _keep_file, _keep_dir) = FinalStateConfig::sample(10);
+        let mut copy = FinalState::new(config).unwrap();
+        assert_eq!(
+            copy.import_snapshot(snapshot_file.path()).unwrap(),
+            Slot::new(1, 0)
+        );
+        assert_eq!(copy.get_state_root(), final_state.get_state_root());
+        assert_eq!(
+            copy.ledger.get_parallel_balance(&addr),
+            Some(Amount::from_raw(1))
+        );
+        assert_eq!(copy.async_pool.get_messages().len(), 1);
+
+        // a corrupted snapshot is rejected before the final state is modified
+        let mut bytes = std::fs::read(snapshot_file.path()).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        let corrupted_file = NamedTempFile::new().unwrap();
+        std::fs::write(corrupted_file.path(), bytes).unwrap();
+        assert!(copy.import_snapshot(corrupted_file.path()).is_err());
+        assert_eq!(copy.get_state_root(), final_state.get_state_root());
+    }
+}
@@ -4,6 +4,7 @@
 
 use massa_async_pool::AsyncPoolChanges;
 use massa_ledger::LedgerChanges;
+use massa_models::{DeserializeCompact, ModelsError, SerializeCompact};
 
 /// represents changes that can be applied to the execution state
 #[derive(Default, Debug, Clone)]
@@ -22,3 +23,41 @@ impl StateChanges {
         self.async_pool_changes.extend(changes.async_pool_changes);
     }
 }
+
+/// Allows serializing the `StateChanges` to a compact binary representation
+impl SerializeCompact for StateChanges {
+    fn to_bytes_compact(&self) -> Result<Vec<u8>, ModelsError> {
+        let mut res: Vec<u8> = Vec::new();
+
+        // ledger changes
+        res.extend(self.ledger_changes.to_bytes_compact()?);
+
+        // async pool changes
+        res.extend(self.async_pool_changes.to_bytes_compact()?);
+
+        Ok(res)
+    }
+}
+
+/// Allows deserializing `StateChanges` from their compact binary representation
+impl DeserializeCompact for StateChanges {
+    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), ModelsError> {
+        let mut cursor = 0usize;
+
+        // ledger changes
+        let (ledger_changes, delta) = LedgerChanges::from_bytes_compact(&buffer[cursor..])?;
+        cursor += delta;
+
+        // async pool changes
+        let (async_pool_changes, delta) = AsyncPoolChanges::from_bytes_compact(&buffer[cursor..])?;
+        cursor += delta;
+
+        Ok((
+            StateChanges {
+                ledger_changes,
+                async_pool_changes,
+            },
+            cursor,
+        ))
+    }
+}
@@ -0,0 +1,494 @@
+//! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! This file defines the sparse Merkle tree committing to the contents of the final state.
+//!
+//! Every item of the final state is a leaf of the tree: the balance, the bytecode, the storage deposit
+//! and each datastore entry of the ledger entries, as well as each message of the asynchronous pool.
+//! The hashing rules of the tree and the leaf keys of the ledger items are defined in `massa_models::state_proof`
+//! so that clients can check the proofs built from it.
+//!
+//! The hash of a subtree is:
+//! * the empty subtree hash if it contains no leaves
+//! * the hash of its leaf if it contains only one leaf
+//! * the hash of the concatenation of the hashes of its two children otherwise
+//!
+//! so that the tree only stores the nodes of subtrees holding at least one leaf,
+//! and its size grows linearly with the number of items of the final state.
+//! The nodes are stored as ledger metadata and are updated in the same atomic batch as the ledger changes.
+
+use massa_async_pool::{AsyncMessageId, AsyncPoolChanges, Change};
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_ledger::{
+    FinalLedger, LedgerChanges, LedgerEntry, SetOrDelete, SetOrKeep, SetUpdateOrDelete,
+};
+use massa_models::{
+    state_proof::{
+        balance_leaf_key, balance_leaf_value, bytecode_leaf_key, data_leaf_key, empty_subtree_hash,
+        get_path_bit, internal_hash, leaf_hash, storage_deposit_leaf_key, StateProof,
+        StateProofTerminal, ASYNC_POOL_LEAF_PREFIX, STATE_TREE_DEPTH,
+    },
+    Address, SerializeCompact,
+};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Metadata key prefix of the nodes of the state tree
+const STATE_TREE_PREFIX: u8 = 3u8;
+
+/// Leaf updates: leaf key associated to the new value of the item, `None` to remove it
+pub(crate) type StateTreeLeaves = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Metadata writes, as expected by `FinalLedger::apply_with_metadata`
+type Metadata = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Node writes reverting an update of the tree: node key associated to the previous node, `None` if it was not stored
+pub(crate) type StateTreeUndo = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Path of a leaf or a subtree, read from the most significant bit
+type Path = [u8; HASH_SIZE_BYTES];
+
+/// Leaf key of an asynchronous message
+fn async_message_leaf_key(id: &AsyncMessageId) -> Vec<u8> {
+    let (Reverse(score), slot, index) = id;
+    let mut key = vec![ASYNC_POOL_LEAF_PREFIX];
+    key.extend(score.to_raw().to_be_bytes());
+    key.extend(slot.to_bytes_key());
+    key.extend(index.to_be_bytes());
+    key
+}
+
+/// Adds the leaves of a ledger entry that does not exist yet
+pub(crate) fn add_entry_leaves(addr: &Address, entry: &LedgerEntry, leaves: &mut StateTreeLeaves) {
+    leaves.insert(
+        balance_leaf_key(addr),
+        Some(balance_leaf_value(&entry.parallel_balance)),
+    );
+    leaves.insert(bytecode_leaf_key(addr), Some(entry.bytecode.clone()));
+    for (data_key, value) in &entry.datastore {
+        leaves.insert(data_leaf_key(addr, data_key), Some(value.clone()));
+    }
+    leaves.insert(
+        storage_deposit_leaf_key(addr),
+        Some(balance_leaf_value(&entry.storage_deposit)),
+    );
+}
+
+/// Adds the leaf updates caused by ledger changes.
+/// Must be called before the changes are applied to `ledger`, whose entries are read to know which leaves are removed.
+pub(crate) fn add_ledger_changes_leaves(
+    ledger: &FinalLedger,
+    changes: &LedgerChanges,
+    leaves: &mut StateTreeLeaves,
+) {
+    for (addr, change) in &changes.0 {
+        match change {
+            SetUpdateOrDelete::Set(new_entry) => {
+                // all the items of the previous entry are replaced
+                if let Some(old_entry) = ledger.get_full_entry(addr) {
+                    for data_key in old_entry.datastore.keys() {
+                        leaves.insert(data_leaf_key(addr, data_key), None);
+                    }
+                }
+                add_entry_leaves(addr, new_entry, leaves);
+            }
+            SetUpdateOrDelete::Update(entry_update) => {
+                // the ledger inserts a default entry before updating a missing one
+                if !ledger.entry_exists(addr) {
+                    add_entry_leaves(addr, &LedgerEntry::default(), leaves);
+                }
+                if let SetOrKeep::Set(balance) = &entry_update.parallel_balance {
+                    leaves.insert(balance_leaf_key(addr), Some(balance_leaf_value(balance)));
+                }
+                if let SetOrKeep::Set(bytecode) = &entry_update.bytecode {
+                    leaves.insert(bytecode_leaf_key(addr), Some(bytecode.clone()));
+                }
+                for (data_key, value_update) in &entry_update.datastore {
+                    let value = match value_update {
+                        SetOrDelete::Set(value) => Some(value.clone()),
+                        SetOrDelete::Delete => None,
+                    };
+                    leaves.insert(data_leaf_key(addr, data_key), value);
+                }
+                if let SetOrKeep::Set(storage_deposit) = &entry_update.storage_deposit {
+                    leaves.insert(
+                        storage_deposit_leaf_key(addr),
+                        Some(balance_leaf_value(storage_deposit)),
+                    );
+                }
+            }
+            SetUpdateOrDelete::Delete => {
+                if let Some(old_entry) = ledger.get_full_entry(addr) {
+                    leaves.insert(balance_leaf_key(addr), None);
+                    leaves.insert(bytecode_leaf_key(addr), None);
+                    leaves.insert(storage_deposit_leaf_key(addr), None);
+                    for data_key in old_entry.datastore.keys() {
+                        leaves.insert(data_leaf_key(addr, data_key), None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Adds the leaf updates caused by asynchronous pool changes
+pub(crate) fn add_async_pool_changes_leaves(
+    changes: &AsyncPoolChanges,
+    leaves: &mut StateTreeLeaves,
+) {
+    for change in changes.get_changes() {
+        match change {
+            Change::Add(id, msg) => leaves.insert(
+                async_message_leaf_key(id),
+                Some(
+                    msg.to_bytes_compact()
+                        .expect("critical: could not serialize async message"),
+                ),
+            ),
+            Change::Delete(id) => leaves.insert(async_message_leaf_key(id), None),
+        };
+    }
+}
+
+/// Node of the state tree, as stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    /// subtree without leaves, never stored
+    Empty,
+    /// subtree containing a single leaf
+    Leaf {
+        /// path of the leaf
+        path: Path,
+        /// hash of the leaf
+        hash: Hash,
+    },
+    /// subtree containing several leaves
+    Internal {
+        /// hash of the concatenation of the hashes of both children
+        hash: Hash,
+    },
+}
+
+impl Node {
+    /// Gets the hash of the subtree
+    fn hash(&self) -> Hash {
+        match self {
+            Node::Empty => empty_subtree_hash(),
+            Node::Leaf { hash, .. } | Node::Internal { hash } => *hash,
+        }
+    }
+
+    /// Serializes the node to store it as metadata, `None` if it must not be stored
+    fn to_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            Node::Empty => None,
+            Node::Leaf { path, hash } => {
+                let mut res = vec![0u8];
+                res.extend(path);
+                res.extend(hash.to_bytes());
+                Some(res)
+            }
+            Node::Internal { hash } => {
+                let mut res = vec![1u8];
+                res.extend(hash.to_bytes());
+                Some(res)
+            }
+        }
+    }
+
+    /// Deserializes a node stored as metadata
+    fn from_bytes(bytes: &[u8]) -> Node {
+        let read_hash = |bytes: &[u8]| {
+            bytes
+                .try_into()
+                .ok()
+                .and_then(|bytes| Hash::from_bytes(bytes).ok())
+                .expect("critical: invalid state tree node hash")
+        };
+        match bytes.first() {
+            Some(0u8) if bytes.len() == 1 + 2 * HASH_SIZE_BYTES => Node::Leaf {
+                path: bytes[1..=HASH_SIZE_BYTES]
+                    .try_into()
+                    .expect("critical: invalid state tree node path"),
+                hash: read_hash(&bytes[1 + HASH_SIZE_BYTES..]),
+            },
+            Some(1u8) if bytes.len() == 1 + HASH_SIZE_BYTES => Node::Internal {
+                hash: read_hash(&bytes[1..]),
+            },
+            _ => panic!("critical: invalid state tree node"),
+        }
+    }
+}
+
+/// Metadata key of the node at a given height (0 for leaves) covering the paths starting with `prefix`.
+/// Only the first `STATE_TREE_DEPTH - height` bits of `prefix` are meaningful, the others are zero.
+fn node_key(height: usize, prefix: &Path) -> Vec<u8> {
+    let mut key = vec![STATE_TREE_PREFIX];
+    key.extend((height as u16).to_be_bytes());
+    key.extend(prefix);
+    key
+}
+
+/// Reads a node, taking the pending metadata writes into account
+fn get_node(ledger: &FinalLedger, metadata: &Metadata, height: usize, prefix: &Path) -> Node {
+    let key = node_key(height, prefix);
+    let bytes = match metadata.get(&key) {
+        Some(bytes) => bytes.clone(),
+        None => ledger.get_metadata(&key),
+    };
+    bytes.map_or(Node::Empty, |bytes| Node::from_bytes(&bytes))
+}
+
+/// Writes a node in the pending metadata
+fn set_node(metadata: &mut Metadata, height: usize, prefix: &Path, node: Node) {
+    metadata.insert(node_key(height, prefix), node.to_bytes());
+}
+
+/// Gets the node writes reverting the pending ones.
+/// Must be called before the pending metadata writes are applied to `ledger`.
+pub(crate) fn get_state_tree_undo(ledger: &FinalLedger, metadata: &Metadata) -> StateTreeUndo {
+    metadata
+        .range(vec![STATE_TREE_PREFIX]..vec![STATE_TREE_PREFIX + 1])
+        .map(|(key, _)| (key.clone(), ledger.get_metadata(key)))
+        .collect()
+}
+
+/// Serializes node writes reverting an update of the tree.
+/// Each write is made of the node key, the length of the node (0 if it is not stored) and the node itself.
+pub(crate) fn state_tree_undo_to_bytes(undo: &StateTreeUndo) -> Vec<u8> {
+    let mut res = Vec::new();
+    for (key, node) in undo {
+        res.extend(key);
+        match node {
+            Some(node) => {
+                res.push(node.len() as u8);
+                res.extend(node);
+            }
+            None => res.push(0u8),
+        }
+    }
+    res
+}
+
+/// Deserializes node writes reverting an update of the tree
+///
+/// # Returns
+/// The node writes, or `None` if the bytes are invalid
+pub(crate) fn state_tree_undo_from_bytes(bytes: &[u8]) -> Option<StateTreeUndo> {
+    let key_size = node_key(0, &[0u8; HASH_SIZE_BYTES]).len();
+    let mut undo = StateTreeUndo::new();
+    let mut cursor = 0;
+    while cursor < bytes.len() {
+        let key = bytes.get(cursor..cursor + key_size)?.to_vec();
+        let node_len = *bytes.get(cursor + key_size)? as usize;
+        cursor += key_size + 1;
+        let node = match node_len {
+            0 => None,
+            _ => Some(bytes.get(cursor..cursor + node_len)?.to_vec()),
+        };
+        cursor += node_len;
+        undo.insert(key, node);
+    }
+    Some(undo)
+}
+
+/// Gets the hash of the root of the state tree stored in the ledger metadata
+pub(crate) fn get_state_root(ledger: &FinalLedger) -> Hash {
+    get_node(
+        ledger,
+        &Metadata::new(),
+        STATE_TREE_DEPTH,
+        &[0u8; HASH_SIZE_BYTES],
+    )
+    .hash()
+}
+
+/// Builds the proof of the value of an item against the state root.
+///
+/// # Arguments
+/// * `ledger`: ledger whose metadata store the current tree
+/// * `overlay`: node writes applied over the current tree, to build the proof against a past version of it
+/// * `key`: leaf key of the item
+pub(crate) fn get_state_proof(
+    ledger: &FinalLedger,
+    overlay: &StateTreeUndo,
+    key: &[u8],
+) -> StateProof {
+    let path = Hash::compute_from(key).into_bytes();
+    let mut siblings = Vec::new();
+    let mut prefix = [0u8; HASH_SIZE_BYTES];
+    let mut node = get_node(ledger, overlay, STATE_TREE_DEPTH, &prefix);
+
+    // go down the internal nodes along the path of the item
+    while let Node::Internal { .. } = node {
+        let depth = siblings.len();
+        let height = STATE_TREE_DEPTH - depth - 1;
+        let mut sibling_prefix = prefix;
+        if get_path_bit(&path, depth) {
+            prefix[depth / 8] |= 0x80 >> (depth % 8);
+        } else {
+            sibling_prefix[depth / 8] |= 0x80 >> (depth % 8);
+        }
+        siblings.push(get_node(ledger, overlay, height, &sibling_prefix).hash());
+        node = get_node(ledger, overlay, height, &prefix);
+    }
+
+    let terminal = match node {
+        Node::Leaf { path, hash } => StateProofTerminal::Leaf {
+            path: Hash::from_bytes(&path).expect("critical: invalid state tree leaf path"),
+            hash,
+        },
+        _ => StateProofTerminal::Empty,
+    };
+    StateProof { siblings, terminal }
+}
+
+/// Updates the leaves of the state tree.
+///
+/// # Arguments
+/// * `ledger`: ledger whose metadata store the current tree
+/// * `leaves`: leaf updates
+/// * `metadata`: pending metadata writes, which are read as the latest version of the tree and receive the node writes
+///
+/// # Returns
+/// The hash of the root of the updated tree
+pub(crate) fn update_state_tree(
+    ledger: &FinalLedger,
+    leaves: StateTreeLeaves,
+    metadata: &mut Metadata,
+) -> Hash {
+    let updates: BTreeMap<Path, Option<Hash>> = leaves
+        .into_iter()
+        .map(|(key, value)| {
+            let path = Hash::compute_from(&key);
+            let hash = value.map(|value| leaf_hash(&path, &Hash::compute_from(&value)));
+            (path.into_bytes(), hash)
+        })
+        .collect();
+    let updates: Vec<(Path, Option<Hash>)> = updates.into_iter().collect();
+    let root_prefix = [0u8; HASH_SIZE_BYTES];
+    let root = get_node(ledger, metadata, STATE_TREE_DEPTH, &root_prefix);
+    update_subtree(
+        ledger,
+        metadata,
+        STATE_TREE_DEPTH,
+        root_prefix,
+        root,
+        &updates,
+    )
+    .hash()
+}
+
+/// Recursively applies the updates of leaves located in a subtree, and writes the nodes that changed.
+///
+/// # Arguments
+/// * `height`, `prefix`: position of the subtree
+/// * `current`: current node at that position
+/// * `updates`: leaf updates located in the subtree, sorted by path
+///
+/// # Returns
+/// The updated node at that position
+fn update_subtree(
+    ledger: &FinalLedger,
+    metadata: &mut Metadata,
+    height: usize,
+    prefix: Path,
+    current: Node,
+    updates: &[(Path, Option<Hash>)],
+) -> Node {
+    if updates.is_empty() {
+        return current;
+    }
+
+    let node = match current {
+        // the subtree is empty or holds a single leaf: rebuild it from its leaves
+        Node::Empty | Node::Leaf { .. } => {
+            let mut leaves: Vec<(Path, Hash)> = updates
+                .iter()
+                .filter_map(|(path, hash)| hash.map(|hash| (*path, hash)))
+                .collect();
+            // the existing leaf is kept unless it is updated
+            if let Node::Leaf { path, hash } = current {
+                if updates.binary_search_by(|(p, _)| p.cmp(&path)).is_err() {
+                    leaves.insert(leaves.partition_point(|(p, _)| *p < path), (path, hash));
+                }
+            }
+            build_subtree(metadata, height, prefix, &leaves)
+        }
+
+        // the subtree holds several leaves: update its children
+        Node::Internal { .. } => {
+            let bit_index = STATE_TREE_DEPTH - height;
+            let split = updates.partition_point(|(path, _)| !get_path_bit(path, bit_index));
+            let mut right_prefix = prefix;
+            right_prefix[bit_index / 8] |= 0x80 >> (bit_index % 8);
+            let children = [
+                (prefix, &updates[..split]),
+                (right_prefix, &updates[split..]),
+            ]
+            .map(|(child_prefix, child_updates)| {
+                let child = get_node(ledger, metadata, height - 1, &child_prefix);
+                update_subtree(
+                    ledger,
+                    metadata,
+                    height - 1,
+                    child_prefix,
+                    child,
+                    child_updates,
+                )
+            });
+            match children {
+                [Node::Empty, Node::Empty] => Node::Empty,
+                // a single leaf remains: it moves up to the current position
+                [leaf @ Node::Leaf { .. }, Node::Empty] => {
+                    set_node(metadata, height - 1, &prefix, Node::Empty);
+                    leaf
+                }
+                [Node::Empty, leaf @ Node::Leaf { .. }] => {
+                    set_node(metadata, height - 1, &right_prefix, Node::Empty);
+                    leaf
+                }
+                [left, right] => Node::Internal {
+                    hash: internal_hash(&left.hash(), &right.hash()),
+                },
+            }
+        }
+    };
+    set_node(metadata, height, &prefix, node);
+    node
+}
+
+/// Builds a subtree that has no nodes stored below its position, and writes its nodes.
+///
+/// # Arguments
+/// * `height`, `prefix`: position of the subtree
+/// * `leaves`: leaves of the subtree, sorted by path
+///
+/// # Returns
+/// The node at the position of the subtree. It is written by the caller.
+fn build_subtree(
+    metadata: &mut Metadata,
+    height: usize,
+    prefix: Path,
+    leaves: &[(Path, Hash)],
+) -> Node {
+    match leaves {
+        [] => Node::Empty,
+        [(path, hash)] => Node::Leaf {
+            path: *path,
+            hash: *hash,
+        },
+        _ => {
+            let bit_index = STATE_TREE_DEPTH - height;
+            let split = leaves.partition_point(|(path, _)| !get_path_bit(path, bit_index));
+            let mut right_prefix = prefix;
+            right_prefix[bit_index / 8] |= 0x80 >> (bit_index % 8);
+            let left = build_subtree(metadata, height - 1, prefix, &leaves[..split]);
+            set_node(metadata, height - 1, &prefix, left);
+            let right = build_subtree(metadata, height - 1, right_prefix, &leaves[split..]);
+            set_node(metadata, height - 1, &right_prefix, right);
+            Node::Internal {
+                hash: internal_hash(&left.hash(), &right.hash()),
+            }
+        }
+    }
+}
@@ -2,34 +2,88 @@
 
 //! This file defines tools to test the final state bootstrap
 
-use crate::FinalStateBootstrap;
-use massa_async_pool::AsyncMessage;
+use crate::{
+    state_tree::{add_async_pool_changes_leaves, update_state_tree, StateTreeLeaves},
+    FinalState, FinalStateConfig, FinalStatePart,
+};
+use massa_async_pool::{AsyncMessage, AsyncPoolChanges};
 use massa_ledger::LedgerEntry;
 use massa_models::{Address, Slot};
 use std::collections::BTreeMap;
 
-/// creates a final state bootstrap from components
-pub fn make_bootstrap_state(
+/// creates a final state from components.
+/// The initial ledger file of the configuration needs to exist.
+pub fn create_final_state(
+    config: FinalStateConfig,
     slot: Slot,
     sorted_ledger: BTreeMap<Address, LedgerEntry>,
     messages: Vec<AsyncMessage>,
-) -> FinalStateBootstrap {
-    FinalStateBootstrap {
-        slot,
-        ledger: massa_ledger::test_exports::make_bootstrap_state(sorted_ledger),
-        async_pool: massa_async_pool::test_exports::make_bootstrap_state(messages),
+) -> FinalState {
+    let mut final_state = FinalState::new(config).expect("could not create final state");
+    final_state.reset();
+
+    // write the ledger
+    let last_address = final_state
+        .set_bootstrap_part(
+            FinalStatePart {
+                slot,
+                ledger_part: massa_ledger::test_exports::make_bootstrap_state(sorted_ledger),
+                final_state_changes: Vec::new(),
+                async_pool: None,
+                state_root: None,
+            },
+            None,
+        )
+        .expect("could not set the ledger");
+
+    // compute the state root the final state will have once the messages are added
+    let mut async_pool_changes = AsyncPoolChanges::default();
+    for msg in &messages {
+        async_pool_changes.push_add(msg.compute_id(), msg.clone());
     }
+    let mut leaves = StateTreeLeaves::new();
+    add_async_pool_changes_leaves(&async_pool_changes, &mut leaves);
+    let state_root = update_state_tree(&final_state.ledger, leaves, &mut BTreeMap::new());
+
+    // complete the final state with the asynchronous pool
+    final_state
+        .set_bootstrap_part(
+            FinalStatePart {
+                slot,
+                ledger_part: massa_ledger::test_exports::make_bootstrap_state(BTreeMap::new()),
+                final_state_changes: Vec::new(),
+                async_pool: Some(massa_async_pool::test_exports::make_bootstrap_state(
+                    messages,
+                )),
+                state_root: Some(state_root),
+            },
+            last_address,
+        )
+        .expect("could not set the asynchronous pool");
+    final_state
 }
 
-/// asserts that two `FinalStateBootstrap` are equal
-pub fn assert_eq_final_state_bootstrap(v1: &FinalStateBootstrap, v2: &FinalStateBootstrap) {
+/// asserts that two `FinalState` are equal
+pub fn assert_eq_final_state(v1: &FinalState, v2: &FinalState) {
     // compare slots
     assert_eq!(v1.slot, v2.slot, "final slot mismatch");
 
-    // compare ledger bootstrap states
-    massa_ledger::test_exports::assert_eq_ledger_bootstrap_state(&v1.ledger, &v2.ledger);
+    // compare state roots
+    assert_eq!(
+        v1.get_state_root(),
+        v2.get_state_root(),
+        "state root mismatch"
+    );
+
+    // compare ledgers
+    massa_ledger::test_exports::assert_eq_ledger_bootstrap_state(
+        &v1.ledger.get_ledger_part(None, usize::MAX),
+        &v2.ledger.get_ledger_part(None, usize::MAX),
+    );
+
+    // compare async pools
     massa_async_pool::test_exports::assert_eq_async_pool_bootstrap_state(
-        &v1.async_pool,
-        &v2.async_pool,
+        &v1.async_pool.get_bootstrap_snapshot(),
+        &v2.async_pool.get_bootstrap_snapshot(),
     );
 }
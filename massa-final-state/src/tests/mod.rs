@@ -1 +1,25 @@
 //! Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+use crate::FinalStateConfig;
+use massa_async_pool::AsyncPoolConfig;
+use massa_ledger::LedgerConfig;
+use std::collections::BTreeMap;
+use tempfile::{NamedTempFile, TempDir};
+
+impl FinalStateConfig {
+    /// get a final state configuration with an empty initial ledger,
+    /// the returned temporary file and directory must be kept for the lifetime of the final state
+    pub(crate) fn sample(final_history_length: usize) -> (Self, NamedTempFile, TempDir) {
+        let (ledger_config, keep_file, keep_dir) = LedgerConfig::sample(&BTreeMap::new());
+        (
+            FinalStateConfig {
+                ledger_config,
+                async_pool_config: AsyncPoolConfig { max_length: 10 },
+                final_history_length,
+                thread_count: 2,
+            },
+            keep_file,
+            keep_dir,
+        )
+    }
+}
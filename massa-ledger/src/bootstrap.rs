@@ -1,6 +1,6 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
-//! Provides serializable structures for bootstrapping the `FinalLedger`
+//! Provides serializable structures for bootstrapping the `FinalLedger` in parts
 
 use crate::LedgerEntry;
 use massa_models::{
@@ -10,14 +10,26 @@ use massa_models::{
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-/// Represents a snapshot of the ledger state,
-/// which is enough to fully bootstrap a `FinalLedger`
+/// Represents a part of the ledger: a range of full ledger entries sorted by address.
+/// The ledger is streamed to bootstrapping nodes as a sequence of consecutive parts.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FinalLedgerBootstrapState {
-    /// sorted ledger
+    /// sorted ledger part
     pub(crate) sorted_ledger: BTreeMap<Address, LedgerEntry>,
 }
 
+impl FinalLedgerBootstrapState {
+    /// Checks whether the part contains no entries, which happens once the whole ledger was sent
+    pub fn is_empty(&self) -> bool {
+        self.sorted_ledger.is_empty()
+    }
+
+    /// Gets the entries of the part, sorted by address
+    pub fn get_entries(&self) -> &BTreeMap<Address, LedgerEntry> {
+        &self.sorted_ledger
+    }
+}
+
 /// Allows serializing the `FinalLedgerBootstrapState` to a compact binary representation
 impl SerializeCompact for FinalLedgerBootstrapState {
     fn to_bytes_compact(&self) -> Result<Vec<u8>, massa_models::ModelsError> {
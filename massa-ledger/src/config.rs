@@ -9,4 +9,8 @@ use std::path::PathBuf;
 pub struct LedgerConfig {
     /// initial SCE ledger file
     pub initial_sce_ledger_path: PathBuf,
+    /// path to the directory of the on-disk ledger database
+    pub disk_ledger_path: PathBuf,
+    /// maximal number of ledger values kept in the in-memory cache
+    pub ledger_cache_capacity: usize,
 }
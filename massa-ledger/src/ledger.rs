@@ -2,9 +2,13 @@
 
 //! This file defines the final ledger associating addresses to their balances, bytecode and data.
 
-use crate::ledger_changes::LedgerChanges;
+use crate::ledger_changes::{LedgerChanges, LedgerEntryUpdate};
+use crate::ledger_db::{
+    balance_from_bytes, balance_key, balance_to_bytes, bytecode_key, data_key, storage_deposit_key,
+    LedgerBatch, LedgerDB,
+};
 use crate::ledger_entry::LedgerEntry;
-use crate::types::{Applicable, SetUpdateOrDelete};
+use crate::types::{Applicable, SetOrDelete, SetOrKeep, SetUpdateOrDelete};
 use crate::{FinalLedgerBootstrapState, LedgerConfig, LedgerError};
 use massa_hash::Hash;
 use massa_models::{Address, Amount};
@@ -15,43 +19,18 @@ use std::collections::BTreeMap;
 /// The ledger size can be very high: it can exceed 1 terabyte.
 /// To allow for storage on disk, the ledger uses trees and has `O(log(N))` access, insertion and deletion complexity.
 ///
-/// Note: currently the ledger is stored in RAM. TODO put it on the hard drive with cache.
+/// The ledger is stored on disk (see `ledger_db.rs`) and hot values are cached in RAM.
 pub struct FinalLedger {
     /// ledger configuration
-    _config: LedgerConfig,
-    /// ledger tree, sorted by address
-    sorted_ledger: BTreeMap<Address, LedgerEntry>,
+    config: LedgerConfig,
+    /// on-disk ledger database, sorted by address
+    db: LedgerDB,
 }
 
 /// Allows applying `LedgerChanges` to the final ledger
 impl Applicable<LedgerChanges> for FinalLedger {
     fn apply(&mut self, changes: LedgerChanges) {
-        // for all incoming changes
-        for (addr, change) in changes.0 {
-            match change {
-                // the incoming change sets a ledger entry to a new one
-                SetUpdateOrDelete::Set(new_entry) => {
-                    // inserts/overwrites the entry with the incoming one
-                    self.sorted_ledger.insert(addr, new_entry);
-                }
-
-                // the incoming change updates an existing ledger entry
-                SetUpdateOrDelete::Update(entry_update) => {
-                    // applies the updates to the entry
-                    // if the entry does not exist, inserts a default one and applies the updates to it
-                    self.sorted_ledger
-                        .entry(addr)
-                        .or_insert_with(Default::default)
-                        .apply(entry_update);
-                }
-
-                // the incoming change deletes a ledger entry
-                SetUpdateOrDelete::Delete => {
-                    // delete the entry, if it exists
-                    self.sorted_ledger.remove(&addr);
-                }
-            }
-        }
+        self.apply_with_metadata(changes, Default::default());
     }
 }
 
@@ -74,52 +53,81 @@ pub(crate) use init_file_error;
 impl FinalLedger {
     /// Initializes a new `FinalLedger` by reading its initial state from file.
     pub fn new(config: LedgerConfig) -> Result<Self, LedgerError> {
+        let mut ledger = FinalLedger::load(config)?;
+        ledger.reset_to_initial_ledger()?;
+        Ok(ledger)
+    }
+
+    /// Opens the `FinalLedger` previously stored on disk, without resetting it.
+    /// The metadata written along with the ledger can be used to check what state it is in.
+    pub fn load(config: LedgerConfig) -> Result<Self, LedgerError> {
+        let db = LedgerDB::new(&config)?;
+        Ok(FinalLedger { config, db })
+    }
+
+    /// Resets the ledger to its initial state read from file, removing all the other entries and metadata.
+    /// Used when the ledger stored on disk cannot be resumed from.
+    pub fn reset_to_initial_ledger(&mut self) -> Result<(), LedgerError> {
         // load the ledger tree from file
-        let sorted_ledger = serde_json::from_str::<BTreeMap<Address, Amount>>(
+        let config = &self.config;
+        let initial_ledger = serde_json::from_str::<BTreeMap<Address, Amount>>(
             &std::fs::read_to_string(&config.initial_sce_ledger_path)
                 .map_err(init_file_error!("loading", config))?,
         )
-        .map_err(init_file_error!("parsing", config))?
-        .into_iter()
-        .map(|(address, balance)| {
-            (
-                address,
-                LedgerEntry {
-                    parallel_balance: balance,
-                    ..Default::default()
-                },
-            )
-        })
-        .collect();
+        .map_err(init_file_error!("parsing", config))?;
 
-        // generate the final ledger
-        Ok(FinalLedger {
-            sorted_ledger,
-            _config: config,
-        })
+        // reset the ledger database to the initial ledger
+        self.db.clear()?;
+        let mut batch = LedgerBatch::default();
+        for (address, balance) in initial_ledger {
+            let entry = LedgerEntry {
+                parallel_balance: balance,
+                ..Default::default()
+            };
+            self.db.put_entry(&address, entry, &mut batch);
+        }
+        self.db.write_batch(batch, LedgerBatch::default());
+        Ok(())
     }
 
-    /// Initialize a `FinalLedger` from a bootstrap state
-    ///
-    /// TODO: This loads the whole ledger in RAM. Switch to streaming in the future
+    /// Removes all the entries and metadata of the ledger.
+    /// Used before receiving the ledger in parts from a bootstrap server.
+    pub fn reset(&mut self) {
+        self.db
+            .clear()
+            .unwrap_or_else(|err| panic!("critical: could not reset the ledger: {}", err));
+    }
+
+    /// Gets a part of the ledger to stream it to a bootstrapping node.
     ///
     /// # Arguments
-    /// * configuration: ledger configuration
-    /// * state: bootstrap state
-    pub fn from_bootstrap_state(config: LedgerConfig, state: FinalLedgerBootstrapState) -> Self {
-        FinalLedger {
-            sorted_ledger: state.sorted_ledger,
-            _config: config,
+    /// * `last_address`: last address of the previous part, or `None` to start from the beginning of the ledger
+    /// * `max_size`: approximate maximal size of the part in bytes. Entries are never split, so the part can be a bit larger.
+    ///
+    /// # Returns
+    /// The full entries that come after `last_address` in address order. The part is empty once the whole ledger was sent.
+    pub fn get_ledger_part(
+        &self,
+        last_address: Option<&Address>,
+        max_size: usize,
+    ) -> FinalLedgerBootstrapState {
+        FinalLedgerBootstrapState {
+            sorted_ledger: self.db.get_entries_part(last_address, max_size),
         }
     }
 
-    /// Gets a snapshot of the ledger to bootstrap other nodes
+    /// Writes a part of the ledger received from a bootstrap server.
     ///
-    /// TODO: This loads the whole ledger in RAM. Switch to streaming in the future
-    pub fn get_bootstrap_state(&self) -> FinalLedgerBootstrapState {
-        FinalLedgerBootstrapState {
-            sorted_ledger: self.sorted_ledger.clone(),
+    /// # Returns
+    /// The last address of the part, or `None` if the part is empty
+    pub fn set_ledger_part(&mut self, part: FinalLedgerBootstrapState) -> Option<Address> {
+        let last_address = part.sorted_ledger.keys().next_back().copied();
+        let mut batch = LedgerBatch::default();
+        for (address, entry) in part.sorted_ledger {
+            self.db.put_entry(&address, entry, &mut batch);
         }
+        self.db.write_batch(batch, LedgerBatch::default());
+        last_address
     }
 
     /// Gets a copy of a full ledger entry.
@@ -130,7 +138,99 @@ impl FinalLedger {
     /// TODO: in the future, never manipulate full ledger entries because their datastore can be huge
     /// `https://github.com/massalabs/massa/issues/2342`
     pub fn get_full_entry(&self, addr: &Address) -> Option<LedgerEntry> {
-        self.sorted_ledger.get(addr).cloned()
+        self.db.get_entry(addr)
+    }
+
+    /// Computes the changes that revert other changes once they are applied to the ledger.
+    /// Must be called before applying `changes`: the reverse changes hold the current values of the modified items.
+    ///
+    /// # Arguments
+    /// * `changes`: changes about to be applied
+    ///
+    /// # Returns
+    /// The changes bringing the ledger back to its current state once `changes` are applied
+    pub fn get_reverse_changes(&self, changes: &LedgerChanges) -> LedgerChanges {
+        let mut reverse_changes = LedgerChanges::default();
+        for (addr, change) in &changes.0 {
+            let reverse_change = match change {
+                // entries being replaced or deleted are restored as a whole
+                SetUpdateOrDelete::Set(_) | SetUpdateOrDelete::Delete => {
+                    match self.get_full_entry(addr) {
+                        Some(entry) => SetUpdateOrDelete::Set(entry),
+                        None => SetUpdateOrDelete::Delete,
+                    }
+                }
+                // only the updated items of an existing entry are restored
+                SetUpdateOrDelete::Update(update) => {
+                    let parallel_balance = match self.get_parallel_balance(addr) {
+                        Some(balance) => balance,
+                        None => {
+                            reverse_changes.0.insert(*addr, SetUpdateOrDelete::Delete);
+                            continue;
+                        }
+                    };
+                    SetUpdateOrDelete::Update(LedgerEntryUpdate {
+                        parallel_balance: match update.parallel_balance {
+                            SetOrKeep::Set(_) => SetOrKeep::Set(parallel_balance),
+                            SetOrKeep::Keep => SetOrKeep::Keep,
+                        },
+                        bytecode: match update.bytecode {
+                            SetOrKeep::Set(_) => {
+                                SetOrKeep::Set(self.get_bytecode(addr).unwrap_or_default())
+                            }
+                            SetOrKeep::Keep => SetOrKeep::Keep,
+                        },
+                        datastore: update
+                            .datastore
+                            .keys()
+                            .map(|key| match self.get_data_entry(addr, key) {
+                                Some(value) => (*key, SetOrDelete::Set(value)),
+                                None => (*key, SetOrDelete::Delete),
+                            })
+                            .collect(),
+                        storage_deposit: match update.storage_deposit {
+                            SetOrKeep::Set(_) => {
+                                SetOrKeep::Set(self.get_storage_deposit(addr).unwrap_or_default())
+                            }
+                            SetOrKeep::Keep => SetOrKeep::Keep,
+                        },
+                    })
+                }
+            };
+            reverse_changes.0.insert(*addr, reverse_change);
+        }
+        reverse_changes
+    }
+
+    /// Lists the ledger entries that exist but are empty once some changes are applied:
+    /// their parallel balance and storage deposit are zero and they have neither bytecode nor datastore entries.
+    /// Must be called before applying `changes`. Only the entries modified by `changes` are considered.
+    pub fn get_emptied_entries(&self, changes: &LedgerChanges) -> Vec<Address> {
+        changes
+            .0
+            .keys()
+            .filter(|addr| {
+                changes.entry_exists_or_else(addr, || self.entry_exists(addr))
+                    && changes
+                        .get_parallel_balance_or_else(addr, || self.get_parallel_balance(addr))
+                        .unwrap_or_default()
+                        .is_zero()
+                    && changes
+                        .get_storage_deposit_or_else(addr, || self.get_storage_deposit(addr))
+                        .unwrap_or_default()
+                        .is_zero()
+                    && changes
+                        .get_bytecode_or_else(addr, || self.get_bytecode(addr))
+                        .unwrap_or_default()
+                        .is_empty()
+                    && changes
+                        .get_datastore_keys_or_else(addr, &[], None, 1, |count| {
+                            self.get_datastore_keys(addr, &[], None, count)
+                        })
+                        .is_empty()
+            })
+            .copied()
+            .collect()
     }
 
     /// Gets the parallel balance of a ledger entry
@@ -138,7 +238,9 @@ impl FinalLedger {
     /// # Returns
     /// The parallel balance, or None if the ledger entry was not found
     pub fn get_parallel_balance(&self, addr: &Address) -> Option<Amount> {
-        self.sorted_ledger.get(addr).map(|v| v.parallel_balance)
+        self.db.get(&balance_key(addr)).map(|bytes| {
+            balance_from_bytes(&bytes).expect("critical: invalid balance in the ledger database")
+        })
     }
 
     /// Gets a copy of the bytecode of a ledger entry
@@ -146,7 +248,26 @@ impl FinalLedger {
     /// # Returns
     /// A copy of the found bytecode, or None if the ledger entry was not found
     pub fn get_bytecode(&self, addr: &Address) -> Option<Vec<u8>> {
-        self.sorted_ledger.get(addr).map(|v| v.bytecode.clone())
+        self.db.get(&bytecode_key(addr))
+    }
+
+    /// Gets the storage deposit of a ledger entry
+    ///
+    /// # Returns
+    /// The storage deposit, or None if the ledger entry was not found
+    pub fn get_storage_deposit(&self, addr: &Address) -> Option<Amount> {
+        if !self.entry_exists(addr) {
+            return None;
+        }
+        // entries written before storage deposits were recorded have no deposit
+        Some(
+            self.db
+                .get(&storage_deposit_key(addr))
+                .map_or_else(Amount::default, |bytes| {
+                    balance_from_bytes(&bytes)
+                        .expect("critical: invalid storage deposit in the ledger database")
+                }),
+        )
     }
 
     /// Checks if a ledger entry exists
@@ -154,7 +275,7 @@ impl FinalLedger {
     /// # Returns
     /// true if it exists, false otherwise.
     pub fn entry_exists(&self, addr: &Address) -> bool {
-        self.sorted_ledger.contains_key(addr)
+        self.db.contains_key(&balance_key(addr))
     }
 
     /// Gets a copy of the value of a datastore entry for a given address.
@@ -166,9 +287,7 @@ impl FinalLedger {
     /// # Returns
     /// A copy of the datastore value, or `None` if the ledger entry or datastore entry was not found
     pub fn get_data_entry(&self, addr: &Address, key: &Hash) -> Option<Vec<u8>> {
-        self.sorted_ledger
-            .get(addr)
-            .and_then(|v| v.datastore.get(key).cloned())
+        self.db.get(&data_key(addr, key))
     }
 
     /// Checks for the existence of a datastore entry for a given address.
@@ -180,8 +299,310 @@ impl FinalLedger {
     /// # Returns
     /// true if the datastore entry was found, or false if the ledger entry or datastore entry was not found
     pub fn has_data_entry(&self, addr: &Address, key: &Hash) -> bool {
-        self.sorted_ledger
-            .get(addr)
-            .map_or(false, |v| v.datastore.contains_key(key))
+        self.db.contains_key(&data_key(addr, key))
+    }
+
+    /// Lists the datastore keys of an address in increasing order, for example to paginate over them.
+    ///
+    /// # Arguments
+    /// * `addr`: target address
+    /// * `prefix`: only the keys whose bytes start with `prefix` are listed
+    /// * `start_after`: only the keys strictly greater than `start_after` are listed, if provided
+    /// * `max_count`: maximal number of listed keys
+    ///
+    /// # Returns
+    /// The listed keys, empty if the ledger entry was not found
+    pub fn get_datastore_keys(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+    ) -> Vec<Hash> {
+        self.db
+            .get_datastore_keys(addr, prefix, start_after, max_count)
+    }
+
+    /// Applies `LedgerChanges` to the final ledger, and writes metadata in the same atomic batch.
+    ///
+    /// Metadata are opaque key-value pairs stored alongside the ledger by its owner
+    /// (for example the slot the final state is attached to), so that they always match the ledger contents on disk.
+    ///
+    /// # Arguments
+    /// * `changes`: ledger changes to apply
+    /// * `metadata`: metadata writes, a `None` value deletes the key
+    pub fn apply_with_metadata(
+        &mut self,
+        changes: LedgerChanges,
+        metadata: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    ) {
+        // all the writes are gathered in a single batch so that the changes are applied atomically
+        let mut batch = LedgerBatch::default();
+
+        // for all incoming changes
+        for (addr, change) in changes.0 {
+            match change {
+                // the incoming change sets a ledger entry to a new one
+                SetUpdateOrDelete::Set(new_entry) => {
+                    // remove the keys of the previous entry (datastore included) before writing the new one
+                    self.db.delete_entry(&addr, &mut batch);
+                    self.db.put_entry(&addr, new_entry, &mut batch);
+                }
+
+                // the incoming change updates an existing ledger entry
+                SetUpdateOrDelete::Update(entry_update) => {
+                    // if the entry does not exist, inserts a default one and applies the updates to it
+                    if !self.entry_exists(&addr) {
+                        self.db.put_entry(&addr, LedgerEntry::default(), &mut batch);
+                    }
+
+                    // applies the updates to the entry
+                    if let SetOrKeep::Set(balance) = entry_update.parallel_balance {
+                        batch.insert(balance_key(&addr), balance_to_bytes(&balance));
+                    }
+                    if let SetOrKeep::Set(bytecode) = entry_update.bytecode {
+                        batch.insert(bytecode_key(&addr), bytecode);
+                    }
+                    for (key, value_update) in entry_update.datastore {
+                        match value_update {
+                            SetOrDelete::Set(value) => batch.insert(data_key(&addr, &key), value),
+                            SetOrDelete::Delete => batch.remove(data_key(&addr, &key)),
+                        }
+                    }
+                    if let SetOrKeep::Set(storage_deposit) = entry_update.storage_deposit {
+                        batch.insert(
+                            storage_deposit_key(&addr),
+                            balance_to_bytes(&storage_deposit),
+                        );
+                    }
+                }
+
+                // the incoming change deletes a ledger entry
+                SetUpdateOrDelete::Delete => {
+                    // delete the entry, if it exists
+                    self.db.delete_entry(&addr, &mut batch);
+                }
+            }
+        }
+
+        self.db.write_batch(batch, LedgerBatch(metadata));
+    }
+
+    /// Gets a copy of a metadata value written with `apply_with_metadata`
+    pub fn get_metadata(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.db.get_metadata(key)
+    }
+
+    /// Gets copies of all the metadata key-value pairs whose key starts with `prefix`, sorted by key
+    pub fn get_metadata_by_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.db.get_metadata_by_prefix(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use massa_models::prehash::Map;
+    use massa_models::SerializeCompact;
+    use std::str::FromStr;
+    use tempfile::{NamedTempFile, TempDir};
+
+    #[test]
+    fn test_disk_ledger_apply() {
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let other_addr = Address::from_bytes(&[2u8; 32]).unwrap();
+        let key = Hash::compute_from(b"key");
+
+        // initial ledger containing a single address
+        let initial_file = NamedTempFile::new().unwrap();
+        let initial: BTreeMap<Address, Amount> = vec![(addr, Amount::from_str("10").unwrap())]
+            .into_iter()
+            .collect();
+        serde_json::to_writer(initial_file.as_file(), &initial).unwrap();
+        let disk_ledger = TempDir::new().unwrap();
+        let config = LedgerConfig {
+            initial_sce_ledger_path: initial_file.path().to_path_buf(),
+            disk_ledger_path: disk_ledger.path().to_path_buf(),
+            ledger_cache_capacity: 1,
+        };
+        let mut ledger = FinalLedger::new(config).unwrap();
+        assert_eq!(
+            ledger.get_parallel_balance(&addr),
+            Some(Amount::from_str("10").unwrap())
+        );
+        assert!(!ledger.entry_exists(&other_addr));
+
+        // update the existing entry and create a new one
+        let mut changes = LedgerChanges::default();
+        changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Update(LedgerEntryUpdate {
+                parallel_balance: SetOrKeep::Set(Amount::from_str("7").unwrap()),
+                bytecode: SetOrKeep::Keep,
+                datastore: vec![(key, SetOrDelete::Set(vec![1, 2, 3]))]
+                    .into_iter()
+                    .collect::<Map<_, _>>(),
+                storage_deposit: SetOrKeep::Keep,
+            }),
+        );
+        changes.0.insert(
+            other_addr,
+            SetUpdateOrDelete::Set(LedgerEntry {
+                bytecode: vec![4, 5],
+                ..Default::default()
+            }),
+        );
+        ledger.apply(changes);
+        assert_eq!(
+            ledger.get_parallel_balance(&addr),
+            Some(Amount::from_str("7").unwrap())
+        );
+        assert_eq!(ledger.get_data_entry(&addr, &key), Some(vec![1, 2, 3]));
+        assert_eq!(ledger.get_bytecode(&other_addr), Some(vec![4, 5]));
+
+        // deleting an entry also deletes its datastore
+        let mut changes = LedgerChanges::default();
+        changes.0.insert(addr, SetUpdateOrDelete::Delete);
+        ledger.apply(changes);
+        assert!(!ledger.entry_exists(&addr));
+        assert!(!ledger.has_data_entry(&addr, &key));
+        assert_eq!(
+            ledger.get_ledger_part(None, usize::MAX).sorted_ledger.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_ledger_parts() {
+        let disk_ledger = TempDir::new().unwrap();
+        let config = LedgerConfig {
+            initial_sce_ledger_path: "".into(),
+            disk_ledger_path: disk_ledger.path().to_path_buf(),
+            ledger_cache_capacity: 10,
+        };
+        let mut ledger = FinalLedger::load(config).unwrap();
+        let mut changes = LedgerChanges::default();
+        for i in 1..=3u8 {
+            changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    parallel_balance: Amount::from_raw(i as u64),
+                    bytecode: vec![i; 10],
+                    datastore: vec![(Hash::compute_from(&[i]), vec![i; 5])]
+                        .into_iter()
+                        .collect(),
+                    ..Default::default()
+                }),
+            );
+        }
+        ledger.apply(changes);
+
+        // stream the ledger to another one, one entry per part
+        let other_disk_ledger = TempDir::new().unwrap();
+        let other_config = LedgerConfig {
+            initial_sce_ledger_path: "".into(),
+            disk_ledger_path: other_disk_ledger.path().to_path_buf(),
+            ledger_cache_capacity: 10,
+        };
+        let mut other_ledger = FinalLedger::load(other_config).unwrap();
+        let mut last_address = None;
+        let mut part_count = 0;
+        loop {
+            let part = ledger.get_ledger_part(last_address.as_ref(), 1);
+            if part.is_empty() {
+                break;
+            }
+            assert_eq!(part.sorted_ledger.len(), 1);
+            last_address = other_ledger.set_ledger_part(part);
+            part_count += 1;
+        }
+        assert_eq!(part_count, 3);
+        assert_eq!(
+            ledger
+                .get_ledger_part(None, usize::MAX)
+                .to_bytes_compact()
+                .unwrap(),
+            other_ledger
+                .get_ledger_part(None, usize::MAX)
+                .to_bytes_compact()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_datastore_keys() {
+        let disk_ledger = TempDir::new().unwrap();
+        let config = LedgerConfig {
+            initial_sce_ledger_path: "".into(),
+            disk_ledger_path: disk_ledger.path().to_path_buf(),
+            ledger_cache_capacity: 10,
+        };
+        let mut ledger = FinalLedger::load(config).unwrap();
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let other_addr = Address::from_bytes(&[2u8; 32]).unwrap();
+        let mut keys: Vec<Hash> = (0..20u8).map(|i| Hash::compute_from(&[i])).collect();
+        keys.sort();
+        let mut changes = LedgerChanges::default();
+        for a in [addr, other_addr] {
+            changes.0.insert(
+                a,
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    datastore: keys.iter().map(|key| (*key, vec![1])).collect(),
+                    ..Default::default()
+                }),
+            );
+        }
+        ledger.apply(changes);
+
+        // paginated listing
+        assert_eq!(ledger.get_datastore_keys(&addr, &[], None, 5), keys[..5]);
+        assert_eq!(
+            ledger.get_datastore_keys(&addr, &[], Some(&keys[4]), 100),
+            keys[5..]
+        );
+        assert!(ledger
+            .get_datastore_keys(&addr, &[], Some(&keys[19]), 100)
+            .is_empty());
+
+        // prefix filtering, starting before or after the first key with the prefix
+        let prefix = [keys[10].to_bytes()[0]];
+        let with_prefix: Vec<Hash> = keys
+            .iter()
+            .filter(|key| key.to_bytes()[0] == prefix[0])
+            .copied()
+            .collect();
+        assert_eq!(
+            ledger.get_datastore_keys(&addr, &prefix, Some(&keys[0]), 100),
+            with_prefix
+        );
+        assert_eq!(
+            ledger.get_datastore_keys(&addr, &prefix, Some(&with_prefix[0]), 100),
+            with_prefix[1..]
+        );
+
+        // merge with changes deleting and adding keys
+        let new_key = Hash::compute_from(b"new");
+        let mut changes = LedgerChanges::default();
+        changes.0.insert(
+            addr,
+            SetUpdateOrDelete::Update(LedgerEntryUpdate {
+                datastore: vec![
+                    (keys[0], SetOrDelete::Delete),
+                    (keys[1], SetOrDelete::Delete),
+                    (new_key, SetOrDelete::Set(vec![2])),
+                ]
+                .into_iter()
+                .collect::<Map<_, _>>(),
+                ..Default::default()
+            }),
+        );
+        let mut expected: Vec<Hash> = keys[2..].iter().copied().chain([new_key]).collect();
+        expected.sort();
+        assert_eq!(
+            changes.get_datastore_keys_or_else(&addr, &[], None, 5, |count| {
+                ledger.get_datastore_keys(&addr, &[], None, count)
+            }),
+            expected[..5]
+        );
     }
 }
@@ -4,9 +4,12 @@
 
 use crate::ledger_entry::LedgerEntry;
 use crate::types::{Applicable, SetOrDelete, SetOrKeep, SetUpdateOrDelete};
-use massa_hash::Hash;
-use massa_models::{prehash::Map, Address, Amount};
-use std::collections::hash_map;
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_models::{
+    array_from_slice, constants::ADDRESS_SIZE_BYTES, prehash::Map, u8_from_slice, Address, Amount,
+    DeserializeCompact, DeserializeVarInt, ModelsError, SerializeCompact, SerializeVarInt,
+};
+use std::collections::{hash_map, BTreeSet};
 
 /// represents an update to one or more fields of a `LedgerEntry`
 #[derive(Default, Debug, Clone)]
@@ -17,6 +20,8 @@ pub struct LedgerEntryUpdate {
     pub bytecode: SetOrKeep<Vec<u8>>,
     // change datastore entries
     pub datastore: Map<Hash, SetOrDelete<Vec<u8>>>,
+    /// change the storage deposit
+    pub storage_deposit: SetOrKeep<Amount>,
 }
 
 impl Applicable<LedgerEntryUpdate> for LedgerEntryUpdate {
@@ -25,6 +30,7 @@ impl Applicable<LedgerEntryUpdate> for LedgerEntryUpdate {
         self.parallel_balance.apply(update.parallel_balance);
         self.bytecode.apply(update.bytecode);
         self.datastore.extend(update.datastore);
+        self.storage_deposit.apply(update.storage_deposit);
     }
 }
 
@@ -152,6 +158,35 @@ impl LedgerChanges {
         }
     }
 
+    /// Tries to return the storage deposit of an entry
+    /// or gets it from a function if the entry's status is unknown.
+    ///
+    /// # Arguments
+    /// * `addr`: address for which to get the value
+    /// * `f`: fallback function with no arguments and returning `Option<Amount>`
+    ///
+    /// # Returns
+    /// * Some(v) if a value is present, where v is a copy of the value
+    /// * None if the value is absent
+    /// * f() if the value is unknown
+    pub fn get_storage_deposit_or_else<F: FnOnce() -> Option<Amount>>(
+        &self,
+        addr: &Address,
+        f: F,
+    ) -> Option<Amount> {
+        match self.0.get(addr) {
+            Some(SetUpdateOrDelete::Set(v)) => Some(v.storage_deposit),
+            Some(SetUpdateOrDelete::Update(LedgerEntryUpdate {
+                storage_deposit, ..
+            })) => match storage_deposit {
+                SetOrKeep::Set(v) => Some(*v),
+                SetOrKeep::Keep => f(),
+            },
+            Some(SetUpdateOrDelete::Delete) => None,
+            None => f(),
+        }
+    }
+
     /// Tries to return whether an entry exists
     /// or gets the information from a function if the entry's status is unknown.
     ///
@@ -281,6 +316,33 @@ impl LedgerChanges {
         }
     }
 
+    /// Set the storage deposit of an address.
+    /// If the address doesn't exist, its ledger entry is created.
+    ///
+    /// # Arguments
+    /// * `addr`: target address
+    /// * `storage_deposit`: storage deposit to set for the provided address
+    pub fn set_storage_deposit(&mut self, addr: Address, storage_deposit: Amount) {
+        match self.0.entry(addr) {
+            hash_map::Entry::Occupied(mut occ) => match occ.get_mut() {
+                SetUpdateOrDelete::Set(v) => v.storage_deposit = storage_deposit,
+                SetUpdateOrDelete::Update(u) => u.storage_deposit = SetOrKeep::Set(storage_deposit),
+                d @ SetUpdateOrDelete::Delete => {
+                    *d = SetUpdateOrDelete::Set(LedgerEntry {
+                        storage_deposit,
+                        ..Default::default()
+                    });
+                }
+            },
+            hash_map::Entry::Vacant(vac) => {
+                vac.insert(SetUpdateOrDelete::Update(LedgerEntryUpdate {
+                    storage_deposit: SetOrKeep::Set(storage_deposit),
+                    ..Default::default()
+                }));
+            }
+        }
+    }
+
     /// Tries to return a datastore entry for a given address,
     /// or gets it from a function if the value's status is unknown.
     ///
@@ -391,6 +453,70 @@ impl LedgerChanges {
         }
     }
 
+    /// Tries to list the datastore keys of an address in increasing order,
+    /// completing the list with the keys returned by a function when the changes do not fully determine it.
+    ///
+    /// # Arguments
+    /// * `addr`: target address
+    /// * `prefix`: only the keys whose bytes start with `prefix` are listed
+    /// * `start_after`: only the keys strictly greater than `start_after` are listed, if provided
+    /// * `max_count`: maximal number of listed keys
+    /// * `f`: fallback function listing the first keys (with the same filters) before the changes are applied,
+    ///   taking the maximal number of keys to list as argument
+    ///
+    /// # Returns
+    /// The first `max_count` matching keys once the changes are applied
+    pub fn get_datastore_keys_or_else<F: FnOnce(usize) -> Vec<Hash>>(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+        f: F,
+    ) -> Vec<Hash> {
+        let is_listed = |key: &Hash| {
+            key.to_bytes().starts_with(prefix) && start_after.iter().all(|start| key > *start)
+        };
+        match self.0.get(addr) {
+            // This ledger entry is being replaced by a new one: list the keys of the new datastore
+            Some(SetUpdateOrDelete::Set(v)) => v
+                .datastore
+                .keys()
+                .filter(|key| is_listed(key))
+                .take(max_count)
+                .copied()
+                .collect(),
+
+            // This ledger entry is being updated
+            Some(SetUpdateOrDelete::Update(LedgerEntryUpdate { datastore, .. })) => {
+                let mut set_keys = BTreeSet::new();
+                let mut deleted_keys = BTreeSet::new();
+                for (key, update) in datastore.iter().filter(|(key, _)| is_listed(key)) {
+                    match update {
+                        SetOrDelete::Set(_) => set_keys.insert(*key),
+                        SetOrDelete::Delete => deleted_keys.insert(*key),
+                    };
+                }
+                // each deleted key can remove one of the keys listed by the fallback function:
+                // list that many more so that at least `max_count` keys remain if there are enough of them
+                let mut keys: BTreeSet<Hash> = f(max_count.saturating_add(deleted_keys.len()))
+                    .into_iter()
+                    .filter(|key| !deleted_keys.contains(key))
+                    .collect();
+                keys.extend(set_keys);
+                keys.into_iter().take(max_count).collect()
+            }
+
+            // This ledger entry is being deleted: it has no datastore anymore
+            Some(SetUpdateOrDelete::Delete) => Vec::new(),
+
+            // This ledger entry is not being changed.
+            // We therefore have no info on its datastore.
+            // We call the fallback function and return its output.
+            None => f(max_count),
+        }
+    }
+
     /// Set a datastore entry for a given address.
     /// If the address doesn't exist, its ledger entry is created.
     /// If the datastore entry exists, its value is replaced, otherwise it is created.
@@ -440,4 +566,298 @@ impl LedgerChanges {
             }
         }
     }
+
+    /// Deletes a datastore entry for a given address.
+    /// Nothing happens if the ledger entry is being deleted.
+    ///
+    /// # Arguments
+    /// * `addr`: target address
+    /// * `key`: datastore key
+    pub fn delete_data_entry(&mut self, addr: Address, key: Hash) {
+        // Get the changes being applied to the ledger entry associated to that address
+        match self.0.entry(addr) {
+            // There are changes currently being applied to the ledger entry
+            hash_map::Entry::Occupied(mut occ) => {
+                match occ.get_mut() {
+                    // The ledger entry is being replaced by a new one:
+                    // remove the key from the datastore of the replacement entry
+                    SetUpdateOrDelete::Set(v) => {
+                        v.datastore.remove(&key);
+                    }
+
+                    // The ledger entry is being updated:
+                    // ensure that the update includes deleting the datastore entry
+                    SetUpdateOrDelete::Update(u) => {
+                        u.datastore.insert(key, SetOrDelete::Delete);
+                    }
+
+                    // The ledger entry is being deleted along with its datastore
+                    SetUpdateOrDelete::Delete => {}
+                }
+            }
+
+            // This ledger entry is not being changed
+            hash_map::Entry::Vacant(vac) => {
+                // Induce an Update to the ledger entry that deletes the datastore entry
+                vac.insert(SetUpdateOrDelete::Update(LedgerEntryUpdate {
+                    datastore: vec![(key, SetOrDelete::Delete)].into_iter().collect(),
+                    ..Default::default()
+                }));
+            }
+        }
+    }
+}
+
+/// Serializes a length-prefixed byte array
+fn bytes_to_compact(bytes: &[u8], res: &mut Vec<u8>) -> Result<(), ModelsError> {
+    let len: u64 = bytes.len().try_into().map_err(|_| {
+        ModelsError::SerializeError("could not convert byte array size to u64".into())
+    })?;
+    res.extend(len.to_varint_bytes());
+    res.extend(bytes);
+    Ok(())
+}
+
+/// Deserializes a length-prefixed byte array
+fn bytes_from_compact(buffer: &[u8]) -> Result<(Vec<u8>, usize), ModelsError> {
+    let (len, delta) = u64::from_varint_bytes(buffer)?;
+    let len: usize = len.try_into().map_err(|_| {
+        ModelsError::DeserializeError("could not convert byte array size to usize".into())
+    })?;
+    //TODO cap byte array length https://github.com/massalabs/massa/issues/1200
+    match buffer.get(delta..(delta + len)) {
+        Some(slice) => Ok((slice.to_vec(), delta + len)),
+        None => Err(ModelsError::DeserializeError(
+            "could not deserialize byte array: buffer too small".into(),
+        )),
+    }
+}
+
+/// Allows serializing the `LedgerEntryUpdate` to a compact binary representation
+impl SerializeCompact for LedgerEntryUpdate {
+    fn to_bytes_compact(&self) -> Result<Vec<u8>, ModelsError> {
+        let mut res: Vec<u8> = Vec::new();
+
+        // parallel balance
+        match &self.parallel_balance {
+            SetOrKeep::Set(balance) => {
+                res.push(0u8);
+                res.extend(balance.to_bytes_compact()?);
+            }
+            SetOrKeep::Keep => res.push(1u8),
+        }
+
+        // bytecode
+        match &self.bytecode {
+            SetOrKeep::Set(bytecode) => {
+                res.push(0u8);
+                bytes_to_compact(bytecode, &mut res)?;
+            }
+            SetOrKeep::Keep => res.push(1u8),
+        }
+
+        // datastore length
+        let datastore_len: u64 = self.datastore.len().try_into().map_err(|_| {
+            ModelsError::SerializeError("could not convert datastore size to u64".into())
+        })?;
+        res.extend(datastore_len.to_varint_bytes());
+
+        // datastore updates
+        for (key, value_update) in &self.datastore {
+            res.extend(key.to_bytes());
+            match value_update {
+                SetOrDelete::Set(value) => {
+                    res.push(0u8);
+                    bytes_to_compact(value, &mut res)?;
+                }
+                SetOrDelete::Delete => res.push(1u8),
+            }
+        }
+
+        // storage deposit
+        match &self.storage_deposit {
+            SetOrKeep::Set(storage_deposit) => {
+                res.push(0u8);
+                res.extend(storage_deposit.to_bytes_compact()?);
+            }
+            SetOrKeep::Keep => res.push(1u8),
+        }
+
+        Ok(res)
+    }
+}
+
+/// Allows deserializing a `LedgerEntryUpdate` from its compact binary representation
+impl DeserializeCompact for LedgerEntryUpdate {
+    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), ModelsError> {
+        let mut cursor = 0usize;
+
+        // parallel balance
+        let parallel_balance = match u8_from_slice(&buffer[cursor..])? {
+            0u8 => {
+                let (balance, delta) = Amount::from_bytes_compact(&buffer[(cursor + 1)..])?;
+                cursor += 1 + delta;
+                SetOrKeep::Set(balance)
+            }
+            1u8 => {
+                cursor += 1;
+                SetOrKeep::Keep
+            }
+            _ => {
+                return Err(ModelsError::DeserializeError(
+                    "invalid parallel balance update type".into(),
+                ))
+            }
+        };
+
+        // bytecode
+        let bytecode = match u8_from_slice(&buffer[cursor..])? {
+            0u8 => {
+                let (bytecode, delta) = bytes_from_compact(&buffer[(cursor + 1)..])?;
+                cursor += 1 + delta;
+                SetOrKeep::Set(bytecode)
+            }
+            1u8 => {
+                cursor += 1;
+                SetOrKeep::Keep
+            }
+            _ => {
+                return Err(ModelsError::DeserializeError(
+                    "invalid bytecode update type".into(),
+                ))
+            }
+        };
+
+        // datastore length
+        let (datastore_len, delta) = u64::from_varint_bytes(&buffer[cursor..])?;
+        //TODO cap datastore length https://github.com/massalabs/massa/issues/1200
+        cursor += delta;
+
+        // datastore updates
+        let mut datastore = Map::default();
+        for _ in 0..datastore_len {
+            let key = Hash::from_bytes(&array_from_slice(&buffer[cursor..])?)?;
+            cursor += HASH_SIZE_BYTES;
+            let value_update = match u8_from_slice(&buffer[cursor..])? {
+                0u8 => {
+                    let (value, delta) = bytes_from_compact(&buffer[(cursor + 1)..])?;
+                    cursor += 1 + delta;
+                    SetOrDelete::Set(value)
+                }
+                1u8 => {
+                    cursor += 1;
+                    SetOrDelete::Delete
+                }
+                _ => {
+                    return Err(ModelsError::DeserializeError(
+                        "invalid datastore update type".into(),
+                    ))
+                }
+            };
+            datastore.insert(key, value_update);
+        }
+
+        // storage deposit
+        let storage_deposit = match u8_from_slice(&buffer[cursor..])? {
+            0u8 => {
+                let (storage_deposit, delta) = Amount::from_bytes_compact(&buffer[(cursor + 1)..])?;
+                cursor += 1 + delta;
+                SetOrKeep::Set(storage_deposit)
+            }
+            1u8 => {
+                cursor += 1;
+                SetOrKeep::Keep
+            }
+            _ => {
+                return Err(ModelsError::DeserializeError(
+                    "invalid storage deposit update type".into(),
+                ))
+            }
+        };
+
+        Ok((
+            LedgerEntryUpdate {
+                parallel_balance,
+                bytecode,
+                datastore,
+                storage_deposit,
+            },
+            cursor,
+        ))
+    }
+}
+
+/// Allows serializing the `LedgerChanges` to a compact binary representation
+impl SerializeCompact for LedgerChanges {
+    fn to_bytes_compact(&self) -> Result<Vec<u8>, ModelsError> {
+        let mut res: Vec<u8> = Vec::new();
+
+        // number of changed entries
+        let changes_len: u64 = self.0.len().try_into().map_err(|_| {
+            ModelsError::SerializeError("could not convert ledger changes size to u64".into())
+        })?;
+        res.extend(changes_len.to_varint_bytes());
+
+        // entry changes
+        for (addr, change) in &self.0 {
+            res.extend(addr.to_bytes());
+            match change {
+                SetUpdateOrDelete::Set(entry) => {
+                    res.push(0u8);
+                    res.extend(entry.to_bytes_compact()?);
+                }
+                SetUpdateOrDelete::Update(entry_update) => {
+                    res.push(1u8);
+                    res.extend(entry_update.to_bytes_compact()?);
+                }
+                SetUpdateOrDelete::Delete => res.push(2u8),
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Allows deserializing `LedgerChanges` from their compact binary representation
+impl DeserializeCompact for LedgerChanges {
+    fn from_bytes_compact(buffer: &[u8]) -> Result<(Self, usize), ModelsError> {
+        let mut cursor = 0usize;
+
+        // number of changed entries
+        let (changes_len, delta) = u64::from_varint_bytes(&buffer[cursor..])?;
+        //TODO cap ledger changes length https://github.com/massalabs/massa/issues/1200
+        cursor += delta;
+
+        // entry changes
+        let mut changes = Map::default();
+        for _ in 0..changes_len {
+            let addr = Address::from_bytes(&array_from_slice(&buffer[cursor..])?)?;
+            cursor += ADDRESS_SIZE_BYTES;
+            let change = match u8_from_slice(&buffer[cursor..])? {
+                0u8 => {
+                    let (entry, delta) = LedgerEntry::from_bytes_compact(&buffer[(cursor + 1)..])?;
+                    cursor += 1 + delta;
+                    SetUpdateOrDelete::Set(entry)
+                }
+                1u8 => {
+                    let (entry_update, delta) =
+                        LedgerEntryUpdate::from_bytes_compact(&buffer[(cursor + 1)..])?;
+                    cursor += 1 + delta;
+                    SetUpdateOrDelete::Update(entry_update)
+                }
+                2u8 => {
+                    cursor += 1;
+                    SetUpdateOrDelete::Delete
+                }
+                _ => {
+                    return Err(ModelsError::DeserializeError(
+                        "invalid ledger entry change type".into(),
+                    ))
+                }
+            };
+            changes.insert(addr, change);
+        }
+
+        Ok((LedgerChanges(changes), cursor))
+    }
 }
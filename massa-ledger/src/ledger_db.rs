@@ -0,0 +1,354 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! This file defines the on-disk storage of the final ledger.
+//!
+//! Ledger entries are flattened into a single sorted sled tree. Every key starts with the
+//! address bytes followed by an identifier telling which part of the entry is stored:
+//! * `address | BALANCE_IDENT` → parallel balance (raw `u64`, big endian)
+//! * `address | BYTECODE_IDENT` → executable bytecode
+//! * `address | DATASTORE_IDENT | key` → datastore value
+//! * `address | STORAGE_DEPOSIT_IDENT` → storage deposit (raw `u64`, big endian)
+//!
+//! Keeping the address as prefix preserves the address ordering of the ledger
+//! and allows retrieving or deleting a whole entry with a prefix scan.
+//! Recently accessed values are kept in an LRU cache to avoid hitting the disk for hot entries.
+//!
+//! A second tree stores metadata: opaque key-value pairs managed by the owner of the ledger
+//! (typically the final state) that are committed in the same transaction as ledger changes.
+
+use crate::{LedgerConfig, LedgerEntry, LedgerError};
+use lru::LruCache;
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use massa_models::{constants::ADDRESS_SIZE_BYTES, Address, Amount};
+use parking_lot::Mutex;
+use sled::transaction::ConflictableTransactionResult;
+use sled::Transactional;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// name of the sled tree containing the ledger entries
+const LEDGER_TREE: &str = "final_ledger";
+/// name of the sled tree containing the metadata
+const METADATA_TREE: &str = "final_ledger_metadata";
+/// key identifier of the parallel balance of an entry
+const BALANCE_IDENT: u8 = 0u8;
+/// key identifier of the bytecode of an entry
+const BYTECODE_IDENT: u8 = 1u8;
+/// key identifier of the datastore values of an entry
+const DATASTORE_IDENT: u8 = 2u8;
+/// key identifier of the storage deposit of an entry
+const STORAGE_DEPOSIT_IDENT: u8 = 3u8;
+
+/// Database key of the parallel balance of an entry
+pub(crate) fn balance_key(addr: &Address) -> Vec<u8> {
+    let mut key = addr.to_bytes().to_vec();
+    key.push(BALANCE_IDENT);
+    key
+}
+
+/// Database key of the bytecode of an entry
+pub(crate) fn bytecode_key(addr: &Address) -> Vec<u8> {
+    let mut key = addr.to_bytes().to_vec();
+    key.push(BYTECODE_IDENT);
+    key
+}
+
+/// Database key of a datastore value of an entry
+pub(crate) fn data_key(addr: &Address, key: &Hash) -> Vec<u8> {
+    let mut res = addr.to_bytes().to_vec();
+    res.push(DATASTORE_IDENT);
+    res.extend(key.to_bytes());
+    res
+}
+
+/// Database key of the storage deposit of an entry
+pub(crate) fn storage_deposit_key(addr: &Address) -> Vec<u8> {
+    let mut key = addr.to_bytes().to_vec();
+    key.push(STORAGE_DEPOSIT_IDENT);
+    key
+}
+
+/// Serializes a balance for storage
+pub(crate) fn balance_to_bytes(balance: &Amount) -> Vec<u8> {
+    balance.to_raw().to_be_bytes().to_vec()
+}
+
+/// Deserializes a stored balance
+pub(crate) fn balance_from_bytes(bytes: &[u8]) -> Option<Amount> {
+    Some(Amount::from_raw(u64::from_be_bytes(bytes.try_into().ok()?)))
+}
+
+/// A set of writes to apply atomically to the ledger database.
+/// A `None` value means that the key is deleted.
+#[derive(Default)]
+pub(crate) struct LedgerBatch(pub(crate) BTreeMap<Vec<u8>, Option<Vec<u8>>>);
+
+impl LedgerBatch {
+    /// Converts the batch into a sled batch
+    fn to_sled_batch(&self) -> sled::Batch {
+        let mut sled_batch = sled::Batch::default();
+        for (key, value) in self.0.iter() {
+            match value {
+                Some(value) => sled_batch.insert(key.as_slice(), value.as_slice()),
+                None => sled_batch.remove(key.as_slice()),
+            }
+        }
+        sled_batch
+    }
+
+    /// Inserts or overwrites the value of a key
+    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.0.insert(key, Some(value));
+    }
+
+    /// Deletes a key
+    pub fn remove(&mut self, key: Vec<u8>) {
+        self.0.insert(key, None);
+    }
+}
+
+/// Disk-backed storage of the final ledger with an in-memory cache
+pub(crate) struct LedgerDB {
+    /// sorted tree containing the flattened ledger entries
+    tree: sled::Tree,
+    /// tree containing the metadata
+    metadata_tree: sled::Tree,
+    /// cache of recently read values (`None` caches the absence of a key)
+    cache: Mutex<LruCache<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl LedgerDB {
+    /// Opens the ledger database at the path given in the configuration
+    pub fn new(config: &LedgerConfig) -> Result<Self, LedgerError> {
+        let db = sled::Config::default()
+            .path(&config.disk_ledger_path)
+            .open()
+            .map_err(|err| LedgerError::DatabaseError(format!("could not open: {}", err)))?;
+        let tree = db
+            .open_tree(LEDGER_TREE)
+            .map_err(|err| LedgerError::DatabaseError(format!("could not open tree: {}", err)))?;
+        let metadata_tree = db
+            .open_tree(METADATA_TREE)
+            .map_err(|err| LedgerError::DatabaseError(format!("could not open tree: {}", err)))?;
+        Ok(LedgerDB {
+            tree,
+            metadata_tree,
+            cache: Mutex::new(LruCache::new(config.ledger_cache_capacity)),
+        })
+    }
+
+    /// Removes all entries and metadata from the database
+    pub fn clear(&self) -> Result<(), LedgerError> {
+        self.tree
+            .clear()
+            .map_err(|err| LedgerError::DatabaseError(format!("could not clear: {}", err)))?;
+        self.metadata_tree
+            .clear()
+            .map_err(|err| LedgerError::DatabaseError(format!("could not clear: {}", err)))?;
+        self.cache.lock().clear();
+        Ok(())
+    }
+
+    /// Reads the value of a key, from the cache if possible or from the disk otherwise
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(value) = self.cache.lock().get(key) {
+            return value.clone();
+        }
+        let value = self
+            .tree
+            .get(key)
+            .unwrap_or_else(|err| panic!("critical: could not read the ledger database: {}", err))
+            .map(|v| v.to_vec());
+        self.cache.lock().put(key.to_vec(), value.clone());
+        value
+    }
+
+    /// Checks whether a key is present
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Reads a full ledger entry by scanning all the keys prefixed by its address
+    pub fn get_entry(&self, addr: &Address) -> Option<LedgerEntry> {
+        let mut entries = self.read_entries(self.tree.scan_prefix(addr.to_bytes()), usize::MAX);
+        entries.remove(addr)
+    }
+
+    /// Reads the full ledger entries that come after `last_address` in address order
+    /// (from the beginning of the ledger if `last_address` is `None`),
+    /// stopping before the first entry that starts once `max_size` bytes were read
+    pub fn get_entries_part(
+        &self,
+        last_address: Option<&Address>,
+        max_size: usize,
+    ) -> BTreeMap<Address, LedgerEntry> {
+        let iter = match last_address {
+            Some(addr) => {
+                // all the keys of an entry are lower than `address | u8::MAX`
+                let mut start = addr.to_bytes().to_vec();
+                start.push(u8::MAX);
+                self.tree.range(start..)
+            }
+            None => self.tree.iter(),
+        };
+        self.read_entries(iter, max_size)
+    }
+
+    /// Lists the datastore keys of an address in increasing order.
+    ///
+    /// # Arguments
+    /// * `addr`: target address
+    /// * `prefix`: only the keys whose bytes start with `prefix` are listed
+    /// * `start_after`: only the keys strictly greater than `start_after` are listed, if provided
+    /// * `max_count`: maximal number of listed keys
+    pub fn get_datastore_keys(
+        &self,
+        addr: &Address,
+        prefix: &[u8],
+        start_after: Option<&Hash>,
+        max_count: usize,
+    ) -> Vec<Hash> {
+        let mut key_prefix = addr.to_bytes().to_vec();
+        key_prefix.push(DATASTORE_IDENT);
+        key_prefix.extend(prefix);
+
+        // start at the first key with the prefix, or right after `start_after` if it comes later
+        let start = match start_after.map(|key| data_key(addr, key)) {
+            Some(start_key) if start_key >= key_prefix => Bound::Excluded(start_key),
+            _ => Bound::Included(key_prefix.clone()),
+        };
+        self.tree
+            .range::<Vec<u8>, _>((start, Bound::Unbounded))
+            .keys()
+            .map(|key| {
+                key.unwrap_or_else(|err| {
+                    panic!("critical: could not iterate the ledger database: {}", err)
+                })
+            })
+            .take_while(|key| key.starts_with(&key_prefix))
+            .take(max_count)
+            .map(|key| {
+                let hash_bytes: &[u8; HASH_SIZE_BYTES] = key[(ADDRESS_SIZE_BYTES + 1)..]
+                    .try_into()
+                    .expect("critical: invalid datastore key in the ledger database");
+                Hash::from_bytes(hash_bytes)
+                    .expect("critical: invalid datastore key in the ledger database")
+            })
+            .collect()
+    }
+
+    /// Rebuilds ledger entries from an ordered iterator over database keys.
+    /// Entries are never split: reading stops at an entry boundary once `max_size` bytes were read.
+    fn read_entries(&self, iter: sled::Iter, max_size: usize) -> BTreeMap<Address, LedgerEntry> {
+        let mut res: BTreeMap<Address, LedgerEntry> = BTreeMap::new();
+        let mut size = 0usize;
+        for item in iter {
+            let (key, value) = item.unwrap_or_else(|err| {
+                panic!("critical: could not iterate the ledger database: {}", err)
+            });
+            let addr_bytes: &[u8; ADDRESS_SIZE_BYTES] = key[..ADDRESS_SIZE_BYTES]
+                .try_into()
+                .expect("critical: invalid key in the ledger database");
+            let addr = Address::from_bytes(addr_bytes)
+                .expect("critical: invalid address in the ledger database");
+            if size >= max_size && !res.contains_key(&addr) {
+                break;
+            }
+            size = size.saturating_add(key.len() + value.len());
+            let entry = res.entry(addr).or_default();
+            match key[ADDRESS_SIZE_BYTES] {
+                BALANCE_IDENT => {
+                    entry.parallel_balance = balance_from_bytes(&value)
+                        .expect("critical: invalid balance in the ledger database")
+                }
+                BYTECODE_IDENT => entry.bytecode = value.to_vec(),
+                DATASTORE_IDENT => {
+                    let hash_bytes: &[u8; HASH_SIZE_BYTES] = key[(ADDRESS_SIZE_BYTES + 1)..]
+                        .try_into()
+                        .expect("critical: invalid datastore key in the ledger database");
+                    entry.datastore.insert(
+                        Hash::from_bytes(hash_bytes)
+                            .expect("critical: invalid datastore key in the ledger database"),
+                        value.to_vec(),
+                    );
+                }
+                STORAGE_DEPOSIT_IDENT => {
+                    entry.storage_deposit = balance_from_bytes(&value)
+                        .expect("critical: invalid storage deposit in the ledger database")
+                }
+                _ => panic!("critical: unknown key identifier in the ledger database"),
+            }
+        }
+        res
+    }
+
+    /// Adds the writes of a full ledger entry to a batch
+    pub fn put_entry(&self, addr: &Address, entry: LedgerEntry, batch: &mut LedgerBatch) {
+        batch.insert(balance_key(addr), balance_to_bytes(&entry.parallel_balance));
+        batch.insert(bytecode_key(addr), entry.bytecode);
+        for (key, value) in entry.datastore {
+            batch.insert(data_key(addr, &key), value);
+        }
+        batch.insert(
+            storage_deposit_key(addr),
+            balance_to_bytes(&entry.storage_deposit),
+        );
+    }
+
+    /// Adds the deletion of all the keys of a ledger entry to a batch
+    pub fn delete_entry(&self, addr: &Address, batch: &mut LedgerBatch) {
+        for key in self.tree.scan_prefix(addr.to_bytes()).keys() {
+            let key = key.unwrap_or_else(|err| {
+                panic!("critical: could not iterate the ledger database: {}", err)
+            });
+            batch.remove(key.to_vec());
+        }
+    }
+
+    /// Atomically applies a batch of ledger writes and a batch of metadata writes to the database,
+    /// and refreshes the cached values
+    pub fn write_batch(&self, batch: LedgerBatch, metadata: LedgerBatch) {
+        let sled_batch = batch.to_sled_batch();
+        let sled_metadata = metadata.to_sled_batch();
+        (&self.tree, &self.metadata_tree)
+            .transaction(
+                |(tree, metadata_tree)| -> ConflictableTransactionResult<()> {
+                    tree.apply_batch(&sled_batch)?;
+                    metadata_tree.apply_batch(&sled_metadata)?;
+                    Ok(())
+                },
+            )
+            .unwrap_or_else(|err| {
+                panic!("critical: could not write the ledger database: {:?}", err)
+            });
+
+        // only refresh values that are already cached to avoid flushing hot entries on large writes
+        let mut cache = self.cache.lock();
+        for (key, value) in batch.0 {
+            if let Some(cached) = cache.peek_mut(&key) {
+                *cached = value;
+            }
+        }
+    }
+
+    /// Reads a metadata value
+    pub fn get_metadata(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.metadata_tree
+            .get(key)
+            .unwrap_or_else(|err| panic!("critical: could not read the ledger database: {}", err))
+            .map(|v| v.to_vec())
+    }
+
+    /// Reads all the metadata key-value pairs whose key starts with a given prefix, sorted by key
+    pub fn get_metadata_by_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.metadata_tree
+            .scan_prefix(prefix)
+            .map(|item| {
+                let (key, value) = item.unwrap_or_else(|err| {
+                    panic!("critical: could not iterate the ledger database: {}", err)
+                });
+                (key.to_vec(), value.to_vec())
+            })
+            .collect()
+    }
+}
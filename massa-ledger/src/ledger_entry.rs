@@ -23,6 +23,28 @@ pub struct LedgerEntry {
 
     /// A key-value store associating a hash to arbitrary bytes
     pub datastore: BTreeMap<Hash, Vec<u8>>,
+
+    /// Coins locked by the storage deposit paid for the bytecode and the datastore entries.
+    /// Freeing storage never refunds more than this amount.
+    pub storage_deposit: Amount,
+}
+
+impl LedgerEntry {
+    /// Gets the number of bytes stored by the entry that are subject to a storage deposit:
+    /// its bytecode and all its datastore entries
+    pub fn get_storage_size(&self) -> usize {
+        self.bytecode.len()
+            + self
+                .datastore
+                .values()
+                .map(|value| datastore_entry_storage_size(value))
+                .sum::<usize>()
+    }
+}
+
+/// Gets the number of bytes stored by a datastore entry holding `value`, including its key
+pub fn datastore_entry_storage_size(value: &[u8]) -> usize {
+    HASH_SIZE_BYTES + value.len()
 }
 
 /// A `LedgerEntryUpdate` can be applied to a `LedgerEntry`
@@ -34,6 +56,9 @@ impl Applicable<LedgerEntryUpdate> for LedgerEntry {
         // apply updates to the executable bytecode
         update.bytecode.apply_to(&mut self.bytecode);
 
+        // apply updates to the storage deposit
+        update.storage_deposit.apply_to(&mut self.storage_deposit);
+
         // iterate over all datastore updates
         for (key, value_update) in update.datastore {
             match value_update {
@@ -92,6 +117,9 @@ impl SerializeCompact for LedgerEntry {
             res.extend(value);
         }
 
+        // storage deposit
+        res.extend(self.storage_deposit.to_bytes_compact()?);
+
         Ok(res)
     }
 }
@@ -161,11 +189,16 @@ impl DeserializeCompact for LedgerEntry {
             datastore.insert(key, value);
         }
 
+        // storage deposit
+        let (storage_deposit, delta) = Amount::from_bytes_compact(&buffer[cursor..])?;
+        cursor += delta;
+
         Ok((
             LedgerEntry {
                 parallel_balance,
                 bytecode,
                 datastore,
+                storage_deposit,
             },
             cursor,
         ))
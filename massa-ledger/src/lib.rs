@@ -26,11 +26,14 @@
 //! ## `ledger.rs`
 //! Defines the `FinalLedger` that matches an address to a `LedgerEntry` (see `ledger_entry.rs`),
 //! and can be manipulated using `LedgerChanges` (see `ledger_changes.rs`).
-//! The `FinalLedger` is bootstrapped using tooling available in bootstrap.rs
+//! The `FinalLedger` is streamed to bootstrapping nodes in address-ordered parts using tooling available in bootstrap.rs
+//!
+//! ## `ledger_db.rs`
+//! Stores the final ledger on disk (using sled) and keeps the most recently used values in an LRU cache.
 //!
 //! ## `ledger_entry.rs`
 //! Represents an entry in the ledger for a given address.
-//! It contains balances, executable bytecode and an arbitrary datastore.
+//! It contains balances, executable bytecode, an arbitrary datastore and the storage deposit paid for them.
 //!
 //! ## `ledger_changes.rs`
 //! Represents a list of changes to ledger entries that
@@ -54,6 +57,7 @@ mod config;
 mod error;
 mod ledger;
 mod ledger_changes;
+mod ledger_db;
 mod ledger_entry;
 mod types;
 
@@ -62,7 +66,7 @@ pub use config::LedgerConfig;
 pub use error::LedgerError;
 pub use ledger::FinalLedger;
 pub use ledger_changes::LedgerChanges;
-pub use ledger_entry::LedgerEntry;
+pub use ledger_entry::{datastore_entry_storage_size, LedgerEntry};
 pub use types::{Applicable, SetOrDelete, SetOrKeep, SetUpdateOrDelete};
 
 #[cfg(test)]
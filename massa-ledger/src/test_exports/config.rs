@@ -5,7 +5,7 @@ use crate::LedgerConfig;
 use massa_models::{Address, Amount};
 use std::collections::BTreeMap;
 use std::io::Seek;
-use tempfile::NamedTempFile;
+use tempfile::{NamedTempFile, TempDir};
 
 /// Default value of `LedgerConfig` used for tests
 impl Default for LedgerConfig {
@@ -14,13 +14,17 @@ impl Default for LedgerConfig {
             // unused by the mock (you can use `LedgerConfig::sample()` to get
             // a NamedTempFile in addition)
             initial_sce_ledger_path: "".into(),
+            // tests opening a disk ledger provide their own directory
+            // (use `LedgerConfig::sample()` to get a TempDir that is removed on drop)
+            disk_ledger_path: "".into(),
+            ledger_cache_capacity: 1000,
         }
     }
 }
 
 impl LedgerConfig {
     /// get ledger and ledger configuration
-    pub fn sample(ledger: &BTreeMap<Address, Amount>) -> (Self, NamedTempFile) {
+    pub fn sample(ledger: &BTreeMap<Address, Amount>) -> (Self, NamedTempFile, TempDir) {
         let ledger_file_named = NamedTempFile::new().expect("cannot create temp file");
         serde_json::to_writer_pretty(ledger_file_named.as_file(), &ledger)
             .expect("unable to write ledger file");
@@ -28,11 +32,15 @@ impl LedgerConfig {
             .as_file()
             .seek(std::io::SeekFrom::Start(0))
             .expect("could not seek file");
+        let disk_ledger = TempDir::new().expect("cannot create temp directory");
         (
             Self {
                 initial_sce_ledger_path: ledger_file_named.path().to_path_buf(),
+                disk_ledger_path: disk_ledger.path().to_path_buf(),
+                ledger_cache_capacity: 1000,
             },
             ledger_file_named,
+            disk_ledger,
         )
     }
 }
@@ -1,10 +1,13 @@
 // Copyright (c) 2022 MASSA LABS <info@massa.net>
 
 use crate::address::AddressCycleProductionStats;
+use crate::execution::ExecutionReceipt;
 use crate::ledger_models::LedgerData;
 use crate::node::NodeId;
+use crate::output_event::AsyncMessageStatus;
 use crate::prehash::Map;
 use crate::prehash::Set;
+use crate::state_proof::StateProof;
 use crate::stats::{ConsensusStats, NetworkStats, PoolStats};
 use crate::SignedEndorsement;
 use crate::SignedOperation;
@@ -14,7 +17,7 @@ use crate::{
 use massa_hash::Hash;
 use massa_time::MassaTime;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::IpAddr;
 
 /// node status
@@ -96,6 +99,9 @@ pub struct OperationInfo {
     pub is_final: bool,
     /// the operation itself
     pub operation: SignedOperation,
+    /// receipt of the execution of the operation by the blockclique or the final state,
+    /// for executed smart contract operations
+    pub receipt: Option<ExecutionReceipt>,
 }
 
 impl OperationInfo {
@@ -105,6 +111,9 @@ impl OperationInfo {
         self.in_pool = self.in_pool || other.in_pool;
         self.in_blocks.extend(other.in_blocks.iter());
         self.is_final = self.is_final || other.is_final;
+        if self.receipt.is_none() {
+            self.receipt = other.receipt.clone();
+        }
     }
 }
 
@@ -122,6 +131,9 @@ impl std::fmt::Display for OperationInfo {
             writeln!(f, "\t- {}", block_id)?;
         }
         writeln!(f, "{}", self.operation)?;
+        if let Some(receipt) = &self.receipt {
+            writeln!(f, "{}", receipt)?;
+        }
         Ok(())
     }
 }
@@ -189,6 +201,266 @@ impl std::fmt::Display for SCELedgerInfo {
     }
 }
 
+/// Request of a page of the datastore keys of an address
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DatastoreKeysRequest {
+    /// address owning the datastore
+    pub address: Address,
+    /// only the keys whose bytes start with this prefix are listed
+    pub prefix: Vec<u8>,
+    /// only the keys strictly greater than this one are listed, if provided.
+    /// Use the last key of the previous page to get the next one.
+    pub start_after: Option<Hash>,
+    /// maximal number of keys to list
+    pub max_count: u64,
+}
+
+/// Datastore keys of an address, in increasing order
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DatastoreKeysInfo {
+    /// address owning the datastore
+    pub address: Address,
+    /// keys at the output of the latest final slot
+    pub final_keys: Vec<Hash>,
+    /// keys at the output of the latest executed candidate slot
+    pub candidate_keys: Vec<Hash>,
+}
+
+impl std::fmt::Display for DatastoreKeysInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Address: {}", self.address)?;
+        writeln!(f, "	Final keys:")?;
+        for key in &self.final_keys {
+            writeln!(f, "		{}", key)?;
+        }
+        writeln!(f, "	Candidate keys:")?;
+        for key in &self.candidate_keys {
+            writeln!(f, "		{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Filter used when listing the pending asynchronous messages.
+/// The messages matching all the provided criteria are listed in decreasing priority order.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct AsyncMessageFilter {
+    /// only the messages sent by this address are listed, if provided
+    pub sender: Option<Address>,
+    /// only the messages sent to this address are listed, if provided
+    pub destination: Option<Address>,
+    /// only the messages handled by this function are listed, if provided
+    pub handler: Option<String>,
+    /// only the messages still valid at this slot or later are listed, if provided
+    pub valid_from: Option<Slot>,
+    /// only the messages becoming valid before this slot are listed, if provided
+    pub valid_until: Option<Slot>,
+    /// only the messages with a lower priority than the message with this ID are listed, if provided.
+    /// Use the ID of the last message of the previous page to get the next one.
+    pub start_after: Option<String>,
+    /// maximal number of messages to list
+    pub max_count: u64,
+}
+
+/// Pending asynchronous message
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AsyncMessageInfo {
+    /// ID of the message, also found in the events about its outcome
+    pub id: String,
+    /// slot at which the message was emitted
+    pub emission_slot: Slot,
+    /// index of the message among the ones emitted at `emission_slot`
+    pub emission_index: u64,
+    /// address that sent the message
+    pub sender: Address,
+    /// address the message is sent to
+    pub destination: Address,
+    /// function of the destination bytecode handling the message
+    pub handler: String,
+    /// maximum gas to use when handling the message
+    pub max_gas: u64,
+    /// gas price of the message
+    pub gas_price: Amount,
+    /// coins credited to the destination when the message is handled
+    pub coins: Amount,
+    /// first slot at which the message is valid
+    pub validity_start: Slot,
+    /// slot from which the message is no longer valid
+    pub validity_end: Slot,
+    /// payload of the message
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Display for AsyncMessageInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Message {}", self.id)?;
+        writeln!(
+            f,
+            "	Emitted at slot {} (index {}) by {}",
+            self.emission_slot, self.emission_index, self.sender
+        )?;
+        writeln!(f, "	Handler: {} of {}", self.handler, self.destination)?;
+        writeln!(
+            f,
+            "	Valid from slot {} until slot {} (excluded)",
+            self.validity_start, self.validity_end
+        )?;
+        writeln!(
+            f,
+            "	Max gas: {}, gas price: {}, coins: {}",
+            self.max_gas, self.gas_price, self.coins
+        )?;
+        writeln!(f, "	Data size: {} bytes", self.data.len())?;
+        Ok(())
+    }
+}
+
+/// Pending asynchronous messages matching a filter
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AsyncMessagesInfo {
+    /// messages of the pool at the output of the latest final slot
+    pub final_messages: Vec<AsyncMessageInfo>,
+    /// messages of the pool at the output of the latest executed candidate slot
+    pub candidate_messages: Vec<AsyncMessageInfo>,
+}
+
+impl std::fmt::Display for AsyncMessagesInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Final messages:")?;
+        for msg in &self.final_messages {
+            write!(f, "{}", msg)?;
+        }
+        writeln!(f, "Candidate messages:")?;
+        for msg in &self.candidate_messages {
+            write!(f, "{}", msg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Request of the final values of a ledger entry at the output of a past final slot
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HistoricalLedgerEntryRequest {
+    /// address of the entry
+    pub address: Address,
+    /// final slot at the output of which the values are read
+    pub slot: Slot,
+    /// optional datastore key whose value is also read
+    pub datastore_key: Option<Hash>,
+}
+
+/// Final values of a ledger entry at the output of a past final slot
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HistoricalLedgerEntry {
+    /// address of the entry
+    pub address: Address,
+    /// final slot at the output of which the values are read
+    pub slot: Slot,
+    /// balance of the entry, `None` if it did not exist
+    pub balance: Option<Amount>,
+    /// bytecode of the entry, `None` if it did not exist
+    pub bytecode: Option<Vec<u8>>,
+    /// requested datastore key
+    pub datastore_key: Option<Hash>,
+    /// value of the requested datastore key, `None` if it did not exist
+    pub datastore_value: Option<Vec<u8>>,
+}
+
+impl std::fmt::Display for HistoricalLedgerEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Address: {}", self.address)?;
+        writeln!(f, "Final slot: {}", self.slot)?;
+        match self.balance {
+            Some(balance) => writeln!(f, "\tBalance: {}", balance)?,
+            None => writeln!(f, "\tEntry not found")?,
+        }
+        if let Some(datastore_key) = self.datastore_key {
+            match &self.datastore_value {
+                Some(value) => writeln!(f, "\tDatastore {}: {:?}", datastore_key, value)?,
+                None => writeln!(f, "\tDatastore {}: not found", datastore_key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Request of the proofs of the final values of a ledger entry
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LedgerEntryProofRequest {
+    /// address of the entry
+    pub address: Address,
+    /// optional datastore key whose value is also proven
+    pub datastore_key: Option<Hash>,
+    /// recent final slot at the output of which the values are proven, `None` for the last final slot
+    pub slot: Option<Slot>,
+}
+
+/// Final values of a ledger entry, with proofs against the state root of the final slot they are read at
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LedgerEntryProof {
+    /// address of the entry
+    pub address: Address,
+    /// final slot at the output of which the values are read
+    pub slot: Slot,
+    /// root hash of the final state tree at the output of `slot`
+    pub state_root: Hash,
+    /// parallel balance, `None` if the entry does not exist
+    pub balance: Option<Amount>,
+    /// proof of `balance`
+    pub balance_proof: StateProof,
+    /// hash of the executable bytecode, `None` if the entry does not exist
+    pub bytecode_hash: Option<Hash>,
+    /// proof of `bytecode_hash`
+    pub bytecode_proof: StateProof,
+    /// requested datastore key, if any
+    pub datastore_key: Option<Hash>,
+    /// value of the requested datastore key, `None` if it is absent
+    pub datastore_value: Option<Vec<u8>>,
+    /// proof of `datastore_value`, present if a datastore key was requested
+    pub datastore_proof: Option<StateProof>,
+}
+
+impl std::fmt::Display for LedgerEntryProof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Address: {}", self.address)?;
+        writeln!(f, "Final slot: {}", self.slot)?;
+        writeln!(f, "State root: {}", self.state_root)?;
+        match self.balance {
+            Some(balance) => writeln!(f, "\tBalance: {}", balance)?,
+            None => writeln!(f, "\tEntry not found")?,
+        }
+        if let Some(bytecode_hash) = self.bytecode_hash {
+            writeln!(f, "\tBytecode hash: {}", bytecode_hash)?;
+        }
+        if let Some(datastore_key) = self.datastore_key {
+            match &self.datastore_value {
+                Some(value) => writeln!(f, "\tDatastore {}: {:?}", datastore_key, value)?,
+                None => writeln!(f, "\tDatastore {}: not found", datastore_key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Root hash of the final state tree at the output of a final slot
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FinalStateRoot {
+    /// final slot
+    pub slot: Slot,
+    /// root hash of the final state tree at the output of `slot`,
+    /// `None` if the slot is not final yet or too old to be remembered by the node
+    pub state_root: Option<Hash>,
+}
+
+impl std::fmt::Display for FinalStateRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.state_root {
+            Some(state_root) => writeln!(f, "State root at slot {}: {}", self.slot, state_root),
+            None => writeln!(f, "State root at slot {}: not available", self.slot),
+        }
+    }
+}
+
 /// All you ever dream to know about an address
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AddressInfo {
@@ -492,6 +764,10 @@ pub struct EventFilter {
     pub original_caller_address: Option<Address>,
     /// optional operation id
     pub original_operation_id: Option<OperationId>,
+    /// optional asynchronous message ID, to get the outcome of a message
+    pub async_message_id: Option<String>,
+    /// optional asynchronous message status, to get the outcomes of messages with that status
+    pub async_message_status: Option<AsyncMessageStatus>,
 }
 
 /// read only bytecode execution request
@@ -505,6 +781,15 @@ pub struct ReadOnlyBytecodeExecution {
     pub bytecode: Vec<u8>,
     /// caller's address, optional
     pub address: Option<Address>,
+    /// record a trace of the execution, for debugging purposes
+    #[serde(default)]
+    pub trace: bool,
+    /// temporary changes of the state, for this execution only
+    #[serde(default)]
+    pub overrides: ReadOnlyStateOverrides,
+    /// state on top of which the execution happens, the latest candidate one by default
+    #[serde(default)]
+    pub base_state: ReadOnlyBaseState,
 }
 
 /// read SC call request
@@ -522,4 +807,94 @@ pub struct ReadOnlyCall {
     pub parameter: String,
     /// caller's address, optional
     pub caller_address: Option<Address>,
+    /// coins sent with the call, credited to the target before running the function
+    #[serde(default)]
+    pub coins: Amount,
+    /// record a trace of the execution, for debugging purposes
+    #[serde(default)]
+    pub trace: bool,
+    /// temporary changes of the state, for this execution only
+    #[serde(default)]
+    pub overrides: ReadOnlyStateOverrides,
+    /// state on top of which the execution happens, the latest candidate one by default
+    #[serde(default)]
+    pub base_state: ReadOnlyBaseState,
+}
+
+/// State on top of which a read-only execution happens
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadOnlyBaseState {
+    /// the state after the latest executed slot of the blockclique, which can be rolled back
+    #[default]
+    Candidate,
+    /// the latest final state, which cannot be rolled back
+    Final,
+    /// the state after a given slot, between the latest final slot and the latest executed one
+    AfterSlot(Slot),
+}
+
+/// Temporary changes of the state applied before a read-only execution, and discarded after it
+#[derive(Debug, Default, Deserialize, Clone, Serialize)]
+pub struct ReadOnlyStateOverrides {
+    /// slot at which the execution is simulated, which also sets the time seen by the bytecode.
+    /// The state is still the latest one.
+    pub slot: Option<Slot>,
+    /// overridden ledger entries
+    pub addresses: Vec<AddressStateOverride>,
+}
+
+/// Temporary changes of a ledger entry for a read-only execution.
+/// The entry is created if it does not exist.
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct AddressStateOverride {
+    /// overridden address
+    pub address: Address,
+    /// parallel balance to set, optional
+    pub balance: Option<Amount>,
+    /// bytecode to set, optional
+    pub bytecode: Option<Vec<u8>>,
+    /// datastore entries to set, or to delete if their value is null, indexed by their string key
+    #[serde(default)]
+    pub datastore: BTreeMap<String, Option<Vec<u8>>>,
+}
+
+/// Read-only execution of a multicall batch
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub enum ReadOnlyExecution {
+    /// read-only bytecode execution
+    BytecodeExecution(ReadOnlyBytecodeExecution),
+    /// read-only smart contract call
+    Call(ReadOnlyCall),
+}
+
+/// Operation whose smallest sufficient `max_gas` is estimated.
+/// The `max_gas` of the request is the largest value tried.
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub enum GasEstimationRequest {
+    /// `ExecuteSC` operation, simulated by a read-only bytecode execution
+    ExecuteSC(ReadOnlyBytecodeExecution),
+    /// `CallSC` operation, simulated by a read-only call
+    CallSC(ReadOnlyCall),
+}
+
+/// Result of a gas estimation
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct GasEstimation {
+    /// smallest `max_gas` at which the execution succeeds, `None` if it fails with the requested `max_gas`
+    pub max_gas: Option<u64>,
+    /// error of the execution with the requested `max_gas`, if any
+    pub error: Option<String>,
+}
+
+impl std::fmt::Display for GasEstimation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(max_gas) = self.max_gas {
+            writeln!(f, "Estimated max gas: {}", max_gas)?;
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "Execution failed: {}", error)?;
+        }
+        Ok(())
+    }
 }
@@ -493,6 +493,7 @@ mod test {
             max_bootstrap_children: 100,
             max_bootstrap_pos_cycles: 1000,
             max_bootstrap_pos_entries: 1000,
+            max_bootstrap_final_state_changes: 1000,
             max_ask_blocks_per_message: 10,
             max_operations_per_message: 1024,
             max_endorsements_per_message: 1024,
@@ -1,6 +1,6 @@
 use std::{collections::VecDeque, fmt::Display};
 
-use crate::{output_event::SCOutputEvent, Slot};
+use crate::{output_event::SCOutputEvent, Address, Amount, BlockId, OperationId, Slot};
 use serde::{Deserialize, Serialize};
 
 /// The result of the read-only execution.
@@ -22,6 +22,10 @@ pub struct ExecuteReadOnlyResponse {
     pub result: ReadOnlyResult,
     /// The output events generated by the read-only execution.
     pub output_events: VecDeque<SCOutputEvent>,
+    /// The gas used by the read-only execution, 0 if it failed.
+    pub gas_used: u64,
+    /// The trace of the execution, if it was requested.
+    pub trace: Option<ExecutionTraceFrame>,
 }
 
 impl Display for ExecuteReadOnlyResponse {
@@ -36,6 +40,10 @@ impl Display for ExecuteReadOnlyResponse {
                 ReadOnlyResult::Ok => "ok".to_string(),
             }
         )?;
+        writeln!(f, "Gas used: {}", self.gas_used)?;
+        if let Some(trace) = &self.trace {
+            write!(f, "Trace:\n{}", trace)?;
+        }
         if !self.output_events.is_empty() {
             writeln!(f, "Generated events:",)?;
             for event in self.output_events.iter() {
@@ -45,3 +53,181 @@ impl Display for ExecuteReadOnlyResponse {
         Ok(())
     }
 }
+
+/// What was executed to produce an execution receipt
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ExecutionReceiptOrigin {
+    /// An `ExecuteSC` or `CallSC` operation.
+    Operation(OperationId),
+    /// An asynchronous message, identified by the textual representation of its ID.
+    AsyncMessage(String),
+}
+
+impl Display for ExecutionReceiptOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionReceiptOrigin::Operation(id) => write!(f, "operation {}", id),
+            ExecutionReceiptOrigin::AsyncMessage(id) => write!(f, "asynchronous message {}", id),
+        }
+    }
+}
+
+/// Summary of the execution of an operation or of an asynchronous message.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExecutionReceipt {
+    /// What was executed.
+    pub origin: ExecutionReceiptOrigin,
+    /// The slot at which the execution happened.
+    pub slot: Slot,
+    /// The block containing the operation, None for asynchronous messages executed in a miss.
+    pub block: Option<BlockId>,
+    /// Whether the execution succeeded. The effects of failed executions are reverted.
+    pub success: bool,
+    /// The reason of the failure, for failed executions.
+    pub error: Option<String>,
+    /// The gas used by the execution.
+    pub gas_used: u64,
+    /// The coins credited to the address the execution ran on, zero if that transfer was reverted.
+    pub coins: Amount,
+    /// The events emitted by the execution.
+    pub events: Vec<SCOutputEvent>,
+}
+
+impl Display for ExecutionReceipt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Execution of {} at slot {}", self.origin, self.slot)?;
+        if let Some(block) = self.block {
+            writeln!(f, "Block: {}", block)?;
+        }
+        match &self.error {
+            Some(err) => writeln!(f, "Result: failed: {}", err)?,
+            None => writeln!(f, "Result: success")?,
+        }
+        writeln!(f, "Gas used: {}", self.gas_used)?;
+        writeln!(f, "Coins: {}", self.coins)?;
+        if !self.events.is_empty() {
+            writeln!(f, "Generated events:")?;
+            for event in self.events.iter() {
+                writeln!(f, "{}", event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Call frame of a traced read-only execution.
+/// The root frame is the one of the executed bytecode or called function.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExecutionTraceFrame {
+    /// The address the frame runs on.
+    pub address: Address,
+    /// The coins transferred with the call.
+    pub coins: Amount,
+    /// What happened in the frame, in execution order.
+    pub steps: Vec<ExecutionTraceStep>,
+}
+
+impl ExecutionTraceFrame {
+    /// Writes the frame and its steps, indented according to the depth of the frame
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(
+            f,
+            "{}call {} with {} coins",
+            indent, self.address, self.coins
+        )?;
+        for step in self.steps.iter() {
+            match step {
+                ExecutionTraceStep::Call(frame) => frame.fmt_indented(f, depth + 1)?,
+                ExecutionTraceStep::DatastoreRead {
+                    address,
+                    key,
+                    value,
+                } => writeln!(f, "{}  read {}[{}]: {:?}", indent, address, key, value)?,
+                ExecutionTraceStep::DatastoreWrite {
+                    address,
+                    key,
+                    value,
+                } => writeln!(f, "{}  write {}[{}]: {:?}", indent, address, key, value)?,
+                ExecutionTraceStep::CoinTransfer { from, to, amount } => writeln!(
+                    f,
+                    "{}  transfer {} coins from {:?} to {:?}",
+                    indent, amount, from, to
+                )?,
+                ExecutionTraceStep::MessageSent {
+                    message_id,
+                    destination,
+                    handler,
+                    ..
+                } => writeln!(
+                    f,
+                    "{}  send message {} to {}::{}",
+                    indent, message_id, destination, handler
+                )?,
+                ExecutionTraceStep::Print { message } => {
+                    writeln!(f, "{}  print: {}", indent, message)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for ExecutionTraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Step of a traced read-only execution
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionTraceStep {
+    /// A nested call to another address.
+    Call(ExecutionTraceFrame),
+    /// A read of a datastore entry. `value` is None if the entry does not exist.
+    DatastoreRead {
+        /// The address owning the datastore.
+        address: Address,
+        /// The key of the entry.
+        key: String,
+        /// The value read.
+        value: Option<Vec<u8>>,
+    },
+    /// A write of a datastore entry.
+    DatastoreWrite {
+        /// The address owning the datastore.
+        address: Address,
+        /// The key of the entry.
+        key: String,
+        /// The value written.
+        value: Vec<u8>,
+    },
+    /// A transfer of parallel coins. A missing side means that coins were created or destroyed.
+    CoinTransfer {
+        /// The debited address.
+        from: Option<Address>,
+        /// The credited address.
+        to: Option<Address>,
+        /// The amount transferred.
+        amount: Amount,
+    },
+    /// An asynchronous message sent.
+    MessageSent {
+        /// The textual representation of the ID of the message.
+        message_id: String,
+        /// The destination of the message.
+        destination: Address,
+        /// The handler called on the destination.
+        handler: String,
+        /// The maximum gas of the message.
+        max_gas: u64,
+        /// The coins carried by the message.
+        coins: Amount,
+    },
+    /// A line printed by the bytecode.
+    Print {
+        /// The printed message.
+        message: String,
+    },
+}
@@ -62,6 +62,8 @@ mod serialization_context;
 pub mod signed;
 /// slots
 pub mod slot;
+/// proofs of final state items against a state root
+pub mod state_proof;
 /// various statistics
 pub mod stats;
 /// management of the relation between time and slots
@@ -131,6 +131,8 @@ pub const MAX_BOOTSTRAP_CHILDREN: u32 = 1000;
 pub const MAX_BOOTSTRAP_POS_CYCLES: u32 = 5;
 /// Max number of address and random entries for PoS bootstrap
 pub const MAX_BOOTSTRAP_POS_ENTRIES: u32 = 1000000000;
+/// Max number of slots in the final state changes sent at bootstrap
+pub const MAX_BOOTSTRAP_FINAL_STATE_CHANGES: u32 = 1000;
 /// Max size of the IP list
 pub const IP_LIST_MAX_SIZE: usize = 10000;
 /// Size of the random bytes array used for the bootstrap, safe to import
@@ -144,6 +146,10 @@ pub const BOOTSTRAP_RANDOMNESS_SIZE_BYTES: usize = 32;
 pub const MAX_GAS_PER_BLOCK: u64 = 100_000_000;
 /// Maximum of GAS allowed for asynchronous messages execution on one slot
 pub const MAX_ASYNC_GAS: u64 = 100_000_000;
+/// Maximum of GAS allowed for the asynchronous messages of a single sender on one slot
+pub const MAX_ASYNC_GAS_PER_SENDER: u64 = 25_000_000;
+/// Deposit locked for each byte stored in the ledger by a smart contract (0.0001 coin)
+pub const STORAGE_BYTE_COST: Amount = Amount::from_raw(AMOUNT_DECIMAL_FACTOR / 10_000);
 
 //
 // Constants used in network
@@ -107,10 +107,16 @@ pub const MAX_BOOTSTRAP_MESSAGE_SIZE: u32 = 100_000_000;
 pub const MAX_BOOTSTRAP_POS_ENTRIES: u32 = 1000;
 /// max bootstrapped proof of take cycles
 pub const MAX_BOOTSTRAP_POS_CYCLES: u32 = 5;
+/// max number of slots in the final state changes sent at bootstrap
+pub const MAX_BOOTSTRAP_FINAL_STATE_CHANGES: u32 = 100;
 /// max gas per block
 pub const MAX_GAS_PER_BLOCK: u64 = 100_000_000;
 /// max asynchronous gas
 pub const MAX_ASYNC_GAS: u64 = 10_000_000;
+/// max asynchronous gas per sender
+pub const MAX_ASYNC_GAS_PER_SENDER: u64 = 2_500_000;
+/// deposit locked per byte stored in the ledger
+pub const STORAGE_BYTE_COST: Amount = Amount::from_raw(1);
 /// max message size 3 * 1024 * 1024
 pub const MAX_MESSAGE_SIZE: u32 = 3145728;
 /// max number of operation per block
@@ -1,6 +1,6 @@
-use crate::{Address, BlockId, OperationId, Slot};
+use crate::{Address, Amount, BlockId, ModelsError, OperationId, Slot};
 use serde::{Deserialize, Serialize};
-use std::{collections::VecDeque, fmt::Display};
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// By product of a byte code execution
@@ -9,15 +9,90 @@ pub struct SCOutputEvent {
     pub context: EventExecutionContext,
     /// json data string
     pub data: String,
+    /// outcome of an asynchronous message, for the events generated by the node
+    /// when an asynchronous message leaves the pool
+    pub async_message_outcome: Option<AsyncMessageOutcome>,
 }
 
 impl Display for SCOutputEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Context: {}", self.context)?;
+        if let Some(outcome) = &self.async_message_outcome {
+            writeln!(f, "Asynchronous message outcome: {}", outcome)?;
+        }
         writeln!(f, "Data: {}", self.data)
     }
 }
 
+/// Way an asynchronous message left the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsyncMessageStatus {
+    /// the handler of the message was executed
+    Executed,
+    /// the message was taken for execution but its handler could not be run or failed
+    ExecutionFailed,
+    /// the validity period of the message ended before it was executed
+    Expired,
+    /// the message was removed from the full pool in favor of messages with a higher priority
+    Evicted,
+}
+
+impl Display for AsyncMessageStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsyncMessageStatus::Executed => write!(f, "executed"),
+            AsyncMessageStatus::ExecutionFailed => write!(f, "execution_failed"),
+            AsyncMessageStatus::Expired => write!(f, "expired"),
+            AsyncMessageStatus::Evicted => write!(f, "evicted"),
+        }
+    }
+}
+
+impl FromStr for AsyncMessageStatus {
+    type Err = ModelsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "executed" => Ok(AsyncMessageStatus::Executed),
+            "execution_failed" => Ok(AsyncMessageStatus::ExecutionFailed),
+            "expired" => Ok(AsyncMessageStatus::Expired),
+            "evicted" => Ok(AsyncMessageStatus::Evicted),
+            _ => Err(ModelsError::DeserializeError(format!(
+                "unknown asynchronous message status: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Outcome of an asynchronous message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncMessageOutcome {
+    /// ID of the message, in its textual representation
+    pub message_id: String,
+    /// way the message left the pool
+    pub status: AsyncMessageStatus,
+    /// reason of the failure, for failed executions
+    pub reason: Option<String>,
+    /// coins refunded to the sender of the message
+    pub refund: Amount,
+}
+
+impl Display for AsyncMessageOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "message {} {}, {} coins refunded",
+            self.message_id, self.status, self.refund
+        )?;
+        if let Some(reason) = &self.reason {
+            write!(f, ": {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
 /// Context of the event (not generated by the user)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventExecutionContext {
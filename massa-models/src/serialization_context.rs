@@ -87,6 +87,8 @@ pub struct SerializationContext {
     pub max_bootstrap_pos_entries: u32,
     /// max bootstrap message size
     pub max_bootstrap_message_size: u32,
+    /// max number of slots in bootstrapped final state changes
+    pub max_bootstrap_final_state_changes: u32,
 }
 #[cfg(not(feature = "sandbox"))]
 impl Default for SerializationContext {
@@ -122,6 +124,7 @@ impl Default for SerializationContext {
             max_bootstrap_message_size: MAX_BOOTSTRAP_MESSAGE_SIZE,
             max_bootstrap_pos_cycles: MAX_BOOTSTRAP_POS_CYCLES,
             max_bootstrap_pos_entries: MAX_BOOTSTRAP_POS_ENTRIES,
+            max_bootstrap_final_state_changes: MAX_BOOTSTRAP_FINAL_STATE_CHANGES,
         }
     }
 }
@@ -152,6 +155,7 @@ impl SerializationContext {
             max_bootstrap_message_size: MAX_BOOTSTRAP_MESSAGE_SIZE,
             max_bootstrap_pos_cycles: MAX_BOOTSTRAP_POS_CYCLES,
             max_bootstrap_pos_entries: MAX_BOOTSTRAP_POS_ENTRIES,
+            max_bootstrap_final_state_changes: MAX_BOOTSTRAP_FINAL_STATE_CHANGES,
         }
     }
 }
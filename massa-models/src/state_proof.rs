@@ -0,0 +1,284 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Proofs of the values of final state items against a state root.
+//!
+//! The final state is committed to by a sparse Merkle tree of depth `STATE_TREE_DEPTH`:
+//! each item is a leaf located at the path `Hash(leaf key)`, and the subtrees holding a single leaf are collapsed into it.
+//! This module defines the hashing rules of that tree, which are shared by the nodes building it
+//! and by the clients checking the proofs they receive.
+
+use crate::{Address, Amount};
+use massa_hash::{Hash, HASH_SIZE_BYTES};
+use serde::{Deserialize, Serialize};
+
+/// Depth of the state tree: there is one level per bit of the leaf paths
+pub const STATE_TREE_DEPTH: usize = HASH_SIZE_BYTES * 8;
+
+/// Leaf key prefix of the items of the ledger
+pub const LEDGER_LEAF_PREFIX: u8 = 0u8;
+/// Leaf key prefix of the messages of the asynchronous pool
+pub const ASYNC_POOL_LEAF_PREFIX: u8 = 1u8;
+
+/// Ledger item identifiers, following the address in the leaf keys
+const BALANCE_IDENT: u8 = 0u8;
+const BYTECODE_IDENT: u8 = 1u8;
+const DATASTORE_IDENT: u8 = 2u8;
+const STORAGE_DEPOSIT_IDENT: u8 = 3u8;
+
+/// Leaf key of the balance of a ledger entry
+pub fn balance_leaf_key(addr: &Address) -> Vec<u8> {
+    let mut key = vec![LEDGER_LEAF_PREFIX];
+    key.extend(addr.to_bytes());
+    key.push(BALANCE_IDENT);
+    key
+}
+
+/// Leaf key of the bytecode of a ledger entry
+pub fn bytecode_leaf_key(addr: &Address) -> Vec<u8> {
+    let mut key = vec![LEDGER_LEAF_PREFIX];
+    key.extend(addr.to_bytes());
+    key.push(BYTECODE_IDENT);
+    key
+}
+
+/// Leaf key of a datastore entry of a ledger entry
+pub fn data_leaf_key(addr: &Address, data_key: &Hash) -> Vec<u8> {
+    let mut key = vec![LEDGER_LEAF_PREFIX];
+    key.extend(addr.to_bytes());
+    key.push(DATASTORE_IDENT);
+    key.extend(data_key.to_bytes());
+    key
+}
+
+/// Leaf key of the storage deposit of a ledger entry, whose value is encoded like balances
+pub fn storage_deposit_leaf_key(addr: &Address) -> Vec<u8> {
+    let mut key = vec![LEDGER_LEAF_PREFIX];
+    key.extend(addr.to_bytes());
+    key.push(STORAGE_DEPOSIT_IDENT);
+    key
+}
+
+/// Value of the balance leaf of a ledger entry
+pub fn balance_leaf_value(balance: &Amount) -> Vec<u8> {
+    balance.to_raw().to_be_bytes().to_vec()
+}
+
+/// Hash of an empty subtree
+pub fn empty_subtree_hash() -> Hash {
+    Hash::from_bytes(&[0u8; HASH_SIZE_BYTES]).expect("invalid empty subtree hash")
+}
+
+/// Hash of a leaf, committing to both its path and the hash of its value
+pub fn leaf_hash(path: &Hash, value_hash: &Hash) -> Hash {
+    let mut data = vec![0u8];
+    data.extend(path.to_bytes());
+    data.extend(value_hash.to_bytes());
+    Hash::compute_from(&data)
+}
+
+/// Hash of a subtree holding several leaves, computed from the hashes of its children
+pub fn internal_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut data = vec![1u8];
+    data.extend(left.to_bytes());
+    data.extend(right.to_bytes());
+    Hash::compute_from(&data)
+}
+
+/// Bit of a path at a given depth, starting from the most significant bit.
+/// A set bit means that the path goes through the right child of the node at that depth.
+pub fn get_path_bit(path: &[u8; HASH_SIZE_BYTES], depth: usize) -> bool {
+    path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// Subtree at which the path of a proof ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum StateProofTerminal {
+    /// the subtree is empty: there is no item at the proven path
+    Empty,
+    /// the subtree holds a single leaf, which is the proven item if its path matches
+    Leaf {
+        /// path of the leaf
+        path: Hash,
+        /// hash of the leaf
+        hash: Hash,
+    },
+}
+
+/// Proof of the value (or the absence) of a final state item against a state root
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StateProof {
+    /// hashes of the siblings of the nodes on the path of the item, from the root down
+    pub siblings: Vec<Hash>,
+    /// subtree at which the path ends
+    pub terminal: StateProofTerminal,
+}
+
+impl StateProof {
+    /// Computes the state root implied by the proof for an item.
+    ///
+    /// # Arguments
+    /// * `key`: leaf key of the item
+    /// * `value_hash`: hash of the value of the item, `None` to prove that the item does not exist
+    ///
+    /// # Returns
+    /// The state root, or `None` if the proof is not consistent with the item
+    pub fn compute_root(&self, key: &[u8], value_hash: Option<&Hash>) -> Option<Hash> {
+        let depth = self.siblings.len();
+        if depth > STATE_TREE_DEPTH {
+            return None;
+        }
+        let path = Hash::compute_from(key);
+        let path_bytes = path.to_bytes();
+
+        // hash of the subtree at which the path ends
+        let mut hash = match (self.terminal, value_hash) {
+            (StateProofTerminal::Empty, None) => empty_subtree_hash(),
+            (
+                StateProofTerminal::Leaf {
+                    path: leaf_path, ..
+                },
+                Some(value_hash),
+            ) if leaf_path == path => leaf_hash(&path, value_hash),
+            // another item is alone in the subtree of the proven path
+            (
+                StateProofTerminal::Leaf {
+                    path: leaf_path,
+                    hash,
+                },
+                None,
+            ) if leaf_path != path
+                && (0..depth).all(|d| {
+                    get_path_bit(&leaf_path.to_bytes(), d) == get_path_bit(&path_bytes, d)
+                }) =>
+            {
+                hash
+            }
+            _ => return None,
+        };
+
+        // hash the path up to the root
+        for (d, sibling) in self.siblings.iter().enumerate().rev() {
+            hash = if get_path_bit(&path_bytes, d) {
+                internal_hash(sibling, &hash)
+            } else {
+                internal_hash(&hash, sibling)
+            };
+        }
+        Some(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Leaf of a key with a given value: path, leaf hash
+    fn make_leaf(key: &[u8], value: &[u8]) -> (Hash, Hash) {
+        let path = Hash::compute_from(key);
+        (path, leaf_hash(&path, &Hash::compute_from(value)))
+    }
+
+    /// Finds a key whose path starts with the given bit
+    fn key_with_first_bit(bit: bool, skip: usize) -> Vec<u8> {
+        (0u32..)
+            .map(|i| i.to_be_bytes().to_vec())
+            .filter(|key| get_path_bit(&Hash::compute_from(key).into_bytes(), 0) == bit)
+            .nth(skip)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_empty_tree_proof() {
+        let proof = StateProof {
+            siblings: Vec::new(),
+            terminal: StateProofTerminal::Empty,
+        };
+        assert_eq!(proof.compute_root(b"key", None), Some(empty_subtree_hash()));
+        assert_eq!(
+            proof.compute_root(b"key", Some(&Hash::compute_from(b"value"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_single_leaf_proof() {
+        let (path, hash) = make_leaf(b"key", b"value");
+        let proof = StateProof {
+            siblings: Vec::new(),
+            terminal: StateProofTerminal::Leaf { path, hash },
+        };
+        let value_hash = Hash::compute_from(b"value");
+        assert_eq!(proof.compute_root(b"key", Some(&value_hash)), Some(hash));
+
+        // a wrong value gives another root, and the item cannot be claimed absent
+        let wrong_hash = Hash::compute_from(b"wrong");
+        assert_ne!(proof.compute_root(b"key", Some(&wrong_hash)), Some(hash));
+        assert_eq!(proof.compute_root(b"key", None), None);
+
+        // the leaf alone in the tree proves the absence of any other key
+        assert_eq!(proof.compute_root(b"other", None), Some(hash));
+        assert_eq!(proof.compute_root(b"other", Some(&value_hash)), None);
+    }
+
+    #[test]
+    fn test_two_leaves_proof() {
+        let left_key = key_with_first_bit(false, 0);
+        let right_key = key_with_first_bit(true, 0);
+        let (left_path, left_hash) = make_leaf(&left_key, b"left");
+        let (right_path, right_hash) = make_leaf(&right_key, b"right");
+        let root = internal_hash(&left_hash, &right_hash);
+
+        // both leaves are proven against the root
+        let left_proof = StateProof {
+            siblings: vec![right_hash],
+            terminal: StateProofTerminal::Leaf {
+                path: left_path,
+                hash: left_hash,
+            },
+        };
+        let right_proof = StateProof {
+            siblings: vec![left_hash],
+            terminal: StateProofTerminal::Leaf {
+                path: right_path,
+                hash: right_hash,
+            },
+        };
+        assert_eq!(
+            left_proof.compute_root(&left_key, Some(&Hash::compute_from(b"left"))),
+            Some(root)
+        );
+        assert_eq!(
+            right_proof.compute_root(&right_key, Some(&Hash::compute_from(b"right"))),
+            Some(root)
+        );
+
+        // a tampered sibling gives another root
+        let mut tampered_proof = left_proof.clone();
+        tampered_proof.siblings[0] = Hash::compute_from(b"tampered");
+        assert_ne!(
+            tampered_proof.compute_root(&left_key, Some(&Hash::compute_from(b"left"))),
+            Some(root)
+        );
+
+        // an absent key is proven by the leaf alone in its subtree
+        let absent_key = key_with_first_bit(false, 1);
+        assert_eq!(left_proof.compute_root(&absent_key, None), Some(root));
+
+        // but not by a leaf located in another subtree
+        let absent_key = key_with_first_bit(true, 1);
+        assert_eq!(left_proof.compute_root(&absent_key, None), None);
+    }
+
+    #[test]
+    fn test_too_deep_proof() {
+        let (path, hash) = make_leaf(b"key", b"value");
+        let proof = StateProof {
+            siblings: vec![empty_subtree_hash(); STATE_TREE_DEPTH + 1],
+            terminal: StateProofTerminal::Leaf { path, hash },
+        };
+        assert_eq!(
+            proof.compute_root(b"key", Some(&Hash::compute_from(b"value"))),
+            None
+        );
+    }
+}
@@ -335,6 +335,7 @@ mod tests {
             max_endorsements_per_message: 1024,
             max_bootstrap_message_size: 100000000,
             max_bootstrap_pos_entries: 1000,
+            max_bootstrap_final_state_changes: 1000,
             max_bootstrap_pos_cycles: 5,
             endorsement_count: 8,
         };
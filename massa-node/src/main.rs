@@ -21,8 +21,9 @@ use massa_ledger::LedgerConfig;
 use massa_logging::massa_trace;
 use massa_models::{
     constants::{
-        END_TIMESTAMP, GENESIS_TIMESTAMP, MAX_ASYNC_GAS, MAX_ASYNC_POOL_LENGTH, MAX_GAS_PER_BLOCK,
-        OPERATION_VALIDITY_PERIODS, T0, THREAD_COUNT, VERSION,
+        END_TIMESTAMP, GENESIS_TIMESTAMP, MAX_ASYNC_GAS, MAX_ASYNC_GAS_PER_SENDER,
+        MAX_ASYNC_POOL_LENGTH, MAX_GAS_PER_BLOCK, OPERATION_VALIDITY_PERIODS, STORAGE_BYTE_COST,
+        T0, THREAD_COUNT, VERSION,
     },
     init_serialization_context, SerializationContext,
 };
@@ -34,7 +35,8 @@ use massa_protocol_worker::start_protocol_controller;
 use massa_storage::Storage;
 use massa_time::MassaTime;
 use parking_lot::RwLock;
-use std::{process, sync::Arc};
+use std::{path::PathBuf, process, sync::Arc};
+use structopt::StructOpt;
 use tokio::signal;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
@@ -43,7 +45,100 @@ use tracing_subscriber::filter::{filter_fn, LevelFilter};
 
 mod settings;
 
-async fn launch() -> (
+/// Command line arguments of the node
+#[derive(StructOpt)]
+struct Args {
+    /// Maintenance command to run instead of starting the node
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+/// Maintenance commands, to run while the node is stopped
+#[derive(StructOpt)]
+enum Command {
+    /// Write the final state saved on disk to a snapshot file
+    ExportSnapshot {
+        /// path of the snapshot file to write
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Replace the final state saved on disk by the content of a snapshot file.
+    /// At its next run, the node starts from that final state instead of the initial ledger.
+    ImportSnapshot {
+        /// path of the snapshot file to read
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+}
+
+/// Gets the final state configuration from the node settings
+fn get_final_state_config() -> FinalStateConfig {
+    #[cfg(not(feature = "sandbox"))]
+    let thread_count = THREAD_COUNT;
+    #[cfg(feature = "sandbox")]
+    let thread_count = *THREAD_COUNT;
+
+    let ledger_config = LedgerConfig {
+        initial_sce_ledger_path: SETTINGS.ledger.initial_sce_ledger_path.clone(),
+        disk_ledger_path: SETTINGS.ledger.disk_ledger_path.clone(),
+        ledger_cache_capacity: SETTINGS.ledger.ledger_cache_capacity,
+    };
+    let async_pool_config = AsyncPoolConfig {
+        max_length: MAX_ASYNC_POOL_LENGTH,
+    };
+    FinalStateConfig {
+        final_history_length: SETTINGS.ledger.final_history_length,
+        thread_count,
+        ledger_config,
+        async_pool_config,
+    }
+}
+
+/// Loads the final state saved on disk by a previous run if any,
+/// otherwise initializes it from the initial ledger
+fn load_or_init_final_state(final_state_config: FinalStateConfig) -> FinalState {
+    let (final_state, load_err) =
+        FinalState::load_or_new(final_state_config).expect("could not init final state");
+    match load_err {
+        None => info!("final state loaded from disk at slot {}", final_state.slot),
+        Some(err) => info!("no final state loaded from disk: {}", err),
+    }
+    final_state
+}
+
+/// Runs a maintenance command on the final state saved on disk
+fn run_command(command: Command) {
+    init_serialization_context(SerializationContext::default());
+    match command {
+        Command::ExportSnapshot { path } => {
+            let final_state = FinalState::load(get_final_state_config())
+                .expect("could not load the final state saved on disk");
+            let slot = final_state
+                .export_snapshot(&path)
+                .expect("could not export the final state");
+            info!(
+                "final state at slot {} exported to {}",
+                slot,
+                path.display()
+            );
+        }
+        Command::ImportSnapshot { path } => {
+            let mut final_state = load_or_init_final_state(get_final_state_config());
+            let slot = final_state
+                .import_snapshot(&path)
+                .expect("could not import the final state");
+            info!(
+                "final state at slot {} imported from {}",
+                slot,
+                path.display()
+            );
+        }
+    }
+}
+
+async fn launch(
+    final_state: Arc<RwLock<FinalState>>,
+) -> (
     PoolCommandSender,
     ConsensusEventReceiver,
     ConsensusCommandSender,
@@ -68,8 +163,14 @@ async fn launch() -> (
     // Storage shared by multiple components.
     let shared_storage: Storage = Default::default();
 
-    // Init the global serialization context
-    init_serialization_context(SerializationContext::default());
+    #[cfg(not(feature = "sandbox"))]
+    let thread_count = THREAD_COUNT;
+    #[cfg(not(feature = "sandbox"))]
+    let t0 = T0;
+    #[cfg(feature = "sandbox")]
+    let thread_count = *THREAD_COUNT;
+    #[cfg(feature = "sandbox")]
+    let t0 = *T0;
 
     // interrupt signal listener
     let stop_signal = signal::ctrl_c();
@@ -81,6 +182,7 @@ async fn launch() -> (
         },
         res = get_state(
             &SETTINGS.bootstrap,
+            final_state.clone(),
             massa_bootstrap::types::Establisher::new(),
             *VERSION,
             *GENESIS_TIMESTAMP,
@@ -130,33 +232,6 @@ async fn launch() -> (
     .await
     .expect("could not start pool controller");
 
-    #[cfg(not(feature = "sandbox"))]
-    let thread_count = THREAD_COUNT;
-    #[cfg(not(feature = "sandbox"))]
-    let t0 = T0;
-    #[cfg(feature = "sandbox")]
-    let thread_count = *THREAD_COUNT;
-    #[cfg(feature = "sandbox")]
-    let t0 = *T0;
-
-    // init final state
-    let ledger_config = LedgerConfig {
-        initial_sce_ledger_path: SETTINGS.ledger.initial_sce_ledger_path.clone(),
-    };
-    let async_pool_config = AsyncPoolConfig {
-        max_length: MAX_ASYNC_POOL_LENGTH,
-    };
-    let final_state_config = FinalStateConfig {
-        final_history_length: SETTINGS.ledger.final_history_length,
-        thread_count,
-        ledger_config,
-        async_pool_config,
-    };
-    let final_state = Arc::new(RwLock::new(match bootstrap_state.final_state {
-        Some(l) => FinalState::from_bootstrap_state(final_state_config, l),
-        None => FinalState::new(final_state_config).expect("could not init final state"),
-    }));
-
     // launch execution module
     let execution_config = ExecutionConfig {
         max_final_events: SETTINGS.execution.max_final_events,
@@ -164,6 +239,8 @@ async fn launch() -> (
         cursor_delay: SETTINGS.execution.cursor_delay,
         clock_compensation: bootstrap_state.compensation_millis,
         max_async_gas: MAX_ASYNC_GAS,
+        max_async_gas_per_sender: MAX_ASYNC_GAS_PER_SENDER,
+        storage_byte_cost: STORAGE_BYTE_COST,
         thread_count,
         t0,
         genesis_timestamp: *GENESIS_TIMESTAMP,
@@ -340,6 +417,21 @@ async fn main() {
         .with(tracing_layer)
         .init();
 
+    // run a maintenance command instead of the node if one was provided
+    if let Some(command) = Args::from_args().command {
+        run_command(command);
+        return;
+    }
+
+    // Init the global serialization context
+    init_serialization_context(SerializationContext::default());
+
+    // init final state from the final state saved on disk by a previous run if any,
+    // the bootstrap then brings it up to date. It is opened once and shared across restarts.
+    let final_state = Arc::new(RwLock::new(load_or_init_final_state(
+        get_final_state_config(),
+    )));
+
     // run
     loop {
         let (
@@ -356,7 +448,7 @@ async fn main() {
             mut api_private_stop_rx,
             api_private_handle,
             api_public_handle,
-        ) = launch().await;
+        ) = launch(final_state.clone()).await;
 
         // interrupt signal listener
         let stop_signal = signal::ctrl_c();
@@ -48,6 +48,8 @@ pub struct ExecutionSettings {
 #[derive(Clone, Debug, Deserialize)]
 pub struct LedgerSettings {
     pub initial_sce_ledger_path: PathBuf,
+    pub disk_ledger_path: PathBuf,
+    pub ledger_cache_capacity: usize,
     pub final_history_length: usize,
 }
 
@@ -7,20 +7,26 @@
 use jsonrpc_core_client::transports::http;
 use jsonrpc_core_client::{RpcChannel, RpcError, RpcResult, TypedClient};
 use massa_models::api::{
-    AddressInfo, BlockInfo, BlockSummary, EndorsementInfo, EventFilter, NodeStatus, OperationInfo,
-    ReadOnlyBytecodeExecution, ReadOnlyCall, TimeInterval,
+    AddressInfo, AsyncMessageFilter, AsyncMessagesInfo, BlockInfo, BlockSummary, DatastoreKeysInfo,
+    DatastoreKeysRequest, EndorsementInfo, EventFilter, FinalStateRoot, GasEstimation,
+    GasEstimationRequest, HistoricalLedgerEntry, HistoricalLedgerEntryRequest, LedgerEntryProof,
+    LedgerEntryProofRequest, NodeStatus, OperationInfo, ReadOnlyBytecodeExecution, ReadOnlyCall,
+    ReadOnlyExecution, TimeInterval,
 };
 use massa_models::clique::Clique;
 use massa_models::composite::PubkeySig;
 use massa_models::execution::ExecuteReadOnlyResponse;
 use massa_models::output_event::SCOutputEvent;
 use massa_models::prehash::{Map, Set};
-use massa_models::{Address, BlockId, EndorsementId, OperationId, SignedOperation};
+use massa_models::{Address, BlockId, EndorsementId, OperationId, SignedOperation, Slot};
 use massa_signature::PrivateKey;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::net::{IpAddr, SocketAddr};
 
+mod proof;
+pub use proof::verify_ledger_entry_proof;
+
 /// Client
 pub struct Client {
     /// public component
@@ -219,6 +225,61 @@ impl RpcClient {
             .await
     }
 
+    /// List the datastore keys of addresses, optionally filtered by prefix and paginated
+    pub async fn get_datastore_keys(
+        &self,
+        requests: Vec<DatastoreKeysRequest>,
+    ) -> RpcResult<Vec<DatastoreKeysInfo>> {
+        self.call_method(
+            "get_datastore_keys",
+            "Vec<DatastoreKeysInfo>",
+            vec![requests],
+        )
+        .await
+    }
+
+    /// List the pending asynchronous messages matching a filter
+    pub async fn get_async_messages(
+        &self,
+        filter: AsyncMessageFilter,
+    ) -> RpcResult<AsyncMessagesInfo> {
+        self.call_method("get_async_messages", "AsyncMessagesInfo", vec![filter])
+            .await
+    }
+
+    /// Get final ledger entry values at the output of recent final slots
+    pub async fn get_historical_ledger_entries(
+        &self,
+        requests: Vec<HistoricalLedgerEntryRequest>,
+    ) -> RpcResult<Vec<HistoricalLedgerEntry>> {
+        self.call_method(
+            "get_historical_ledger_entries",
+            "Vec<HistoricalLedgerEntry>",
+            vec![requests],
+        )
+        .await
+    }
+
+    /// Get final ledger entry values along with proofs against the final state root.
+    /// The proofs can be checked with `verify_ledger_entry_proof`.
+    pub async fn get_ledger_entry_proofs(
+        &self,
+        requests: Vec<LedgerEntryProofRequest>,
+    ) -> RpcResult<Vec<LedgerEntryProof>> {
+        self.call_method(
+            "get_ledger_entry_proofs",
+            "Vec<LedgerEntryProof>",
+            vec![requests],
+        )
+        .await
+    }
+
+    /// Get the root hashes of the final state tree at the output of recent final slots
+    pub async fn get_final_state_roots(&self, slots: Vec<Slot>) -> RpcResult<Vec<FinalStateRoot>> {
+        self.call_method("get_final_state_roots", "Vec<FinalStateRoot>", vec![slots])
+            .await
+    }
+
     // User (interaction with the node)
 
     /// Adds operations to pool. Returns operations that were ok and sent to pool.
@@ -261,4 +322,29 @@ impl RpcClient {
         .pop()
         .ok_or_else(|| RpcError::Client("missing return value on execute_read_only_call".into()))
     }
+
+    /// execute a batch of read only bytecode executions and SC calls on the same state
+    pub async fn execute_read_only_multicall(
+        &self,
+        read_only_executions: Vec<ReadOnlyExecution>,
+    ) -> RpcResult<Vec<ExecuteReadOnlyResponse>> {
+        self.call_method(
+            "execute_read_only_multicall",
+            "Vec<ExecuteReadOnlyResponse>",
+            vec![read_only_executions],
+        )
+        .await
+    }
+
+    /// estimate the smallest `max_gas` at which an `ExecuteSC` or `CallSC` operation succeeds
+    pub async fn estimate_gas(&self, request: GasEstimationRequest) -> RpcResult<GasEstimation> {
+        self.call_method::<Vec<Vec<GasEstimationRequest>>, Vec<GasEstimation>>(
+            "estimate_gas",
+            "Vec<GasEstimation>",
+            vec![vec![request]],
+        )
+        .await?
+        .pop()
+        .ok_or_else(|| RpcError::Client("missing return value on estimate_gas".into()))
+    }
 }
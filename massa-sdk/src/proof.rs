@@ -0,0 +1,160 @@
+// Copyright (c) 2022 MASSA LABS <info@massa.net>
+
+//! Verification of the proofs of final state values returned by a node
+
+use massa_hash::Hash;
+use massa_models::api::LedgerEntryProof;
+use massa_models::state_proof::{
+    balance_leaf_key, balance_leaf_value, bytecode_leaf_key, data_leaf_key,
+};
+
+/// Checks the values of a `LedgerEntryProof` against a trusted state root.
+///
+/// The node that built the proof does not need to be trusted,
+/// but `state_root` must be the root of the final state at the output of `proof.slot`
+/// obtained from a trusted source (for example several independent nodes).
+/// Note that `proof.state_root` is not used: it only tells which root the node claims.
+///
+/// # Returns
+/// `true` if the balance, the bytecode hash and the requested datastore value (if any)
+/// are the ones of the final state committed to by `state_root`
+pub fn verify_ledger_entry_proof(proof: &LedgerEntryProof, state_root: &Hash) -> bool {
+    let address = &proof.address;
+
+    // balance
+    let balance_hash = proof
+        .balance
+        .map(|balance| Hash::compute_from(&balance_leaf_value(&balance)));
+    if proof
+        .balance_proof
+        .compute_root(&balance_leaf_key(address), balance_hash.as_ref())
+        != Some(*state_root)
+    {
+        return false;
+    }
+
+    // bytecode
+    if proof
+        .bytecode_proof
+        .compute_root(&bytecode_leaf_key(address), proof.bytecode_hash.as_ref())
+        != Some(*state_root)
+    {
+        return false;
+    }
+
+    // datastore value
+    match (&proof.datastore_key, &proof.datastore_proof) {
+        (Some(key), Some(datastore_proof)) => {
+            let value_hash = proof
+                .datastore_value
+                .as_ref()
+                .map(|value| Hash::compute_from(value));
+            datastore_proof.compute_root(&data_leaf_key(address, key), value_hash.as_ref())
+                == Some(*state_root)
+        }
+        (None, None) => proof.datastore_value.is_none(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use massa_async_pool::AsyncPoolConfig;
+    use massa_final_state::{FinalState, FinalStateConfig, StateChanges};
+    use massa_ledger::{LedgerConfig, LedgerEntry, SetUpdateOrDelete};
+    use massa_models::{Address, Amount, Slot};
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_verify_ledger_entry_proof() {
+        massa_models::init_serialization_context(massa_models::SerializationContext::default());
+        let (ledger_config, _keep_file, _keep_dir) = LedgerConfig::sample(&BTreeMap::new());
+        let config = FinalStateConfig {
+            ledger_config,
+            async_pool_config: AsyncPoolConfig { max_length: 10 },
+            final_history_length: 10,
+            thread_count: 2,
+        };
+        let mut final_state = FinalState::new(config).unwrap();
+        let addr = Address::from_bytes(&[1u8; 32]).unwrap();
+        let key = Hash::compute_from(b"key");
+
+        // create a few entries, then update one of them
+        let mut changes = StateChanges::default();
+        for i in 1..=5u8 {
+            changes.ledger_changes.0.insert(
+                Address::from_bytes(&[i; 32]).unwrap(),
+                SetUpdateOrDelete::Set(LedgerEntry {
+                    parallel_balance: Amount::from_str("3").unwrap(),
+                    bytecode: vec![i],
+                    datastore: BTreeMap::from([(key, vec![i])]),
+                    ..Default::default()
+                }),
+            );
+        }
+        final_state.finalize(Slot::new(1, 0), changes);
+        let mut changes = StateChanges::default();
+        changes.ledger_changes.set_data_entry(addr, key, vec![9]);
+        final_state.finalize(Slot::new(1, 1), changes);
+        let state_root = final_state.get_state_root();
+
+        // existing entry and datastore key
+        let proof = final_state
+            .get_ledger_entry_proof(&addr, Some(key), None)
+            .unwrap();
+        assert_eq!(proof.datastore_value, Some(vec![9]));
+        assert!(verify_ledger_entry_proof(&proof, &state_root));
+
+        // tampered values or root
+        let mut tampered = proof.clone();
+        tampered.balance = Some(Amount::from_str("4").unwrap());
+        assert!(!verify_ledger_entry_proof(&tampered, &state_root));
+        let mut tampered = proof.clone();
+        tampered.bytecode_hash = None;
+        assert!(!verify_ledger_entry_proof(&tampered, &state_root));
+        let mut tampered = proof.clone();
+        tampered.datastore_value = Some(vec![1]);
+        assert!(!verify_ledger_entry_proof(&tampered, &state_root));
+        let mut tampered = proof.clone();
+        tampered.datastore_value = None;
+        assert!(!verify_ledger_entry_proof(&tampered, &state_root));
+        let mut tampered = proof.clone();
+        tampered.datastore_proof = None;
+        assert!(!verify_ledger_entry_proof(&tampered, &state_root));
+        assert!(!verify_ledger_entry_proof(
+            &proof,
+            &final_state.get_state_root_at(Slot::new(1, 0)).unwrap()
+        ));
+
+        // absent datastore key and absent entry
+        let absent_key = Hash::compute_from(b"absent");
+        let proof = final_state
+            .get_ledger_entry_proof(&addr, Some(absent_key), None)
+            .unwrap();
+        assert_eq!(proof.datastore_value, None);
+        assert!(verify_ledger_entry_proof(&proof, &state_root));
+        let absent_addr = Address::from_bytes(&[9u8; 32]).unwrap();
+        let proof = final_state
+            .get_ledger_entry_proof(&absent_addr, Some(key), None)
+            .unwrap();
+        assert_eq!(proof.balance, None);
+        assert!(verify_ledger_entry_proof(&proof, &state_root));
+        let mut tampered = proof;
+        tampered.balance = Some(Amount::default());
+        assert!(!verify_ledger_entry_proof(&tampered, &state_root));
+
+        // proof at the output of a past final slot
+        let slot = Slot::new(1, 0);
+        let proof = final_state
+            .get_ledger_entry_proof(&addr, Some(key), Some(slot))
+            .unwrap();
+        assert_eq!(proof.datastore_value, Some(vec![1]));
+        assert!(verify_ledger_entry_proof(
+            &proof,
+            &final_state.get_state_root_at(slot).unwrap()
+        ));
+        assert!(!verify_ledger_entry_proof(&proof, &state_root));
+    }
+}